@@ -0,0 +1,101 @@
+use std::fmt;
+
+use error::EngineError;
+
+/// Why a search ended before its limits were reached, see
+/// [`Engine::last_abort`].
+///
+/// [`Engine::last_abort`]: struct.Engine.html#method.last_abort
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AbortReason {
+    /// The search was stopped on request, with [`Engine::stop`] or an
+    /// [`Interrupter`].
+    ///
+    /// [`Engine::stop`]: struct.Engine.html#method.stop
+    /// [`Interrupter`]: struct.Interrupter.html
+    Stopped,
+
+    /// The engine was still searching at the [`hard_limit`], and was stopped
+    /// or killed.
+    ///
+    /// [`hard_limit`]: struct.Engine.html#method.hard_limit
+    Deadline,
+
+    /// The engine process exited or its pipes broke during the search.
+    EngineDied,
+
+    /// A new position was sent during the search, so its result is about
+    /// the previous one.
+    Superseded,
+}
+
+impl AbortReason {
+    /// The reason for a search failing with `error`, if it's about the
+    /// search being cut short.
+    pub(crate) fn of(error: &EngineError) -> Option<AbortReason> {
+        match *error.unlabelled() {
+            EngineError::Timeout(..) => Some(AbortReason::Deadline),
+            EngineError::ProcessExited(..) | EngineError::Io(..) => Some(AbortReason::EngineDied),
+            _ => None,
+        }
+    }
+
+    /// Whether the search ended because of a failure rather than a decision
+    /// of the caller, so that retrying it may give another outcome.
+    pub fn is_failure(&self) -> bool {
+        match *self {
+            AbortReason::Deadline | AbortReason::EngineDied => true,
+            AbortReason::Stopped | AbortReason::Superseded => false,
+        }
+    }
+}
+
+impl fmt::Display for AbortReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AbortReason::Stopped => write!(f, "stop requested"),
+            AbortReason::Deadline => write!(f, "deadline reached"),
+            AbortReason::EngineDied => write!(f, "engine died"),
+            AbortReason::Superseded => write!(f, "position superseded"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+    use Engine;
+
+    #[test]
+    fn test_abort_reasons() {
+        let engine = Engine::new("./stockfish").unwrap();
+        engine.bestmove().unwrap();
+        assert_eq!(engine.last_abort(), None);
+
+        engine.go_infinite().unwrap();
+        engine.stop().unwrap();
+        assert_eq!(engine.last_abort(), Some(AbortReason::Stopped));
+
+        engine.go_infinite().unwrap();
+        engine.make_moves(&["e2e4".to_string()]).unwrap();
+        engine.stop().unwrap();
+        assert_eq!(engine.last_abort(), Some(AbortReason::Superseded));
+
+        let interrupter = engine.interrupter();
+        let stopper = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            interrupter.interrupt().unwrap();
+        });
+        engine.search("infinite").unwrap();
+        stopper.join().unwrap();
+        assert_eq!(engine.last_abort(), Some(AbortReason::Stopped));
+
+        let engine = engine.hard_limit(Some(Duration::from_millis(100)));
+        engine.search("infinite").unwrap();
+        assert_eq!(engine.last_abort(), Some(AbortReason::Deadline));
+        assert!(AbortReason::Deadline.is_failure());
+    }
+}
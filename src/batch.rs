@@ -0,0 +1,233 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use error::Result;
+use info::InfoRef;
+use score::Score;
+use {best_of, Engine};
+
+/// The engine's verdict on one position of a batch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PositionResult {
+    pub fen: String,
+    pub bestmove: String,
+
+    /// Score of the best move, from the point of view of the side to move.
+    pub score: Option<Score>,
+}
+
+impl PositionResult {
+    /// Picks the best move and its score out of the output of a search of
+    /// `fen`. Fails with `EngineError::NotFound` if the output has no best
+    /// move.
+    pub(crate) fn from_search(fen: &str, lines: &[String]) -> Result<PositionResult> {
+        let bestmove = best_of(lines)?;
+        let score = lines.iter()
+            .rev()
+            .filter_map(|line| InfoRef::parse(line))
+            .find(|info| info.score.is_some() && info.multipv.unwrap_or(1) == 1)
+            .and_then(|info| info.score);
+        Ok(PositionResult { fen: fen.to_string(), bestmove, score })
+    }
+
+    /// Formats the result as a line of a checkpoint file, fields separated
+    /// by tabs, which FENs and moves never contain.
+    fn to_record(&self) -> String {
+        let score = match self.score {
+            Some(Score::Cp(cp)) => format!("cp {}", cp),
+            Some(Score::Mate(n)) => format!("mate {}", n),
+            None => "-".to_string(),
+        };
+        format!("{}\t{}\t{}", self.fen, self.bestmove, score)
+    }
+
+    fn from_record(line: &str) -> Option<PositionResult> {
+        let mut fields = line.split('\t');
+        let (fen, bestmove, score) = (fields.next()?, fields.next()?, fields.next()?);
+        let mut words = score.split_whitespace();
+        let score = match (words.next(), words.next().and_then(|n| n.parse().ok())) {
+            (Some("cp"), Some(cp)) => Some(Score::Cp(cp)),
+            (Some("mate"), Some(n)) => Some(Score::Mate(n)),
+            _ => None,
+        };
+        Some(PositionResult { fen: fen.to_string(), bestmove: bestmove.to_string(), score })
+    }
+}
+
+/// Where a batch run saves its results as they complete, so an interrupted
+/// run resumes where it left off.
+///
+/// The file holds one line per analysed position, in order. It is flushed
+/// after every `interval` positions, so a crash loses at most that many.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+    path: PathBuf,
+    interval: usize,
+}
+
+impl Checkpoint {
+    /// Creates a checkpoint saved to `path` after every position.
+    pub fn new<P: AsRef<Path>>(path: P) -> Checkpoint {
+        Checkpoint { path: path.as_ref().to_path_buf(), interval: 1 }
+    }
+
+    /// Changes how many positions are analysed between two saves.
+    pub fn interval(mut self, positions: usize) -> Checkpoint {
+        self.interval = positions.max(1);
+        self
+    }
+
+    /// Reads the results saved so far. A missing file means nothing was
+    /// saved yet, and a truncated last line, one without its line
+    /// terminator, is ignored.
+    pub fn load(&self) -> Result<Vec<PositionResult>> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        let mut reader = BufReader::new(file);
+        let mut results = vec![];
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 0 {
+            let result = match line.strip_suffix('\n') {
+                Some(record) => PositionResult::from_record(record),
+                None => None,
+            };
+            match result {
+                Some(result) => results.push(result),
+                None => break,
+            }
+            line.clear();
+        }
+        Ok(results)
+    }
+
+    /// Replaces the checkpoint with `results`, through a temporary file so a
+    /// crash midway leaves the previous one intact, and opens it to append
+    /// the next ones.
+    fn rewrite(&self, results: &[PositionResult]) -> Result<File> {
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(".tmp");
+        let temp = PathBuf::from(temp);
+
+        let mut file = OpenOptions::new().create(true).write(true).truncate(true).open(&temp)?;
+        for result in results {
+            writeln!(file, "{}", result.to_record())?;
+        }
+        file.sync_data()?;
+        drop(file);
+        fs::rename(&temp, &self.path)?;
+
+        Ok(OpenOptions::new().append(true).open(&self.path)?)
+    }
+}
+
+/// Finds the best move of every position, resuming from the checkpoint if
+/// there is one.
+///
+/// Saved results are reused as long as their positions match the start of
+/// `positions`; the rest of the checkpoint is discarded and analysed again.
+/// Fails with [`EngineError::NotFound`] if a search ends without a best
+/// move, keeping the results saved up to there.
+///
+/// # Arguments
+///
+/// * `engine`     - The engine to analyse with.
+/// * `positions`  - FEN strings of the positions, in order.
+/// * `movetime`   - Search time for every position, in milliseconds.
+/// * `checkpoint` - Where to save the results, if anywhere.
+///
+/// # Examples
+///
+/// ```
+/// use uci::batch::{analyze_positions, Checkpoint};
+///
+/// let engine = uci::Engine::new("stockfish").unwrap();
+/// let positions = vec!["rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"];
+/// let checkpoint = Checkpoint::new(std::env::temp_dir().join("uci-batch-doctest.tsv"));
+///
+/// let results = analyze_positions(&engine, &positions, 100, Some(&checkpoint)).unwrap();
+/// assert_eq!(results.len(), 1);
+/// ```
+///
+/// [`EngineError::NotFound`]: ../enum.EngineError.html#variant.NotFound
+pub fn analyze_positions(engine: &Engine, positions: &[&str], movetime: u32,
+                         checkpoint: Option<&Checkpoint>) -> Result<Vec<PositionResult>> {
+    let mut results = match checkpoint {
+        Some(checkpoint) => checkpoint.load()?,
+        None => vec![],
+    };
+    let resumed = results.iter().zip(positions).take_while(|&(result, fen)| result.fen == *fen).count();
+    results.truncate(resumed);
+    if resumed > 0 {
+        info!("Resuming batch after {} positions", resumed);
+    }
+
+    // Rewrite what's kept, dropping whatever no longer matches.
+    let mut file = match checkpoint {
+        Some(checkpoint) => Some((checkpoint.rewrite(&results)?, checkpoint.interval)),
+        None => None,
+    };
+
+    for (i, fen) in positions.iter().enumerate().skip(resumed) {
+        engine.set_position(fen)?;
+        let lines = engine.search(&format!("movetime {}", movetime))?;
+        let result = PositionResult::from_search(fen, &lines)?;
+
+        if let Some((ref mut file, interval)) = file {
+            writeln!(file, "{}", result.to_record())?;
+            if (i + 1) % interval == 0 || i + 1 == positions.len() {
+                file.sync_data()?;
+            }
+        }
+        results.push(result);
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use error::EngineError;
+
+    #[test]
+    fn test_records() {
+        let result = PositionResult {
+            fen: "8/8/8/8/8/8/8/K1k5 w - - 0 1".to_string(),
+            bestmove: "a1a2".to_string(),
+            score: Some(Score::Mate(-3)),
+        };
+        assert_eq!(PositionResult::from_record(&result.to_record()), Some(result));
+
+        let unscored = PositionResult::from_record("fen\t(none)\t-").unwrap();
+        assert_eq!(unscored.score, None);
+        assert!(PositionResult::from_record("8/8/8/8/8/8/8/K1k5 w - - 0").is_none());
+    }
+
+    #[test]
+    fn test_search_without_bestmove() {
+        let lines = vec!["info depth 1 score cp 20 pv e2e4".to_string()];
+        assert!(matches!(PositionResult::from_search("fen", &lines), Err(EngineError::NotFound)));
+
+        let lines = vec!["info depth 1 score cp 20 pv e2e4".to_string(), "bestmove e2e4".to_string()];
+        let result = PositionResult::from_search("fen", &lines).unwrap();
+        assert_eq!((result.bestmove.as_str(), result.score), ("e2e4", Some(Score::Cp(20))));
+    }
+
+    #[test]
+    fn test_truncated_checkpoint() {
+        let path = std::env::temp_dir().join(format!("uci-batch-{}.tsv", std::process::id()));
+        fs::write(&path, "fen\ta1a2\tcp 23\nfen\ta1a2\tcp 2").unwrap();
+        let checkpoint = Checkpoint::new(&path);
+        let results = checkpoint.load().unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].score, Some(Score::Cp(23)));
+
+        checkpoint.rewrite(&results).unwrap();
+        assert_eq!(checkpoint.load().unwrap(), results);
+        fs::remove_file(&path).unwrap();
+    }
+}
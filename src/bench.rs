@@ -0,0 +1,269 @@
+use std::fmt::Write;
+
+use error::Result;
+use info::InfoRef;
+use {best_of, Engine};
+
+/// How long each engine searches every position of the suite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BenchLimit {
+    /// Search a fixed number of nodes. Reproducible across machines.
+    Nodes(u64),
+
+    /// Search for a fixed time, in milliseconds.
+    Movetime(u32),
+}
+
+impl BenchLimit {
+    fn go_args(self) -> String {
+        match self {
+            BenchLimit::Nodes(nodes) => format!("nodes {}", nodes),
+            BenchLimit::Movetime(ms) => format!("movetime {}", ms),
+        }
+    }
+}
+
+/// Whether engines keep their hash table from one position to the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashMode {
+    /// Start a new game, see [`Engine::new_game`], before every position.
+    /// Results don't depend on the order of the positions, the safe choice
+    /// for unrelated ones.
+    ///
+    /// [`Engine::new_game`]: ../struct.Engine.html#method.new_game
+    Cleared,
+
+    /// Start a new game only before the first position, so the engine
+    /// reuses its transposition table. Meant for consecutive positions of the
+    /// same game, where deeper searches are reached in the same limit.
+    Warm,
+}
+
+/// Outcome of one engine searching one position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchResult {
+    /// Label the engine was given in [`run`].
+    ///
+    /// [`run`]: fn.run.html
+    pub engine: String,
+
+    pub fen: String,
+    pub bestmove: String,
+
+    /// Depth reached, as reported by the last `info` line.
+    pub depth: u32,
+    pub nodes: u64,
+    pub nps: u64,
+
+    /// Search time reported by the engine, in milliseconds.
+    pub time: u64,
+
+    /// Whether the engine chose the same move as the first engine of the run.
+    pub agrees: bool,
+}
+
+/// Results of a benchmark run, one entry per engine and position.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BenchReport {
+    pub results: Vec<BenchResult>,
+}
+
+/// Runs every engine over the same positions with the same limit.
+///
+/// Each position is searched after a `ucinewgame`, so results don't depend on
+/// the order of the suite. Fails with [`EngineError::NotFound`] if a search
+/// ends without a best move.
+///
+/// # Arguments
+///
+/// * `engines`   - The engines to compare, each with a label for the report.
+/// * `positions` - FEN strings of the suite.
+/// * `limit`     - The limit used for every search.
+///
+/// # Examples
+///
+/// ```
+/// use uci::bench::{self, BenchLimit};
+///
+/// let a = uci::Engine::new("stockfish").unwrap();
+/// let b = uci::Engine::new("stockfish").unwrap();
+/// b.set_option("Skill Level", "5").unwrap();
+///
+/// let fens = ["6b1/8/1k5P/8/1P3B2/5pp1/8/4K3 b - - 0 1"];
+/// let report = bench::run(&[("full", &a), ("weak", &b)], &fens, BenchLimit::Nodes(100000)).unwrap();
+/// println!("{}", report.to_csv());
+/// ```
+///
+/// [`EngineError::NotFound`]: ../enum.EngineError.html#variant.NotFound
+pub fn run(engines: &[(&str, &Engine)], positions: &[&str], limit: BenchLimit)
+           -> Result<BenchReport> {
+    run_with_hash(engines, positions, limit, HashMode::Cleared)
+}
+
+/// Like [`run`], but with the [`HashMode`] to use between positions.
+///
+/// # Examples
+///
+/// Measuring the depth gained by keeping the hash over the moves of a game:
+///
+/// ```
+/// use uci::bench::{self, BenchLimit, HashMode};
+///
+/// let engine = uci::Engine::new("stockfish").unwrap();
+/// let game = [
+///     "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+///     "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2",
+///     "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2",
+/// ];
+///
+/// let limit = BenchLimit::Movetime(100);
+/// let cleared = bench::run_with_hash(&[("sf", &engine)], &game, limit, HashMode::Cleared).unwrap();
+/// let warm = bench::run_with_hash(&[("sf", &engine)], &game, limit, HashMode::Warm).unwrap();
+/// println!("depth gained: {}", warm.average_depth("sf") - cleared.average_depth("sf"));
+/// ```
+///
+/// [`run`]: fn.run.html
+/// [`HashMode`]: enum.HashMode.html
+pub fn run_with_hash(engines: &[(&str, &Engine)], positions: &[&str], limit: BenchLimit,
+                     hash: HashMode) -> Result<BenchReport> {
+    let mut report = BenchReport::default();
+
+    for (i, fen) in positions.iter().enumerate() {
+        let mut reference: Option<String> = None;
+        for &(label, engine) in engines {
+            if i == 0 || hash == HashMode::Cleared {
+                engine.new_game()?;
+            }
+            engine.set_position(fen)?;
+            let lines = engine.search(&limit.go_args())?;
+
+            let bestmove = best_of(&lines)?;
+            let agrees = match reference {
+                Some(ref r) => *r == bestmove,
+                None => {
+                    reference = Some(bestmove.clone());
+                    true
+                }
+            };
+
+            let infos: Vec<InfoRef> = lines.iter().filter_map(|l| InfoRef::parse(l)).collect();
+            report.results.push(BenchResult {
+                engine: label.to_string(),
+                fen: fen.to_string(),
+                depth: last_value(&infos, |i| i.depth.map(|d| d as u64)) as u32,
+                nodes: last_value(&infos, |i| i.nodes),
+                nps: last_value(&infos, |i| i.nps),
+                time: last_value(&infos, |i| i.time),
+                bestmove,
+                agrees,
+            });
+        }
+    }
+
+    Ok(report)
+}
+
+/// Returns the last value the engine reported for a field, or 0.
+fn last_value<F>(infos: &[InfoRef], field: F) -> u64
+    where F: Fn(&InfoRef) -> Option<u64>
+{
+    infos.iter().rev().find_map(field).unwrap_or(0)
+}
+
+impl BenchReport {
+    /// Fraction of positions where the given engine agreed with the first one.
+    pub fn agreement(&self, engine: &str) -> f64 {
+        let results: Vec<&BenchResult> = self.results.iter()
+            .filter(|r| r.engine == engine)
+            .collect();
+        if results.is_empty() {
+            return 0.0;
+        }
+        results.iter().filter(|r| r.agrees).count() as f64 / results.len() as f64
+    }
+
+    /// Average depth the given engine reached over the positions.
+    pub fn average_depth(&self, engine: &str) -> f64 {
+        let depths: Vec<u32> = self.results.iter()
+            .filter(|r| r.engine == engine)
+            .map(|r| r.depth)
+            .collect();
+        if depths.is_empty() {
+            return 0.0;
+        }
+        depths.iter().sum::<u32>() as f64 / depths.len() as f64
+    }
+
+    /// Formats the report as CSV, with a header line.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("engine,fen,bestmove,depth,nodes,nps,time,agrees\n");
+        for r in &self.results {
+            writeln!(out, "{},{},{},{},{},{},{},{}",
+                     csv_field(&r.engine), csv_field(&r.fen), r.bestmove,
+                     r.depth, r.nodes, r.nps, r.time, r.agrees).unwrap();
+        }
+        out
+    }
+
+    /// Formats the report as a JSON array of objects.
+    pub fn to_json(&self) -> String {
+        let rows: Vec<String> = self.results.iter()
+            .map(|r| format!("{{\"engine\":{},\"fen\":{},\"bestmove\":{},\"depth\":{},\
+                              \"nodes\":{},\"nps\":{},\"time\":{},\"agrees\":{}}}",
+                             json_string(&r.engine), json_string(&r.fen),
+                             json_string(&r.bestmove), r.depth, r.nodes, r.nps,
+                             r.time, r.agrees))
+            .collect();
+        format!("[{}]", rows.join(","))
+    }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains(',') || s.contains('"') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_formats() {
+        let report = BenchReport {
+            results: vec![BenchResult {
+                engine: "sf \"dev\"".to_string(),
+                fen: "8/8/8/8/8/8/8/K1k5 w - - 0 1".to_string(),
+                bestmove: "a1a2".to_string(),
+                depth: 12,
+                nodes: 1000,
+                nps: 50000,
+                time: 20,
+                agrees: true,
+            }],
+        };
+
+        assert_eq!(report.to_csv().lines().nth(1).unwrap(),
+                   "\"sf \"\"dev\"\"\",8/8/8/8/8/8/8/K1k5 w - - 0 1,a1a2,12,1000,50000,20,true");
+        assert!(report.to_json().starts_with("[{\"engine\":\"sf \\\"dev\\\"\",\"fen\""));
+        assert_eq!(report.agreement("sf \"dev\""), 1.0);
+        assert_eq!(report.average_depth("sf \"dev\""), 12.0);
+        assert_eq!(report.average_depth("other"), 0.0);
+    }
+}
@@ -0,0 +1,764 @@
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use connection::{self, Connection};
+use error::{EngineError, Result};
+use logging::{Logger, Verbosity};
+#[cfg(windows)]
+use pipe;
+use tail::OutputTail;
+use workdir::WorkDir;
+use watchdog::{Action, Watchdog};
+use xboard;
+use {Engine, Interrupter, Normalization, RetryPolicy, SearchLimits, SearchProbe, SessionStats, DEFAULT_TIME};
+
+/// Default capacity of the buffer used to read engine output.
+const DEFAULT_READ_BUFFER_SIZE: usize = 8 * 1024;
+
+/// Default number of output lines kept for error reports.
+const DEFAULT_OUTPUT_TAIL: usize = 20;
+
+/// Time a container gets to start, image pull included.
+const DOCKER_STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Containers started so far by this process, for unique names.
+static CONTAINERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Protocol spoken by an engine executable, see
+/// [`EngineBuilder::detect_protocol`].
+///
+/// [`EngineBuilder::detect_protocol`]: struct.EngineBuilder.html#method.detect_protocol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    /// The Universal Chess Interface, supported by [`Engine`].
+    ///
+    /// [`Engine`]: struct.Engine.html
+    Uci,
+
+    /// The xboard/WinBoard protocol (CECP). These engines are driven
+    /// through an adapter translating between the protocols, see
+    /// [`EngineBuilder::protocol`].
+    ///
+    /// [`EngineBuilder::protocol`]: struct.EngineBuilder.html#method.protocol
+    Xboard,
+}
+
+/// Configures how an [`Engine`] is started.
+///
+/// Use this instead of [`Engine::new`] when the defaults need changing.
+///
+/// # Examples
+///
+/// ```
+/// let engine = uci::EngineBuilder::new("stockfish")
+///     .read_buffer_size(64 * 1024)
+///     .max_line_length(1024 * 1024)
+///     .build()
+///     .unwrap();
+/// ```
+///
+/// [`Engine`]: struct.Engine.html
+/// [`Engine::new`]: struct.Engine.html#method.new
+#[derive(Debug, Clone)]
+pub struct EngineBuilder {
+    path: String,
+    args: Vec<String>,
+    image: Option<String>,
+    read_buffer_size: usize,
+    max_line_length: Option<usize>,
+    protocol: Protocol,
+    dump_bytes: bool,
+    label: Option<String>,
+    log_target: Option<String>,
+    log_verbosity: Verbosity,
+    output_tail: usize,
+    startup_timeout: Option<Duration>,
+    init_commands: Vec<String>,
+    registration: Option<(String, String)>,
+    temp_dir: bool,
+    chess960: bool,
+    analyse_mode: bool,
+}
+
+impl EngineBuilder {
+    /// Creates a builder for the engine at the given path.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the engine executable.
+    pub fn new(path: &str) -> EngineBuilder {
+        EngineBuilder {
+            path: path.to_string(),
+            args: vec![],
+            image: None,
+            read_buffer_size: DEFAULT_READ_BUFFER_SIZE,
+            max_line_length: None,
+            protocol: Protocol::Uci,
+            dump_bytes: false,
+            label: None,
+            log_target: None,
+            log_verbosity: Verbosity::Debug,
+            output_tail: DEFAULT_OUTPUT_TAIL,
+            startup_timeout: None,
+            init_commands: vec![],
+            registration: None,
+            temp_dir: false,
+            chess960: false,
+            analyse_mode: false,
+        }
+    }
+
+    /// Creates a builder for an engine run in a container of the given
+    /// image, with `docker run`, and spoken to over its standard streams.
+    ///
+    /// Starting a container takes longer than starting a process, and the
+    /// image may have to be pulled first, so the handshake gets a startup
+    /// timeout of a minute. When the image can't be found or pulled, Docker
+    /// exits and [`build`] fails with [`EngineError::ProcessExited`],
+    /// holding Docker's error message.
+    ///
+    /// Every container gets a name of its own and is removed once stopped.
+    /// Whenever the engine is killed, as on a startup timeout, its container
+    /// is killed with `docker kill` too, besides the `docker run` client.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// let engine = uci::EngineBuilder::docker("example/stockfish:16")
+    ///     .arg("--cpus=2")
+    ///     .startup_timeout(Some(Duration::from_secs(300)))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    ///
+    /// [`build`]: #method.build
+    /// [`EngineError::ProcessExited`]: enum.EngineError.html#variant.ProcessExited
+    pub fn docker(image: &str) -> EngineBuilder {
+        let mut builder = EngineBuilder::new("docker")
+            .arg("run")
+            .arg("--rm")
+            .arg("--interactive")
+            .startup_timeout(Some(DOCKER_STARTUP_TIMEOUT));
+        builder.image = Some(image.to_string());
+        builder
+    }
+
+    /// Adds an argument to the command line of the engine. For a
+    /// [`docker`] builder, the arguments go to `docker run`, before the
+    /// image.
+    ///
+    /// [`docker`]: #method.docker
+    pub fn arg(mut self, arg: &str) -> EngineBuilder {
+        self.args.push(arg.to_string());
+        self
+    }
+
+    /// Limits how long the engine may take to start and complete the `uci`
+    /// handshake. An engine that doesn't make it in time is killed and
+    /// [`build`] fails with [`EngineError::Timeout`]. There's no limit by
+    /// default, except for [`docker`] builders.
+    ///
+    /// [`build`]: #method.build
+    /// [`docker`]: #method.docker
+    /// [`EngineError::Timeout`]: enum.EngineError.html#variant.Timeout
+    pub fn startup_timeout(mut self, timeout: Option<Duration>) -> EngineBuilder {
+        self.startup_timeout = timeout;
+        self
+    }
+
+    /// Adds a command sent right after the `uci` handshake, before the
+    /// engine is handed over, for engines that need setting up first.
+    /// Commands are sent in the order they were added, each followed by
+    /// `isready`, and count towards the [`startup_timeout`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::EngineBuilder::new("stockfish")
+    ///     .init_command("setoption name Hash value 64")
+    ///     .init_command("ucinewgame")
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    ///
+    /// [`startup_timeout`]: #method.startup_timeout
+    pub fn init_command(mut self, command: &str) -> EngineBuilder {
+        self.init_commands.push(command.to_string());
+        self
+    }
+
+    /// Sets the name and code sent with `register` to engines that ask for
+    /// a registration. Engines asking for one when none was given are told
+    /// `register later`.
+    ///
+    /// Engines with copy protection or registration checks are only handed
+    /// over once the checks passed; [`build`] fails with
+    /// [`EngineError::CopyProtection`] or [`EngineError::Registration`]
+    /// otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let engine = uci::EngineBuilder::new("commercial-engine")
+    ///     .register("Jane Doe", "1234-5678")
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    ///
+    /// [`build`]: #method.build
+    /// [`EngineError::CopyProtection`]: enum.EngineError.html#variant.CopyProtection
+    /// [`EngineError::Registration`]: enum.EngineError.html#variant.Registration
+    pub fn register(mut self, name: &str, code: &str) -> EngineBuilder {
+        self.registration = Some((name.to_string(), code.to_string()));
+        self
+    }
+
+    /// Runs the engine in a temporary working directory of its own, removed
+    /// with everything in it when the engine is dropped. Keeps engines that
+    /// write logs or learning files from trampling each other, as when an
+    /// [`EnginePool`] runs many of them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::EngineBuilder::new("stockfish")
+    ///     .temp_dir(true)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    ///
+    /// [`EnginePool`]: pool/struct.EnginePool.html
+    pub fn temp_dir(mut self, enabled: bool) -> EngineBuilder {
+        self.temp_dir = enabled;
+        self
+    }
+
+    /// Plays Chess960 (Fischer random chess): `UCI_Chess960` is set right
+    /// after the handshake, castling rights of positions are sent to the
+    /// engine in Shredder-FEN, and castling moves, given and returned, are
+    /// written as the king taking its own rook, like `e1h1`.
+    ///
+    /// [`build`] fails with [`EngineError::UnknownOption`] if the engine
+    /// doesn't declare `UCI_Chess960`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::EngineBuilder::new("stockfish").chess960(true).build().unwrap();
+    /// engine.set_position("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9").unwrap();
+    /// println!("{}", engine.bestmove().unwrap());
+    /// ```
+    ///
+    /// [`build`]: #method.build
+    /// [`EngineError::UnknownOption`]: enum.EngineError.html#variant.UnknownOption
+    pub fn chess960(mut self, enabled: bool) -> EngineBuilder {
+        self.chess960 = enabled;
+        self
+    }
+
+    /// Sets `UCI_AnalyseMode` right after the handshake, telling the engine
+    /// it analyses rather than plays, so it leaves out contempt and other
+    /// heuristics meant for games. Engines not declaring the option are
+    /// left as they are.
+    ///
+    /// See [`Engine::analysis_mode`] to also turn off strength limiting and
+    /// contempt explicitly, or to switch modes later.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::EngineBuilder::new("stockfish").analyse_mode(true).build().unwrap();
+    /// ```
+    ///
+    /// [`Engine::analysis_mode`]: struct.Engine.html#method.analysis_mode
+    pub fn analyse_mode(mut self, enabled: bool) -> EngineBuilder {
+        self.analyse_mode = enabled;
+        self
+    }
+
+    /// The program to run. A relative path is made absolute when the engine
+    /// runs in another working directory, as it would be looked up there.
+    fn program(&self, moved: bool) -> PathBuf {
+        let path = Path::new(&self.path);
+        if moved && path.is_relative() && path.components().count() > 1 {
+            if let Ok(absolute) = path.canonicalize() {
+                return absolute;
+            }
+        }
+        path.to_path_buf()
+    }
+
+    /// The command line arguments, ending with the name and image for
+    /// containers.
+    fn command_args<'a>(&'a self, container: Option<&'a str>) -> Vec<&'a str> {
+        let mut args: Vec<&str> = self.args.iter().map(String::as_str).collect();
+        if let Some(name) = container {
+            args.push("--name");
+            args.push(name);
+        }
+        args.extend(self.image.as_deref());
+        args
+    }
+
+    /// A name for a new container of the builder's image, so it can be
+    /// killed along with its `docker run` client.
+    fn container_name(&self) -> Option<String> {
+        self.image.as_ref().map(|_| {
+            let n = CONTAINERS.fetch_add(1, Ordering::SeqCst);
+            format!("uci-engine-{}-{}", process::id(), n)
+        })
+    }
+
+    /// Changes the capacity of the buffer engine output is read through.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - New capacity in bytes. Defaults to 8 KiB. A size of 0 is
+    ///   taken as 1, as an empty buffer couldn't read anything.
+    pub fn read_buffer_size(mut self, size: usize) -> EngineBuilder {
+        self.read_buffer_size = size.max(1);
+        self
+    }
+
+    /// Limits the length of the lines the engine may send.
+    ///
+    /// Longer lines are discarded and reported as
+    /// [`EngineError::LineTooLong`]. There's no limit by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `max` - Maximum length in bytes, not counting the line terminator.
+    ///
+    /// [`EngineError::LineTooLong`]: enum.EngineError.html#variant.LineTooLong
+    pub fn max_line_length(mut self, max: usize) -> EngineBuilder {
+        self.max_line_length = Some(max);
+        self
+    }
+
+    /// Logs the raw bytes, in hex, of every line the engine sends that has
+    /// bytes other than printable ASCII, tabs and line terminators, for
+    /// diagnosing odd encodings and stray control characters.
+    ///
+    /// Lines are always decoded byte for byte as Latin-1, so nothing is
+    /// lost, but such characters are easy to miss once in a string.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::EngineBuilder::new("stockfish")
+    ///     .dump_bytes(true)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn dump_bytes(mut self, enabled: bool) -> EngineBuilder {
+        self.dump_bytes = enabled;
+        self
+    }
+
+    /// Names the engine, as `"sf16-main"` or `"lc0-gpu1"`, to tell it apart
+    /// from others running alongside.
+    ///
+    /// The label is the engine's log target, unless [`log_target`] gives
+    /// another, and is attached to its event [`Subscription`]s, its
+    /// [`Provenance`], the [`WorkerStats`] and replacement events of an
+    /// [`EnginePool`], and the fail-over events of a [`FailoverEngine`].
+    /// Its errors carry it as [`EngineError::Labelled`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::EngineBuilder::new("stockfish").label("sf16-main").build().unwrap();
+    /// assert_eq!(engine.label(), Some("sf16-main"));
+    /// assert_eq!(engine.provenance().label.as_deref(), Some("sf16-main"));
+    /// ```
+    ///
+    /// [`log_target`]: #method.log_target
+    /// [`Subscription`]: events/struct.Subscription.html
+    /// [`Provenance`]: struct.Provenance.html
+    /// [`WorkerStats`]: pool/struct.WorkerStats.html
+    /// [`EnginePool`]: pool/struct.EnginePool.html
+    /// [`FailoverEngine`]: failover/struct.FailoverEngine.html
+    /// [`EngineError::Labelled`]: enum.EngineError.html#variant.Labelled
+    pub fn label(mut self, label: &str) -> EngineBuilder {
+        self.label = Some(label.to_string());
+        self
+    }
+
+    /// The label given with [`label`], if any.
+    ///
+    /// [`label`]: #method.label
+    pub(crate) fn label_ref(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Logs everything about this engine under the given target instead of
+    /// the crate's, so the traffic of engines running side by side can be
+    /// told apart and filtered, as with `RUST_LOG=sf16-main=info`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let main = uci::EngineBuilder::new("stockfish").log_target("sf16-main").build().unwrap();
+    /// let sparring = uci::EngineBuilder::new("stockfish").log_target("sf16-sparring").build().unwrap();
+    /// ```
+    pub fn log_target(mut self, target: &str) -> EngineBuilder {
+        self.log_target = Some(target.to_string());
+        self
+    }
+
+    /// Limits how much this engine logs, see [`Verbosity`]. Everything is
+    /// logged by default, leaving the filtering to the logger.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use uci::{EngineBuilder, Verbosity};
+    ///
+    /// // A helper engine whose commands would drown the main one's.
+    /// let helper = EngineBuilder::new("stockfish").log_verbosity(Verbosity::Warn).build().unwrap();
+    /// ```
+    ///
+    /// [`Verbosity`]: enum.Verbosity.html
+    pub fn log_verbosity(mut self, verbosity: Verbosity) -> EngineBuilder {
+        self.log_verbosity = verbosity;
+        self
+    }
+
+    /// The logger of the engines built.
+    fn logger(&self) -> Logger {
+        let target = self.log_target.as_deref().or(self.label.as_deref()).unwrap_or("uci");
+        Logger::new(target, self.log_verbosity)
+    }
+
+    /// Changes how many of the last lines the engine printed, on stdout and
+    /// stderr, are attached to [`EngineError::Timeout`] and
+    /// [`EngineError::ProcessExited`].
+    ///
+    /// # Arguments
+    ///
+    /// * `lines` - Number of lines to keep. Defaults to 20, 0 keeps none.
+    ///
+    /// [`EngineError::Timeout`]: enum.EngineError.html#variant.Timeout
+    /// [`EngineError::ProcessExited`]: enum.EngineError.html#variant.ProcessExited
+    pub fn output_tail(mut self, lines: usize) -> EngineBuilder {
+        self.output_tail = lines;
+        self
+    }
+
+    /// Finds out which protocol the executable speaks by starting it apart
+    /// and probing with `uci`, then with `xboard`.
+    ///
+    /// The probed process is killed afterwards. Fails with
+    /// [`EngineError::Timeout`] if it answers neither within `timeout`, and
+    /// with [`EngineError::Io`] if it can't be spawned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use uci::{EngineBuilder, Protocol};
+    ///
+    /// let builder = EngineBuilder::new("stockfish");
+    /// if builder.detect_protocol(Duration::from_secs(2)).unwrap() == Protocol::Uci {
+    ///     let engine = builder.build().unwrap();
+    /// }
+    /// ```
+    ///
+    /// [`EngineError::Timeout`]: enum.EngineError.html#variant.Timeout
+    /// [`EngineError::Io`]: enum.EngineError.html#variant.Io
+    pub fn detect_protocol(&self, timeout: Duration) -> Result<Protocol> {
+        let container = self.container_name();
+        let mut child = Command::new(&self.path)
+                                .args(self.command_args(container.as_deref()))
+                                .stdin(Stdio::piped())
+                                .stdout(Stdio::piped())
+                                .spawn()?;
+        let stdout = child.stdout.take().unwrap();
+        let mut stdin = child.stdin.take().unwrap();
+
+        let (lines, received) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).split(b'\n') {
+                let line = match line {
+                    Ok(line) => String::from_utf8_lossy(&line).trim().to_string(),
+                    Err(_) => break,
+                };
+                if lines.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let res = (|| {
+            stdin.write_all(b"uci\n")?;
+            if wait_for(&received, timeout, |line| line == "uciok") {
+                return Ok(Protocol::Uci);
+            }
+            stdin.write_all(b"xboard\nprotover 2\n")?;
+            if wait_for(&received, timeout, |line| line.starts_with("feature")) {
+                return Ok(Protocol::Xboard);
+            }
+            Err(EngineError::Timeout(vec![]).labelled(self.label_ref()))
+        })();
+
+        let _ = child.kill();
+        if let Some(ref container) = container {
+            connection::kill_container(container);
+        }
+        let _ = child.wait();
+        engine_log!(self.logger(), Debug, "Detected protocol of {}: {:?}", self.path, res);
+        res
+    }
+
+    /// Sets the protocol the executable speaks. Defaults to UCI.
+    ///
+    /// Engines speaking xboard are driven through an adapter translating the
+    /// commands the crate sends and the output of the engine, so they're
+    /// used like UCI engines. Positions given as FEN need the engine to
+    /// support `setboard`. Infinite searches and pondering become `analyze`
+    /// sessions, and moves of a PV that aren't in coordinate notation are
+    /// left out of `info` lines.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use uci::{EngineBuilder, Protocol};
+    ///
+    /// let engine = EngineBuilder::new("fairymax").protocol(Protocol::Xboard).build().unwrap();
+    /// println!("{}", engine.bestmove().unwrap());
+    /// ```
+    pub fn protocol(mut self, protocol: Protocol) -> EngineBuilder {
+        self.protocol = protocol;
+        self
+    }
+
+    /// Detects the protocol of the executable with [`detect_protocol`], then
+    /// builds the engine speaking it, returning both.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use uci::{EngineBuilder, Protocol};
+    ///
+    /// let (protocol, engine) = EngineBuilder::new("stockfish")
+    ///     .build_detected(Duration::from_secs(2))
+    ///     .unwrap();
+    /// assert_eq!(protocol, Protocol::Uci);
+    /// println!("{}", engine.bestmove().unwrap());
+    /// ```
+    ///
+    /// [`detect_protocol`]: #method.detect_protocol
+    pub fn build_detected(self, timeout: Duration) -> Result<(Protocol, Engine)> {
+        let protocol = self.detect_protocol(timeout)?;
+        Ok((protocol, self.protocol(protocol).build()?))
+    }
+
+    /// Spawns the engine and performs the `uci` handshake.
+    ///
+    /// Fails with [`EngineError::Io`] if the engine couldn't be spawned (path
+    /// is invalid, execution permission denied, Docker isn't installed, etc.)
+    ///
+    /// [`EngineError::Io`]: enum.EngineError.html#variant.Io
+    pub fn build(self) -> Result<Engine> {
+        let workdir = if self.temp_dir { Some(WorkDir::create()?) } else { None };
+        let mut command = Command::new(self.program(workdir.is_some()));
+        if let Some(ref workdir) = workdir {
+            command.current_dir(workdir.path());
+        }
+        let container = self.container_name();
+        let mut cmd = command.args(self.command_args(container.as_deref()))
+                             .stdin(Stdio::piped())
+                             .stdout(Stdio::piped())
+                             .stderr(Stdio::piped())
+                             .spawn()
+                             .map_err(|e| EngineError::from(e).labelled(self.label_ref()))?;
+        let stdout = cmd.stdout.take().unwrap();
+        let tail = Arc::new(OutputTail::new(self.output_tail));
+        let stderr = OutputTail::follow(tail.clone(), cmd.stderr.take().unwrap(), self.logger());
+
+        let (connection, output): (Connection, Box<dyn Read + Send>) = match self.protocol {
+            Protocol::Uci => (Connection::spawned(cmd), Box::new(stdout)),
+            Protocol::Xboard => {
+                let (input, output) = xboard::adapt(cmd.stdin.take().unwrap(), stdout);
+                (Connection::spawned_with(cmd, input), Box::new(output))
+            }
+        };
+        let connection = connection.in_workdir(workdir).in_container(container);
+        let label = self.label.clone();
+        let res = self.connect(connection, output, tail.clone());
+        let exited = match res {
+            Err(ref e) => match *e.unlabelled() {
+                EngineError::ProcessExited(_) => true,
+                EngineError::Io(ref e) => e.kind() == io::ErrorKind::BrokenPipe,
+                _ => false,
+            },
+            Ok(_) => false,
+        };
+        if exited {
+            // The reason is likely still on its way through stderr.
+            let _ = stderr.join();
+            return Err(EngineError::ProcessExited(tail.lines()).labelled(label.as_deref()));
+        }
+        res
+    }
+
+    /// Connects to an engine served on a named pipe, like
+    /// `\\.\pipe\stockfish`, given as the builder's path, instead of
+    /// spawning one. The engine is expected to be started already, and to
+    /// answer the `uci` handshake.
+    ///
+    /// The pipe is used for overlapped I/O, so commands like `stop` can be
+    /// written while a read is waiting for the engine's output.
+    ///
+    /// The crate can't kill an engine it didn't start, so a hard limit or
+    /// a failed ping leave it running.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// let engine = uci::EngineBuilder::new(r"\\.\pipe\stockfish").named_pipe().unwrap();
+    /// println!("{}", engine.bestmove().unwrap());
+    /// ```
+    #[cfg(windows)]
+    pub fn named_pipe(self) -> Result<Engine> {
+        let (input, output) = pipe::open(Path::new(&self.path))?;
+        let tail = Arc::new(OutputTail::new(self.output_tail));
+        self.connect(Connection::stream(input), Box::new(output), tail)
+    }
+
+    /// Uses an engine started by someone else, like a supervisor or a
+    /// container runtime, through its input and output streams. The engine
+    /// must not have been sent `uci` yet.
+    ///
+    /// The builder's path is only used in log messages. The crate can't kill
+    /// an engine it didn't start, so a hard limit or a failed ping leave it
+    /// running.
+    ///
+    /// # Arguments
+    ///
+    /// * `input`  - Where commands for the engine are written.
+    /// * `output` - Where the engine's answers are read from.
+    pub fn attach<W, R>(self, input: W, output: R) -> Result<Engine>
+        where W: Write + Send + 'static,
+              R: Read + Send + 'static
+    {
+        let tail = Arc::new(OutputTail::new(self.output_tail));
+        self.connect(Connection::stream(input), Box::new(output), tail)
+    }
+
+    fn connect(self, connection: Connection, output: Box<dyn Read + Send>, tail: Arc<OutputTail>)
+               -> Result<Engine> {
+        let connection = Arc::new(Mutex::new(connection));
+        let mut res = Engine {
+            interrupter: Interrupter::new(connection.clone()),
+            engine: connection,
+            reader: RefCell::new(BufReader::with_capacity(self.read_buffer_size, output)),
+            max_line_length: self.max_line_length,
+            dump_bytes: self.dump_bytes,
+            logger: self.logger(),
+            label: self.label.clone(),
+            tail,
+            retry: RetryPolicy::none(),
+            limits: SearchLimits { movetime: Some(DEFAULT_TIME), ..SearchLimits::default() },
+            hard_limit: None,
+            partial_on_timeout: false,
+            enforce_movetime: false,
+            movetime_enforced: Cell::new(false),
+            overshoot_streak: Cell::new(0),
+            partial: RefCell::new(None),
+            abort: Cell::new(None),
+            normalization: Normalization::None,
+            profile: false,
+            last_timing: Cell::new(None),
+            latency: Cell::new(None),
+            subscribers: RefCell::new(vec![]),
+            searching: Cell::new(false),
+            search_start: Cell::new(None),
+            probe: SearchProbe::default(),
+            stats: Cell::new(SessionStats::default()),
+            strict_options: false,
+            pending_options: RefCell::new(vec![]),
+            route_noise: false,
+            noise: RefCell::new(vec![]),
+            debug: Cell::new(false),
+            chess960: false,
+            handshake: vec![],
+            banner: vec![],
+            option_values: RefCell::new(BTreeMap::new()),
+        };
+
+        let watchdog = self.startup_timeout.map(|timeout| {
+            Watchdog::arm(res.engine.clone(), res.logger.clone(), vec![(Instant::now() + timeout, Action::Kill)])
+        });
+        let handshake = res.handshake(self.registration.as_ref()).and_then(|()| {
+            if self.chess960 {
+                res.set_option("UCI_Chess960", "true")?;
+                res.chess960 = true;
+            }
+            let declares_analyse_mode = res.declared_options().iter()
+                .any(|option| option.name.eq_ignore_ascii_case("UCI_AnalyseMode"));
+            if self.analyse_mode && declares_analyse_mode {
+                res.set_option("UCI_AnalyseMode", "true")?;
+            }
+            for command in &self.init_commands {
+                res.command(command)?;
+            }
+            Ok(())
+        });
+        let timed_out = watchdog.as_ref().and_then(Watchdog::fired).is_some();
+        drop(watchdog);
+        match handshake {
+            Err(_) if timed_out => return Err(EngineError::Timeout(res.tail.lines()).labelled(res.label())),
+            handshake => handshake.map_err(|e| e.labelled(res.label()))?,
+        }
+
+        Ok(res)
+    }
+}
+
+/// Waits for a line matching `done` until the timeout.
+fn wait_for<F>(lines: &mpsc::Receiver<String>, timeout: Duration, done: F) -> bool
+    where F: Fn(&str) -> bool
+{
+    let deadline = Instant::now() + timeout;
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        match lines.recv_timeout(left) {
+            Ok(ref line) if done(line) => return true,
+            Ok(_) => {}
+            Err(_) => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spawn_failure() {
+        match EngineBuilder::new("./no-such-engine").label("missing").build() {
+            Err(ref e) => match *e.unlabelled() {
+                EngineError::Io(ref e) => assert_eq!(e.kind(), io::ErrorKind::NotFound),
+                ref e => panic!("unexpected error: {}", e),
+            },
+            Ok(_) => panic!("spawning a missing engine should fail"),
+        }
+
+        let docker = EngineBuilder::docker("example/stockfish").arg("--cpus=2");
+        let name = docker.container_name().unwrap();
+        assert_ne!(docker.container_name(), Some(name.clone()));
+        assert_eq!(docker.command_args(Some(&name)),
+                   vec!["run", "--rm", "--interactive", "--cpus=2", "--name", &name, "example/stockfish"]);
+    }
+}
@@ -0,0 +1,71 @@
+/// Rewrites the castling field of a FEN in Shredder-FEN, naming the file of
+/// every rook that can castle instead of using `KQkq`.
+///
+/// In X-FEN, `K` and `Q` stand for the outermost rook on either side of the
+/// king, which is ambiguous to some engines when a side has two rooks on its
+/// back rank. Rights whose rook can't be found are dropped. FENs that can't
+/// be read are returned as they are.
+pub(crate) fn shredder_fen(fen: &str) -> String {
+    let mut fields: Vec<&str> = fen.split_whitespace().collect();
+    let ranks: Vec<Vec<char>> = match fields.first() {
+        Some(board) => board.split('/').map(expand_rank).collect(),
+        None => return fen.to_string(),
+    };
+    if fields.len() < 3 || ranks.len() != 8 {
+        return fen.to_string();
+    }
+
+    let mut white: Vec<char> = vec![];
+    let mut black: Vec<char> = vec![];
+    for right in fields[2].chars() {
+        match right {
+            'K' | 'Q' => white.extend(rook_file(&ranks[7], 'K', 'R', right == 'K')),
+            'k' | 'q' => black.extend(rook_file(&ranks[0], 'k', 'r', right == 'k')),
+            'A'..='H' => white.push(right.to_ascii_lowercase()),
+            'a'..='h' => black.push(right),
+            _ => {}
+        }
+    }
+    let castling: String = white.iter().map(char::to_ascii_uppercase).chain(black).collect();
+
+    let castling = if castling.is_empty() { "-".to_string() } else { castling };
+    fields[2] = &castling;
+    fields.join(" ")
+}
+
+/// The squares of a FEN rank, empty ones as spaces.
+fn expand_rank(rank: &str) -> Vec<char> {
+    rank.chars()
+        .flat_map(|c| match c.to_digit(10) {
+            Some(n) => vec![' '; n as usize],
+            None => vec![c],
+        })
+        .collect()
+}
+
+/// The file of the outermost rook on the king's side of a rank.
+fn rook_file(rank: &[char], king: char, rook: char, king_side: bool) -> Option<char> {
+    let king = rank.iter().position(|&c| c == king)?;
+    let file = if king_side {
+        (king + 1..rank.len()).rev().find(|&file| rank[file] == rook)?
+    } else {
+        (0..king).find(|&file| rank[file] == rook)?
+    };
+    Some((b'a' + file as u8) as char)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shredder_fen() {
+        assert_eq!(shredder_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+                   "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1");
+        // Two rooks on the king side: K is the outermost one.
+        assert_eq!(shredder_fen("1r2k1rr/8/8/8/8/8/8/RR3KR1 w KQk - 0 1"),
+                   "1r2k1rr/8/8/8/8/8/8/RR3KR1 w GAh - 0 1");
+        assert_eq!(shredder_fen("4k3/8/8/8/8/8/8/4K2R w Hq - 0 1"), "4k3/8/8/8/8/8/8/4K2R w H - 0 1");
+        assert_eq!(shredder_fen("not a fen"), "not a fen");
+    }
+}
@@ -0,0 +1,387 @@
+use std::time::{Duration, Instant};
+
+/// Moves a game is expected to last, used to spread the remaining time.
+const EXPECTED_GAME_LENGTH: u32 = 50;
+
+/// Fewest moves the remaining time is spread over, however long the game.
+const MIN_MOVES_LEFT: u32 = 20;
+
+/// Longest share of the usable time a single move may take.
+const MAX_SHARE: u32 = 4;
+
+/// Remaining time and increment of one player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayerTime {
+    pub remaining: Duration,
+    pub increment: Duration,
+}
+
+/// The clock situation of a move about to be searched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clocks {
+    pub white: PlayerTime,
+    pub black: PlayerTime,
+    pub white_to_move: bool,
+
+    /// Number of the move about to be played, starting at 1.
+    pub move_number: u32,
+}
+
+impl Clocks {
+    /// Time of the player to move.
+    pub fn mover(&self) -> PlayerTime {
+        if self.white_to_move { self.white } else { self.black }
+    }
+}
+
+/// One of the players.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    White,
+    Black,
+}
+
+/// A chess clock for both players, with increments and delay.
+///
+/// The clock of the side to move runs from [`start`] until [`press`], which
+/// hands the move over. A player whose time runs out has their flag fall:
+/// [`flag`] reports it and the clock stops.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use uci::clock::{GameClock, TimeManager};
+///
+/// let engine = uci::Engine::new("stockfish").unwrap();
+/// let manager = TimeManager::new();
+/// let mut clock = GameClock::new(Duration::from_secs(60), Duration::from_secs(1));
+///
+/// clock.start();
+/// let mv = engine.bestmove_on_clock(&manager, &clock.clocks()).unwrap();
+/// clock.press();
+/// assert_eq!(clock.flag(), None);
+/// ```
+///
+/// [`start`]: #method.start
+/// [`press`]: #method.press
+/// [`flag`]: #method.flag
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameClock {
+    white: Duration,
+    black: Duration,
+    increment: Duration,
+    delay: Duration,
+    to_move: Side,
+    move_number: u32,
+    running_since: Option<Instant>,
+    flag: Option<Side>,
+}
+
+impl GameClock {
+    /// Creates a stopped clock, with White to move.
+    ///
+    /// # Arguments
+    ///
+    /// * `initial`   - Starting time of each player.
+    /// * `increment` - Time added to a player's clock after each of their moves.
+    pub fn new(initial: Duration, increment: Duration) -> GameClock {
+        GameClock {
+            white: initial,
+            black: initial,
+            increment,
+            delay: Duration::from_secs(0),
+            to_move: Side::White,
+            move_number: 1,
+            running_since: None,
+            flag: None,
+        }
+    }
+
+    /// Sets a delay: the first part of every move that isn't taken from the
+    /// player's clock.
+    pub fn delay(mut self, delay: Duration) -> GameClock {
+        self.delay = delay;
+        self
+    }
+
+    /// Starts the clock of the side to move.
+    pub fn start(&mut self) {
+        if self.running_since.is_none() {
+            self.running_since = Some(Instant::now());
+        }
+    }
+
+    /// Ends the move of the side to move, charging them the time since
+    /// [`start`] or the previous press, and starts the opponent's clock.
+    ///
+    /// Returns false if the player ran out of time instead.
+    ///
+    /// [`start`]: #method.start
+    pub fn press(&mut self) -> bool {
+        let elapsed = self.running_since.map(|since| since.elapsed()).unwrap_or_default();
+        let ok = self.record(elapsed);
+        self.running_since = if ok { Some(Instant::now()) } else { None };
+        ok
+    }
+
+    /// Like [`press`], but with the time the move took given instead of
+    /// measured. The clock isn't started.
+    ///
+    /// [`press`]: #method.press
+    pub fn record(&mut self, elapsed: Duration) -> bool {
+        if self.flag.is_some() {
+            return false;
+        }
+
+        let charged = elapsed.checked_sub(self.delay).unwrap_or_default();
+        let increment = self.increment;
+        let remaining = match self.to_move {
+            Side::White => &mut self.white,
+            Side::Black => &mut self.black,
+        };
+        match remaining.checked_sub(charged) {
+            Some(left) if !left.is_zero() => *remaining = left + increment,
+            _ => {
+                *remaining = Duration::from_secs(0);
+                self.flag = Some(self.to_move);
+                return false;
+            }
+        }
+
+        self.to_move = match self.to_move {
+            Side::White => Side::Black,
+            Side::Black => {
+                self.move_number += 1;
+                Side::White
+            }
+        };
+        true
+    }
+
+    /// Time left to a player, counting the move in progress.
+    pub fn remaining(&self, side: Side) -> Duration {
+        let stored = match side {
+            Side::White => self.white,
+            Side::Black => self.black,
+        };
+        match self.running_since {
+            Some(since) if side == self.to_move && self.flag.is_none() => {
+                let charged = since.elapsed().checked_sub(self.delay).unwrap_or_default();
+                stored.checked_sub(charged).unwrap_or_default()
+            }
+            _ => stored,
+        }
+    }
+
+    /// The player whose flag fell, if any.
+    ///
+    /// Also detects a flag falling during the move in progress.
+    pub fn flag(&self) -> Option<Side> {
+        if self.flag.is_none() && self.running_since.is_some()
+            && self.remaining(self.to_move).is_zero() {
+            return Some(self.to_move);
+        }
+        self.flag
+    }
+
+    pub fn to_move(&self) -> Side {
+        self.to_move
+    }
+
+    /// Number of the move about to be played, starting at 1.
+    pub fn move_number(&self) -> u32 {
+        self.move_number
+    }
+
+    /// Returns the clock situation, e.g. for [`TimeManager::go_args`].
+    ///
+    /// [`TimeManager::go_args`]: struct.TimeManager.html#method.go_args
+    pub fn clocks(&self) -> Clocks {
+        Clocks {
+            white: PlayerTime { remaining: self.remaining(Side::White), increment: self.increment },
+            black: PlayerTime { remaining: self.remaining(Side::Black), increment: self.increment },
+            white_to_move: self.to_move == Side::White,
+            move_number: self.move_number,
+        }
+    }
+}
+
+/// Decides how much time the engine gets for each move of a timed game.
+///
+/// By default the time is allocated by the crate and sent as a `movetime`.
+/// With [`delegate`] the clocks are sent instead and the engine's own time
+/// management decides.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::{Duration, Instant};
+/// use uci::clock::{Clocks, PlayerTime, TimeManager};
+///
+/// let engine = uci::Engine::new("stockfish").unwrap();
+/// let side = PlayerTime {
+///     remaining: Duration::from_secs(60),
+///     increment: Duration::from_secs(1),
+/// };
+/// let clocks = Clocks { white: side, black: side, white_to_move: true, move_number: 1 };
+///
+/// let manager = TimeManager::new().safety_margin(Duration::from_millis(100));
+/// let mv = engine.bestmove_on_clock(&manager, &clocks).unwrap();
+/// ```
+///
+/// [`delegate`]: #method.delegate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeManager {
+    delegate: bool,
+    safety_margin: Duration,
+    emergency_reserve: Duration,
+}
+
+impl Default for TimeManager {
+    fn default() -> TimeManager {
+        TimeManager::new()
+    }
+}
+
+impl TimeManager {
+    /// Creates a time manager with a 50 ms safety margin and a one second
+    /// emergency reserve.
+    pub fn new() -> TimeManager {
+        TimeManager {
+            delegate: false,
+            safety_margin: Duration::from_millis(50),
+            emergency_reserve: Duration::from_secs(1),
+        }
+    }
+
+    /// Sends `wtime`/`btime` to the engine instead of a `movetime`.
+    pub fn delegate(mut self, delegate: bool) -> TimeManager {
+        self.delegate = delegate;
+        self
+    }
+
+    /// Time subtracted from the clock before anything else, to cover the
+    /// delay between the engine answering and the move reaching the clock.
+    pub fn safety_margin(mut self, margin: Duration) -> TimeManager {
+        self.safety_margin = margin;
+        self
+    }
+
+    /// Time that is only spent once the clock runs lower than it.
+    pub fn emergency_reserve(mut self, reserve: Duration) -> TimeManager {
+        self.emergency_reserve = reserve;
+        self
+    }
+
+    /// Adds a measured latency to the safety margin.
+    pub(crate) fn compensate(self, latency: Duration) -> TimeManager {
+        let safety_margin = self.safety_margin + latency;
+        self.safety_margin(safety_margin)
+    }
+
+    /// Returns the time to search the next move for.
+    ///
+    /// The usable time, what's left after the margin and the reserve, is
+    /// spread over the moves the game is expected to last and most of the
+    /// increment is added. Once in the reserve, only small slices of it are
+    /// spent.
+    ///
+    /// # Arguments
+    ///
+    /// * `time`        - Clock of the player to move.
+    /// * `move_number` - Number of the move about to be played, starting at 1.
+    pub fn allocate(&self, time: PlayerTime, move_number: u32) -> Duration {
+        let available = time.remaining.checked_sub(self.safety_margin).unwrap_or_default();
+        let increment = time.increment * 3 / 4;
+
+        let movetime = match available.checked_sub(self.emergency_reserve) {
+            Some(usable) if !usable.is_zero() => {
+                let moves_left = EXPECTED_GAME_LENGTH.saturating_sub(move_number).max(MIN_MOVES_LEFT);
+                (usable / moves_left + increment).min(usable / MAX_SHARE + increment)
+            }
+            _ => available / MIN_MOVES_LEFT + increment / 2,
+        };
+
+        movetime.min(available).max(Duration::from_millis(1))
+    }
+
+    /// Returns the arguments of the `go` command for the next move.
+    pub fn go_args(&self, clocks: &Clocks) -> String {
+        if self.delegate {
+            let remaining = |time: PlayerTime| {
+                time.remaining.checked_sub(self.safety_margin).unwrap_or_default().as_millis().max(1)
+            };
+            format!("wtime {} btime {} winc {} binc {}",
+                    remaining(clocks.white), remaining(clocks.black),
+                    clocks.white.increment.as_millis(), clocks.black.increment.as_millis())
+        } else {
+            let movetime = self.allocate(clocks.mover(), clocks.move_number);
+            format!("movetime {}", movetime.as_millis())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(remaining: u64, increment: u64) -> PlayerTime {
+        PlayerTime {
+            remaining: Duration::from_millis(remaining),
+            increment: Duration::from_millis(increment),
+        }
+    }
+
+    #[test]
+    fn test_allocation() {
+        let manager = TimeManager::new();
+
+        // 60s - 50ms margin - 1s reserve over 49 moves, plus 3/4 of 1s.
+        let early = manager.allocate(time(60_000, 1000), 1);
+        assert_eq!(early.as_millis(), 58_950 / 49 + 750);
+
+        // Late in the game the time is spread over at least 20 moves.
+        let late = manager.allocate(time(60_000, 0), 60);
+        assert_eq!(late.as_millis(), 58_950 / 20);
+
+        // In the reserve only slices of it are spent.
+        let emergency = manager.allocate(time(500, 0), 30);
+        assert_eq!(emergency.as_millis(), 450 / 20);
+
+        assert_eq!(manager.allocate(time(0, 0), 30).as_millis(), 1);
+    }
+
+    #[test]
+    fn test_go_args() {
+        let clocks = Clocks {
+            white: time(10_000, 100),
+            black: time(20_000, 200),
+            white_to_move: false,
+            move_number: 40,
+        };
+
+        let manager = TimeManager::new().emergency_reserve(Duration::from_secs(0));
+        assert_eq!(manager.go_args(&clocks), "movetime 1147");
+        assert_eq!(manager.delegate(true).go_args(&clocks),
+                   "wtime 9950 btime 19950 winc 100 binc 200");
+    }
+
+    #[test]
+    fn test_game_clock() {
+        let mut clock = GameClock::new(Duration::from_secs(10), Duration::from_secs(1))
+            .delay(Duration::from_secs(2));
+
+        assert!(clock.record(Duration::from_secs(1)));
+        assert_eq!(clock.remaining(Side::White), Duration::from_secs(11));
+        assert!(clock.record(Duration::from_secs(5)));
+        assert_eq!(clock.remaining(Side::Black), Duration::from_secs(8));
+        assert_eq!(clock.move_number(), 2);
+        assert_eq!(clock.to_move(), Side::White);
+
+        assert!(!clock.record(Duration::from_secs(13)));
+        assert_eq!(clock.flag(), Some(Side::White));
+        assert!(!clock.record(Duration::from_secs(0)));
+        assert_eq!(clock.clocks().white.remaining, Duration::from_secs(0));
+    }
+}
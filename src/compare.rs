@@ -0,0 +1,109 @@
+use error::Result;
+use info::InfoRef;
+use score::Score;
+use {best_of, Engine, SearchLimits};
+
+/// How a line of play from a common position was found by the engine, see
+/// [`compare_lines`].
+///
+/// [`compare_lines`]: fn.compare_lines.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineComparison {
+    /// The moves of the line, in Coordinate notation.
+    pub moves: Vec<String>,
+
+    /// Score of the position at the end of the line, from the point of view
+    /// of the side to move at the start.
+    pub score: Option<Score>,
+
+    /// Depth the resulting position was searched to.
+    pub depth: Option<u32>,
+
+    /// The engine's best move at the end of the line.
+    pub bestmove: String,
+}
+
+/// Analyses the position at the end of every line with the same limits and
+/// returns them ranked, best first for the side to move at the start.
+///
+/// Lines without a score, which happens for illegal moves, come last. Fails
+/// with [`EngineError::NotFound`] if a search ends without a best move.
+///
+/// # Arguments
+///
+/// * `engine` - The engine to analyse with. Its position is changed.
+/// * `fen`    - The starting position, in FEN notation.
+/// * `lines`  - The continuations to compare, as moves in Coordinate notation.
+/// * `limits` - Limits of every search.
+///
+/// # Examples
+///
+/// ```
+/// use uci::SearchLimits;
+/// use uci::compare::compare_lines;
+///
+/// let engine = uci::Engine::new("stockfish").unwrap();
+/// let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+/// let lines = vec![vec!["e2e4", "c7c5"], vec!["d2d4", "d7d5", "c2c4"]];
+///
+/// for line in compare_lines(&engine, fen, &lines, &engine.limits()).unwrap() {
+///     println!("{:?}: {:?}", line.moves, line.score);
+/// }
+/// ```
+///
+/// [`EngineError::NotFound`]: ../enum.EngineError.html#variant.NotFound
+pub fn compare_lines<S: AsRef<str>>(engine: &Engine, fen: &str, lines: &[Vec<S>],
+                                    limits: &SearchLimits) -> Result<Vec<LineComparison>> {
+    let mut compared = vec![];
+    for line in lines {
+        let moves: Vec<String> = line.iter().map(|mv| mv.as_ref().to_string()).collect();
+        engine.make_moves_from_position(fen, &moves)?;
+        let output = engine.search(&limits.go_args())?;
+        let bestmove = best_of(&output)?;
+
+        let last = output.iter()
+            .rev()
+            .filter_map(|line| InfoRef::parse(line))
+            .find(|info| info.score.is_some() && info.multipv.unwrap_or(1) == 1);
+        // The side to move changes with every move of the line.
+        let score = last.and_then(|info| info.score)
+            .map(|score| engine.normalize(score))
+            .map(|score| if moves.len() % 2 == 1 { score.negate() } else { score });
+
+        compared.push(LineComparison {
+            moves,
+            score,
+            depth: last.and_then(|info| info.depth),
+            bestmove,
+        });
+    }
+
+    compared.sort_by_key(|line| std::cmp::Reverse(rank(line.score)));
+    Ok(compared)
+}
+
+/// Orders scores from worst to best: no score, getting mated (later is
+/// better), centipawns, then mating (sooner is better).
+fn rank(score: Option<Score>) -> (u8, i32) {
+    match score {
+        None => (0, 0),
+        Some(Score::Mate(n)) if n <= 0 => (1, -n),
+        Some(Score::Cp(cp)) => (2, cp),
+        Some(Score::Mate(n)) => (3, -n),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank() {
+        let mut scores = vec![Some(Score::Cp(20)), None, Some(Score::Mate(3)), Some(Score::Mate(-2)),
+                              Some(Score::Mate(1)), Some(Score::Cp(-50)), Some(Score::Mate(-5))];
+        scores.sort_by_key(|&score| std::cmp::Reverse(rank(score)));
+        assert_eq!(scores, vec![Some(Score::Mate(1)), Some(Score::Mate(3)), Some(Score::Cp(20)),
+                                Some(Score::Cp(-50)), Some(Score::Mate(-5)), Some(Score::Mate(-2)),
+                                None]);
+    }
+}
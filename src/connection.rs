@@ -0,0 +1,142 @@
+use std::io::{self, Write};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use workdir::WorkDir;
+
+/// How long a process gets to exit by itself once its input is closed,
+/// before it's killed.
+const EXIT_GRACE: Duration = Duration::from_millis(500);
+
+/// The way commands reach an engine: the input of a process the crate
+/// started, or a stream to an engine running elsewhere.
+pub(crate) struct Connection {
+    process: Option<Child>,
+    input: Box<dyn Write + Send>,
+
+    /// The process's temporary working directory, removed along with the
+    /// connection once the process has exited.
+    workdir: Option<WorkDir>,
+
+    /// The container the process runs, when it's a `docker run` client.
+    /// Killing the client leaves its container running, so it's killed too.
+    container: Option<String>,
+}
+
+impl Connection {
+    /// Takes the input of a spawned process.
+    pub(crate) fn spawned(mut process: Child) -> Connection {
+        let input = process.stdin.take().unwrap();
+        Connection::spawned_with(process, input)
+    }
+
+    /// A spawned process whose input was taken already, like for
+    /// translating commands on their way to it.
+    pub(crate) fn spawned_with<W: Write + Send + 'static>(process: Child, input: W) -> Connection {
+        Connection { process: Some(process), input: Box::new(input), workdir: None, container: None }
+    }
+
+    /// Takes ownership of the process's working directory.
+    pub(crate) fn in_workdir(mut self, workdir: Option<WorkDir>) -> Connection {
+        self.workdir = workdir;
+        self
+    }
+
+    /// Names the container the process runs.
+    pub(crate) fn in_container(mut self, container: Option<String>) -> Connection {
+        self.container = container;
+        self
+    }
+
+    /// A connection to an engine the crate didn't start.
+    pub(crate) fn stream<W: Write + Send + 'static>(input: W) -> Connection {
+        Connection { process: None, input: Box::new(input), workdir: None, container: None }
+    }
+
+    pub(crate) fn input(&mut self) -> &mut dyn Write {
+        &mut *self.input
+    }
+
+    /// Kills the engine process. An engine the crate didn't start can't be
+    /// killed, so this does nothing for it.
+    pub(crate) fn kill(&mut self) -> io::Result<()> {
+        let res = match self.process {
+            Some(ref mut process) => process.kill(),
+            None => Ok(()),
+        };
+        if let Some(ref container) = self.container {
+            kill_container(container);
+        }
+        res
+    }
+}
+
+/// Kills a container started with `docker run --name`. Does nothing if it
+/// has stopped already.
+pub(crate) fn kill_container(name: &str) {
+    let _ = Command::new("docker")
+        .args(["kill", name])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+}
+
+impl Drop for Connection {
+    /// Closes the process's input and waits for it to exit, killing it if it
+    /// doesn't within [`EXIT_GRACE`]. The process is reaped before its
+    /// working directory is removed, so nothing still has files open in it.
+    fn drop(&mut self) {
+        let mut process = match self.process.take() {
+            Some(process) => process,
+            None => return,
+        };
+        self.input = Box::new(io::sink());
+
+        let deadline = Instant::now() + EXIT_GRACE;
+        while Instant::now() < deadline {
+            match process.try_wait() {
+                Ok(None) => thread::sleep(Duration::from_millis(10)),
+                // Exited, or can't be waited for at all.
+                _ => return,
+            }
+        }
+        let _ = process.kill();
+        if let Some(ref container) = self.container {
+            kill_container(container);
+        }
+        let _ = process.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+    use std::process::{Command, Stdio};
+
+    #[test]
+    fn test_reaped_before_workdir_removed() {
+        let stockfish = fs::canonicalize("./stockfish").unwrap();
+        for program in &[stockfish.as_path(), Path::new("sleep")] {
+            let workdir = WorkDir::create().unwrap();
+            let path = workdir.path().to_path_buf();
+            let process = Command::new(program)
+                .arg("30")
+                .current_dir(&path)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .spawn()
+                .unwrap();
+            let pid = process.id();
+
+            drop(Connection::spawned(process).in_workdir(Some(workdir)));
+            assert!(!path.exists());
+            if cfg!(target_os = "linux") {
+                assert!(!Path::new(&format!("/proc/{}", pid)).exists());
+            }
+        }
+    }
+}
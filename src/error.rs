@@ -12,7 +12,101 @@ pub enum EngineError {
     /// Engine doesn't recognize the specified option.
     UnknownOption(String),
 
+    /// The value doesn't suit the option as the engine declared it. Holds the
+    /// option's name and what's wrong with the value.
+    InvalidOptionValue(String, String),
+
     NotFound,
+
+    /// Engine sent a line longer than the configured maximum (in bytes).
+    LineTooLong(usize),
+
+    /// The command isn't allowed while the engine is searching.
+    Searching,
+
+    /// The job was cancelled before it ran.
+    Cancelled,
+
+    /// The engine didn't answer in time. Holds the last lines it printed,
+    /// if any were kept.
+    Timeout(Vec<String>),
+
+    /// The engine process exited or closed its output. Holds the last lines
+    /// it printed, if any were kept.
+    ProcessExited(Vec<String>),
+
+    /// The engine failed its copy protection check.
+    CopyProtection,
+
+    /// The engine rejected the registration data, see
+    /// [`EngineBuilder::register`].
+    ///
+    /// [`EngineBuilder::register`]: struct.EngineBuilder.html#method.register
+    Registration,
+
+    /// An error of the engine with the given [`label`]. Holds the label and
+    /// the error; [`unlabelled`] gives the error itself, for matching on it.
+    ///
+    /// Errors of talking to the engine, and all errors returned through a
+    /// pool or a failover engine, carry the label of the engine they came
+    /// from, if it has one.
+    ///
+    /// [`label`]: struct.EngineBuilder.html#method.label
+    /// [`unlabelled`]: #method.unlabelled
+    Labelled(String, Box<EngineError>),
+}
+
+impl EngineError {
+    /// The label of the engine the error came from, if it has one.
+    pub fn label(&self) -> Option<&str> {
+        match *self {
+            EngineError::Labelled(ref label, _) => Some(label),
+            _ => None,
+        }
+    }
+
+    /// The error without the label of its engine.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use uci::{EngineBuilder, EngineError};
+    ///
+    /// match EngineBuilder::new("false").label("sf16-main").build() {
+    ///     Err(e) => {
+    ///         assert_eq!(e.label(), Some("sf16-main"));
+    ///         assert!(matches!(*e.unlabelled(), EngineError::ProcessExited(..)));
+    ///     }
+    ///     Ok(_) => panic!("the engine should have exited"),
+    /// }
+    /// ```
+    pub fn unlabelled(&self) -> &EngineError {
+        match *self {
+            EngineError::Labelled(_, ref err) => err,
+            ref err => err,
+        }
+    }
+
+    /// Attaches the label of the engine the error came from. Errors that
+    /// already have a label keep it.
+    pub(crate) fn labelled(self, label: Option<&str>) -> EngineError {
+        match (label, self) {
+            (_, err @ EngineError::Labelled(..)) => err,
+            (Some(label), err) => EngineError::Labelled(label.to_string(), Box::new(err)),
+            (None, err) => err,
+        }
+    }
+}
+
+/// Appends the last output of the engine to an error message.
+fn write_tail(f: &mut fmt::Formatter, tail: &[String]) -> fmt::Result {
+    if !tail.is_empty() {
+        write!(f, ", last output:")?;
+        for line in tail {
+            write!(f, "\n  {}", line)?;
+        }
+    }
+    Ok(())
 }
 
 impl fmt::Display for EngineError {
@@ -20,25 +114,43 @@ impl fmt::Display for EngineError {
         match *self {
             EngineError::Io(ref err) => write!(f, "IO error: {}", err),
             EngineError::UnknownOption(ref option) => write!(f, "No such option: '{}'", option.as_str()),
+            EngineError::InvalidOptionValue(ref option, ref reason) => {
+                write!(f, "Invalid value for option '{}': {}", option, reason)
+            }
             EngineError::NotFound => write!(f, "Pattern not found"),
+            EngineError::LineTooLong(max) => write!(f, "Engine sent a line longer than {} bytes", max),
+            EngineError::Searching => write!(f, "Engine is searching"),
+            EngineError::Cancelled => write!(f, "Job was cancelled"),
+            EngineError::Timeout(ref tail) => {
+                write!(f, "Engine didn't answer in time")?;
+                write_tail(f, tail)
+            }
+            EngineError::ProcessExited(ref tail) => {
+                write!(f, "Engine exited")?;
+                write_tail(f, tail)
+            }
+            EngineError::CopyProtection => write!(f, "Engine failed its copy protection check"),
+            EngineError::Registration => write!(f, "Engine rejected the registration"),
+            EngineError::Labelled(ref label, ref err) => write!(f, "{}: {}", label, err),
         }
     }
 }
 
 impl std::error::Error for EngineError {
-    fn description(&self) -> &str {
-        match *self {
-            EngineError::Io(ref err) => err.description(),
-            EngineError::UnknownOption(..) => "Unknown option",
-            EngineError::NotFound => "Pattern not found",
-        }
-    }
-
-    fn cause(&self) -> Option<&std::error::Error> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match *self {
             EngineError::Io(ref err) => Some(err),
             EngineError::UnknownOption(..) => None,
+            EngineError::InvalidOptionValue(..) => None,
             EngineError::NotFound => None,
+            EngineError::LineTooLong(..) => None,
+            EngineError::Searching => None,
+            EngineError::Cancelled => None,
+            EngineError::Timeout(..) => None,
+            EngineError::ProcessExited(..) => None,
+            EngineError::CopyProtection => None,
+            EngineError::Registration => None,
+            EngineError::Labelled(_, ref err) => err.source(),
         }
     }
 }
@@ -0,0 +1,338 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use info::InfoRef;
+
+/// Something the engine sent, as delivered to a [`Subscription`].
+///
+/// [`Subscription`]: struct.Subscription.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// An `info` line, without the line terminator. Parse it with
+    /// [`InfoRef::parse`].
+    ///
+    /// [`InfoRef::parse`]: ../struct.InfoRef.html#method.parse
+    Info(String),
+
+    /// The `bestmove` line ending a search.
+    BestMove(String),
+
+    /// An engine of an [`EnginePool`] was replaced. Contains the reason.
+    ///
+    /// [`EnginePool`]: ../pool/struct.EnginePool.html
+    Replaced(String),
+
+    /// A [`FailoverEngine`] started another configuration. Contains the
+    /// reason.
+    ///
+    /// [`FailoverEngine`]: ../failover/struct.FailoverEngine.html
+    FailedOver(String),
+
+    /// The engine took well over the `movetime` of several searches in a
+    /// row, see [`Engine::enforce_movetime`]. Contains the details.
+    ///
+    /// [`Engine::enforce_movetime`]: ../struct.Engine.html#method.enforce_movetime
+    MovetimeIgnored(String),
+}
+
+/// What happens when a subscriber falls behind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelPolicy {
+    /// Queue everything. Memory grows without bound if the subscriber
+    /// stalls during a long search.
+    Unbounded,
+
+    /// Hold at most this many events. When full, the engine is not read from
+    /// until the subscriber catches up.
+    Block(usize),
+
+    /// Hold at most this many events, discarding the oldest when full.
+    DropOldest(usize),
+
+    /// Hold at most this many events. A queued `info` line carrying a pv is
+    /// replaced by a newer one for the same depth and `multipv` index, and
+    /// the oldest event is discarded when the queue is still full.
+    Coalesce(usize),
+
+    /// Pass at most this many `info` lines per second for every `multipv`
+    /// index, discarding the others, for subscribers that only redraw a
+    /// display. The first line of every new depth and `bestmove` always pass.
+    Sample(u32),
+}
+
+struct State {
+    events: VecDeque<Event>,
+    dropped: u64,
+    closed: bool,
+
+    /// When a line last passed, and its depth, by `multipv` index.
+    sampled: BTreeMap<u32, (Instant, Option<u32>)>,
+}
+
+impl State {
+    /// Whether an event passes the rate of a `Sample` policy.
+    fn sample(&mut self, event: &Event, per_second: u32) -> bool {
+        let info = match *event {
+            Event::Info(ref line) => match InfoRef::parse(line) {
+                Some(info) => info,
+                None => return true,
+            },
+            _ => return true,
+        };
+
+        let now = Instant::now();
+        let interval = Duration::from_secs(1) / per_second.max(1);
+        let index = info.multipv.unwrap_or(1);
+        let passes = match self.sampled.get(&index) {
+            None => true,
+            Some(&(at, depth)) => info.depth > depth || now.duration_since(at) >= interval,
+        };
+        if passes {
+            let depth = self.sampled.get(&index).and_then(|&(_, depth)| depth).max(info.depth);
+            self.sampled.insert(index, (now, depth));
+        }
+        passes
+    }
+}
+
+/// The queue shared between the engine and one subscriber.
+pub(crate) struct Queue {
+    policy: ChannelPolicy,
+    state: Mutex<State>,
+    not_empty: Condvar,
+    not_full: Condvar,
+}
+
+impl Queue {
+    fn new(policy: ChannelPolicy) -> Queue {
+        Queue {
+            policy,
+            state: Mutex::new(State {
+                events: VecDeque::new(),
+                dropped: 0,
+                closed: false,
+                sampled: BTreeMap::new(),
+            }),
+            not_empty: Condvar::new(),
+            not_full: Condvar::new(),
+        }
+    }
+
+    /// Queues an event according to the policy. Returns false once the
+    /// subscriber is gone.
+    pub(crate) fn push(&self, event: Event) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match self.policy {
+            ChannelPolicy::Unbounded => {}
+            ChannelPolicy::Block(cap) => {
+                while !state.closed && state.events.len() >= cap.max(1) {
+                    state = self.not_full.wait(state).unwrap();
+                }
+            }
+            ChannelPolicy::DropOldest(cap) => {
+                while state.events.len() >= cap.max(1) {
+                    state.events.pop_front();
+                    state.dropped += 1;
+                }
+            }
+            ChannelPolicy::Coalesce(cap) => {
+                if let Some(key) = coalesce_key(&event) {
+                    if let Some(i) = state.events.iter().position(|e| coalesce_key(e) == Some(key)) {
+                        state.events.remove(i);
+                        state.dropped += 1;
+                    }
+                }
+                while state.events.len() >= cap.max(1) {
+                    state.events.pop_front();
+                    state.dropped += 1;
+                }
+            }
+            ChannelPolicy::Sample(per_second) => {
+                if let Event::BestMove(..) = event {
+                    state.sampled.clear();
+                } else if !state.sample(&event, per_second) {
+                    state.dropped += 1;
+                    return !state.closed;
+                }
+            }
+        }
+        if state.closed {
+            return false;
+        }
+
+        state.events.push_back(event);
+        self.not_empty.notify_one();
+        true
+    }
+
+    /// Marks the queue as closed, waking up anyone waiting on it.
+    pub(crate) fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.not_empty.notify_all();
+        self.not_full.notify_all();
+    }
+}
+
+/// Depth and `multipv` index of an `info` line with a pv.
+fn coalesce_key(event: &Event) -> Option<(u32, u32)> {
+    match *event {
+        Event::Info(ref line) => {
+            let info = InfoRef::parse(line)?;
+            info.pv?;
+            Some((info.depth?, info.multipv.unwrap_or(1)))
+        }
+        Event::BestMove(..) | Event::Replaced(..) | Event::FailedOver(..)
+        | Event::MovetimeIgnored(..) => None,
+    }
+}
+
+/// Receiving end of the events of an engine, see [`Engine::subscribe`].
+///
+/// Can be moved to another thread. Dropping it unsubscribes.
+///
+/// [`Engine::subscribe`]: ../struct.Engine.html#method.subscribe
+pub struct Subscription {
+    queue: Arc<Queue>,
+    label: Option<String>,
+}
+
+impl Subscription {
+    pub(crate) fn new(policy: ChannelPolicy) -> (Subscription, Arc<Queue>) {
+        let queue = Arc::new(Queue::new(policy));
+        (Subscription { queue: queue.clone(), label: None }, queue)
+    }
+
+    pub(crate) fn labelled(mut self, label: Option<&str>) -> Subscription {
+        self.label = label.map(str::to_string);
+        self
+    }
+
+    /// The [`label`] of the engine, or the engines of the pool, the events
+    /// come from.
+    ///
+    /// [`label`]: ../struct.EngineBuilder.html#method.label
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Waits for the next event. Returns `None` once the engine is dropped
+    /// and all queued events have been received.
+    pub fn recv(&self) -> Option<Event> {
+        let mut state = self.queue.state.lock().unwrap();
+        loop {
+            if let Some(event) = state.events.pop_front() {
+                self.queue.not_full.notify_one();
+                return Some(event);
+            }
+            if state.closed {
+                return None;
+            }
+            state = self.queue.not_empty.wait(state).unwrap();
+        }
+    }
+
+    /// Like [`recv`], but gives up after `timeout`.
+    ///
+    /// [`recv`]: #method.recv
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<Event> {
+        let state = self.queue.state.lock().unwrap();
+        let (mut state, _) = self.queue.not_empty
+            .wait_timeout_while(state, timeout, |s| s.events.is_empty() && !s.closed)
+            .unwrap();
+        let event = state.events.pop_front();
+        if event.is_some() {
+            self.queue.not_full.notify_one();
+        }
+        event
+    }
+
+    /// Returns the next event if one is queued, without waiting.
+    pub fn try_recv(&self) -> Option<Event> {
+        let event = self.queue.state.lock().unwrap().events.pop_front();
+        if event.is_some() {
+            self.queue.not_full.notify_one();
+        }
+        event
+    }
+
+    /// Number of events discarded so far because the queue was full or, with
+    /// [`ChannelPolicy::Coalesce`], because a newer line superseded them.
+    ///
+    /// [`ChannelPolicy::Coalesce`]: enum.ChannelPolicy.html#variant.Coalesce
+    pub fn dropped(&self) -> u64 {
+        self.queue.state.lock().unwrap().dropped
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.queue.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(n: u32) -> Event {
+        Event::Info(format!("info depth {}", n))
+    }
+
+    #[test]
+    fn test_drop_oldest() {
+        let (sub, queue) = Subscription::new(ChannelPolicy::DropOldest(2));
+        for n in 1..5 {
+            assert!(queue.push(info(n)));
+        }
+
+        assert_eq!(sub.dropped(), 2);
+        assert_eq!(sub.try_recv(), Some(info(3)));
+        assert_eq!(sub.try_recv(), Some(info(4)));
+        assert_eq!(sub.try_recv(), None);
+    }
+
+    #[test]
+    fn test_coalesce() {
+        let (sub, queue) = Subscription::new(ChannelPolicy::Coalesce(10));
+        let pv = |depth, multipv, mv| {
+            Event::Info(format!("info depth {} multipv {} score cp 0 pv {}", depth, multipv, mv))
+        };
+        queue.push(pv(5, 1, "e2e4"));
+        queue.push(pv(5, 2, "d2d4"));
+        queue.push(info(5));
+        queue.push(pv(5, 1, "g1f3"));
+
+        assert_eq!(sub.dropped(), 1);
+        assert_eq!(sub.try_recv(), Some(pv(5, 2, "d2d4")));
+        assert_eq!(sub.try_recv(), Some(info(5)));
+        assert_eq!(sub.try_recv(), Some(pv(5, 1, "g1f3")));
+    }
+
+    #[test]
+    fn test_sample() {
+        let (sub, queue) = Subscription::new(ChannelPolicy::Sample(1));
+        queue.push(Event::Info("info depth 5 multipv 1 pv e2e4".to_string()));
+        queue.push(Event::Info("info depth 5 multipv 1 pv d2d4".to_string()));
+        queue.push(Event::Info("info depth 5 multipv 2 pv d2d4".to_string()));
+        queue.push(Event::Info("info depth 6 multipv 1 pv g1f3".to_string()));
+        queue.push(Event::BestMove("bestmove g1f3".to_string()));
+
+        assert_eq!(sub.dropped(), 1);
+        let received: Vec<Event> = (0..4).filter_map(|_| sub.try_recv()).collect();
+        assert_eq!(received.len(), 4);
+        assert_eq!(received[3], Event::BestMove("bestmove g1f3".to_string()));
+    }
+
+    #[test]
+    fn test_closed_queue() {
+        let (sub, queue) = Subscription::new(ChannelPolicy::Block(1));
+        assert!(queue.push(info(1)));
+        queue.close();
+
+        assert_eq!(sub.recv(), Some(info(1)));
+        assert_eq!(sub.recv(), None);
+        drop(sub);
+        assert!(!queue.push(info(2)));
+    }
+}
@@ -0,0 +1,213 @@
+use std::sync::Arc;
+
+use builder::EngineBuilder;
+use error::{EngineError, Result};
+use events::{ChannelPolicy, Event, Queue, Subscription};
+use Engine;
+
+/// Failures in a row after which the next configuration is used.
+const DEFAULT_MAX_FAILURES: u32 = 3;
+
+/// An engine that switches to a fallback configuration when the primary
+/// one keeps crashing or timing out, so an analysis service degrades rather
+/// than stops.
+///
+/// A crashed or unresponsive engine is restarted with the same configuration
+/// until it failed [`max_failures`] times in a row, then the next one is
+/// started and an [`Event::FailedOver`] is sent to subscribers. The last
+/// configuration is kept restarting.
+///
+/// # Examples
+///
+/// ```
+/// use uci::EngineBuilder;
+/// use uci::failover::FailoverEngine;
+///
+/// let mut engine = FailoverEngine::new(EngineBuilder::new("stockfish"),
+///                                      vec![EngineBuilder::new("stockfish").arg("--safe-mode")])
+///     .unwrap();
+/// let mv = engine.run(|engine| engine.bestmove()).unwrap();
+/// ```
+///
+/// [`max_failures`]: #method.max_failures
+/// [`Event::FailedOver`]: ../events/enum.Event.html#variant.FailedOver
+pub struct FailoverEngine {
+    /// The primary configuration, then the fallbacks.
+    builders: Vec<EngineBuilder>,
+    active: usize,
+    engine: Engine,
+    failures: u32,
+    max_failures: u32,
+    subscribers: Vec<Arc<Queue>>,
+}
+
+impl FailoverEngine {
+    /// Starts the first configuration that makes it through the handshake.
+    ///
+    /// # Arguments
+    ///
+    /// * `primary`   - The configuration to use while it works.
+    /// * `fallbacks` - The configurations to switch to, in order.
+    pub fn new(primary: EngineBuilder, fallbacks: Vec<EngineBuilder>) -> Result<FailoverEngine> {
+        let mut builders = vec![primary];
+        builders.extend(fallbacks);
+
+        let mut last_error = None;
+        for (active, builder) in builders.iter().enumerate() {
+            match builder.clone().build() {
+                Ok(engine) => {
+                    return Ok(FailoverEngine {
+                        builders,
+                        active,
+                        engine,
+                        failures: 0,
+                        max_failures: DEFAULT_MAX_FAILURES,
+                        subscribers: vec![],
+                    });
+                }
+                Err(e) => {
+                    warn!("Engine configuration {} could not be started: {}", active, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap())
+    }
+
+    /// Changes how many times in a row a configuration may fail before the
+    /// next one is used. Defaults to 3.
+    pub fn max_failures(mut self, failures: u32) -> FailoverEngine {
+        self.max_failures = failures.max(1);
+        self
+    }
+
+    /// Returns a [`Subscription`] receiving an [`Event::FailedOver`] every
+    /// time another configuration is started.
+    ///
+    /// [`Subscription`]: ../events/struct.Subscription.html
+    /// [`Event::FailedOver`]: ../events/enum.Event.html#variant.FailedOver
+    pub fn subscribe(&mut self, policy: ChannelPolicy) -> Subscription {
+        let (subscription, queue) = Subscription::new(policy);
+        self.subscribers.push(queue);
+        subscription
+    }
+
+    /// Runs `f` on the engine. If the engine crashes or times out, it's
+    /// restarted, switching configurations as needed, and `f` is run again.
+    ///
+    /// Other errors are returned as they are. So is the failure that is one
+    /// too many for the last configuration.
+    pub fn run<F, T>(&mut self, f: F) -> Result<T>
+        where F: Fn(&Engine) -> Result<T>
+    {
+        loop {
+            match f(&self.engine).map_err(|e| e.labelled(self.engine.label())) {
+                Ok(res) => {
+                    self.failures = 0;
+                    return Ok(res);
+                }
+                Err(e) if is_crash(&e) => self.recover(e)?,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Restarts the engine after a failure, with the next configuration
+    /// once the current one has failed too often.
+    fn recover(&mut self, mut error: EngineError) -> Result<()> {
+        loop {
+            self.failures += 1;
+            if self.failures >= self.max_failures {
+                if self.active + 1 == self.builders.len() {
+                    return Err(error);
+                }
+                self.active += 1;
+                self.failures = 0;
+                let configuration = match self.builders[self.active].label_ref() {
+                    Some(label) => format!("configuration {} ({})", self.active, label),
+                    None => format!("configuration {}", self.active),
+                };
+                let reason = format!("switched to {} after: {}", configuration, error);
+                warn!("Engine failed over, {}", reason);
+                let event = Event::FailedOver(reason);
+                self.subscribers.retain(|queue| queue.push(event.clone()));
+            }
+
+            self.engine.kill();
+            match self.builders[self.active].clone().build() {
+                Ok(engine) => {
+                    self.engine = engine;
+                    return Ok(());
+                }
+                Err(e) => error = e,
+            }
+        }
+    }
+
+    /// The engine in use.
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    /// Index of the configuration in use: 0 for the primary one, then the
+    /// fallbacks in order.
+    pub fn active(&self) -> usize {
+        self.active
+    }
+}
+
+impl Drop for FailoverEngine {
+    fn drop(&mut self) {
+        for queue in &self.subscribers {
+            queue.close();
+        }
+    }
+}
+
+/// Whether the error means the engine can't be talked to anymore.
+fn is_crash(error: &EngineError) -> bool {
+    matches!(*error.unlabelled(), EngineError::ProcessExited(..) | EngineError::Timeout(..) | EngineError::Io(..))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    #[test]
+    fn test_failover() {
+        let mut engine = FailoverEngine::new(EngineBuilder::new("false"),
+                                             vec![EngineBuilder::new("./stockfish")])
+            .unwrap();
+        assert_eq!(engine.active(), 1);
+        engine.run(|engine| engine.bestmove()).unwrap();
+
+        let mut engine = FailoverEngine::new(EngineBuilder::new("./stockfish"),
+                                             vec![EngineBuilder::new("./stockfish")])
+            .unwrap()
+            .max_failures(2);
+        let events = engine.subscribe(ChannelPolicy::Unbounded);
+        let crashes = Cell::new(0);
+        let mv = engine.run(|engine| {
+            crashes.set(crashes.get() + 1);
+            if crashes.get() <= 2 {
+                return Err(EngineError::ProcessExited(vec![]));
+            }
+            engine.bestmove()
+        });
+
+        assert!(mv.is_ok());
+        assert_eq!(engine.active(), 1);
+        match events.try_recv() {
+            Some(Event::FailedOver(..)) => {}
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        let mut engine = FailoverEngine::new(EngineBuilder::new("./stockfish").label("sf-main"), vec![])
+            .unwrap();
+        let err = engine.run(|_| Err::<(), _>(EngineError::NotFound)).unwrap_err();
+        assert_eq!(err.label(), Some("sf-main"));
+        assert_eq!(err.to_string(), "sf-main: Pattern not found");
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use error::{EngineError, Result};
+use Engine;
+
+/// Why a game is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawReason {
+    /// The same position occurred three times (claimable) or five times
+    /// (forced).
+    Repetition,
+
+    /// No capture or pawn move for fifty moves (claimable) or seventy-five
+    /// moves (forced).
+    FiftyMoves,
+}
+
+/// Whether a game can or must be drawn, see [`Game::draw`].
+///
+/// [`Game::draw`]: struct.Game.html#method.draw
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawStatus {
+    /// Play goes on.
+    None,
+
+    /// A player may claim a draw.
+    Claimable(DrawReason),
+
+    /// The game is drawn, whatever the players do.
+    Forced(DrawReason),
+}
+
+/// A game being played move by move, keeping track of the repetitions and
+/// the halfmove clock needed to adjudicate draws.
+///
+/// Engines don't report these draws themselves. The positions are read back
+/// from the engine with its `d` command after every move, which Stockfish
+/// supports.
+///
+/// # Examples
+///
+/// ```
+/// use uci::game::DrawStatus;
+///
+/// let engine = uci::Engine::new("stockfish").unwrap();
+/// let mut game = engine.game("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+/// for mv in &["g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6", "f3g1", "f6g8"] {
+///     game.play(mv).unwrap();
+/// }
+/// println!("{:?}", game.draw());
+/// ```
+pub struct Game<'a> {
+    engine: &'a Engine,
+    start: String,
+    moves: Vec<String>,
+    fen: String,
+    repetitions: HashMap<String, u32>,
+}
+
+impl<'a> Game<'a> {
+    pub(crate) fn new(engine: &'a Engine, fen: &str) -> Result<Game<'a>> {
+        engine.set_position(fen)?;
+        let mut game = Game {
+            engine,
+            start: fen.to_string(),
+            moves: vec![],
+            fen: fen.to_string(),
+            repetitions: HashMap::new(),
+        };
+        game.record(fen.to_string());
+        Ok(game)
+    }
+
+    /// Plays a move and sets the engine to the resulting position.
+    ///
+    /// # Arguments
+    ///
+    /// * `mv` - The move, in Coordinate notation.
+    pub fn play(&mut self, mv: &str) -> Result<()> {
+        let mut moves = self.moves.clone();
+        moves.push(mv.to_string());
+        self.engine.make_moves_from_position(&self.start, &moves)?;
+
+        let board = self.engine.command("d")?;
+        let fen = board.lines()
+            .find_map(|line| line.trim().strip_prefix("Fen:"))
+            .ok_or(EngineError::NotFound)?
+            .trim()
+            .to_string();
+        // Only now that the engine played it is the move part of the game.
+        self.moves = moves;
+        self.record(fen);
+        Ok(())
+    }
+
+    fn record(&mut self, fen: String) {
+        *self.repetitions.entry(position_key(&fen)).or_insert(0) += 1;
+        self.fen = fen;
+    }
+
+    /// The current position, in FEN notation.
+    pub fn fen(&self) -> &str {
+        &self.fen
+    }
+
+    /// The moves played so far.
+    pub fn moves(&self) -> &[String] {
+        &self.moves
+    }
+
+    /// Number of times the current position has occurred.
+    pub fn repetitions(&self) -> u32 {
+        self.repetitions.get(&position_key(&self.fen)).cloned().unwrap_or(0)
+    }
+
+    /// Number of halfmoves since the last capture or pawn move.
+    pub fn halfmove_clock(&self) -> u32 {
+        self.fen.split_whitespace().nth(4).and_then(|n| n.parse().ok()).unwrap_or(0)
+    }
+
+    /// Tells whether the game can or must be drawn by repetition or by the
+    /// fifty-move rule. Forced draws take precedence over claimable ones.
+    pub fn draw(&self) -> DrawStatus {
+        let repetitions = self.repetitions();
+        let halfmoves = self.halfmove_clock();
+
+        if repetitions >= 5 {
+            DrawStatus::Forced(DrawReason::Repetition)
+        } else if halfmoves >= 150 {
+            DrawStatus::Forced(DrawReason::FiftyMoves)
+        } else if repetitions >= 3 {
+            DrawStatus::Claimable(DrawReason::Repetition)
+        } else if halfmoves >= 100 {
+            DrawStatus::Claimable(DrawReason::FiftyMoves)
+        } else {
+            DrawStatus::None
+        }
+    }
+}
+
+/// The part of a FEN that makes positions the same for repetitions: pieces,
+/// side to move, castling rights and en passant square.
+fn position_key(fen: &str) -> String {
+    fen.split_whitespace().take(4).collect::<Vec<&str>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    #[test]
+    fn test_position_key() {
+        assert_eq!(position_key("8/8/8/8/8/8/8/K1k5 w - - 12 40"), "8/8/8/8/8/8/8/K1k5 w - -");
+        assert_eq!(position_key("8/8/8/8/8/8/8/K1k5 w - - 15 42"),
+                   position_key("8/8/8/8/8/8/8/K1k5 w - - 12 40"));
+    }
+
+    #[test]
+    fn test_failed_move() {
+        // The board is shown without its FEN, then the engine is gone.
+        let output = "uciok\nreadyok\nreadyok\n";
+        let engine = Engine::attach(io::sink(), io::Cursor::new(output.as_bytes().to_vec())).unwrap();
+        let mut game = engine.game("8/8/8/8/8/8/8/K1k5 w - - 0 1").unwrap();
+
+        assert!(matches!(game.play("a1a2"), Err(EngineError::NotFound)));
+        assert!(game.play("a1b1").is_err());
+        assert!(game.moves().is_empty());
+        assert_eq!(game.repetitions(), 1);
+    }
+}
@@ -0,0 +1,372 @@
+use std::str::SplitWhitespace;
+
+use score::{Score, Wdl};
+
+/// Fields of `info` lines, which end the lists of moves of those that have
+/// no fixed length.
+const KEYS: [&str; 18] = ["depth", "seldepth", "time", "nodes", "pv", "multipv", "score", "currmove",
+                          "currmovenumber", "hashfull", "nps", "tbhits", "sbhits", "cpuload", "string",
+                          "refutation", "currline", "wdl"];
+
+/// A parsed `info` line that borrows from the line it was parsed from.
+///
+/// Parsing doesn't allocate, which matters when consuming the output of a
+/// fast engine during long searches. Fields the engine didn't send are `None`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InfoRef<'a> {
+    pub depth: Option<u32>,
+    pub seldepth: Option<u32>,
+    pub multipv: Option<u32>,
+    pub score: Option<Score>,
+
+    /// The score is only a lower bound (the engine failed high).
+    pub lowerbound: bool,
+
+    /// The score is only an upper bound (the engine failed low).
+    pub upperbound: bool,
+
+    /// Win, draw and loss chances, sent by engines with `UCI_ShowWDL`
+    /// enabled.
+    pub wdl: Option<Wdl>,
+
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+
+    /// Search time in milliseconds.
+    pub time: Option<u64>,
+
+    /// Hash table usage in permill.
+    pub hashfull: Option<u32>,
+    pub tbhits: Option<u64>,
+    pub currmove: Option<&'a str>,
+    pub currmovenumber: Option<u32>,
+
+    /// The principal variation, as the space separated moves sent by the
+    /// engine. See [`pv_moves`].
+    ///
+    /// [`pv_moves`]: #method.pv_moves
+    pub pv: Option<&'a str>,
+
+    /// The line the engine is searching, as the space separated moves sent
+    /// by engines with `UCI_ShowCurrLine` enabled. See [`currline_moves`].
+    ///
+    /// [`currline_moves`]: #method.currline_moves
+    pub currline: Option<&'a str>,
+
+    /// The CPU searching the `currline`, for engines using several.
+    pub currline_cpu: Option<u32>,
+
+    /// Free-form text sent with `info string`.
+    pub string: Option<&'a str>,
+}
+
+impl<'a> InfoRef<'a> {
+    /// Parses an `info` line. Returns `None` for any other kind of line.
+    ///
+    /// Unknown fields are skipped. `pv` and `string` take the rest of the
+    /// line, as the protocol specifies.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use uci::{InfoRef, Score};
+    ///
+    /// let info = InfoRef::parse("info depth 12 score cp 31 nodes 5000 pv e2e4 e7e5").unwrap();
+    /// assert_eq!(info.depth, Some(12));
+    /// assert_eq!(info.score, Some(Score::Cp(31)));
+    /// assert_eq!(info.pv_moves().collect::<Vec<_>>(), vec!["e2e4", "e7e5"]);
+    /// ```
+    pub fn parse(line: &'a str) -> Option<InfoRef<'a>> {
+        let mut tokens = Tokens { line: line.trim_end(), pos: 0 };
+        if tokens.next()? != "info" {
+            return None;
+        }
+
+        let mut info = InfoRef::default();
+        while let Some(key) = tokens.next() {
+            match key {
+                "depth" => info.depth = tokens.value(),
+                "seldepth" => info.seldepth = tokens.value(),
+                "multipv" => info.multipv = tokens.value(),
+                "nodes" => info.nodes = tokens.value(),
+                "nps" => info.nps = tokens.value(),
+                "time" => info.time = tokens.value(),
+                "hashfull" => info.hashfull = tokens.value(),
+                "tbhits" => info.tbhits = tokens.value(),
+                "currmove" => info.currmove = tokens.next(),
+                "currmovenumber" => info.currmovenumber = tokens.value(),
+                "score" => {
+                    if let (Some(kind), Some(value)) = (tokens.next(), tokens.next()) {
+                        info.score = Score::parse(kind, value);
+                    }
+                    match tokens.peek() {
+                        Some("lowerbound") => info.lowerbound = true,
+                        Some("upperbound") => info.upperbound = true,
+                        _ => continue,
+                    }
+                    tokens.next();
+                }
+                "wdl" => {
+                    if let (Some(win), Some(draw), Some(loss)) = (tokens.value(), tokens.value(), tokens.value()) {
+                        info.wdl = Some(Wdl { win, draw, loss });
+                    }
+                }
+                "currline" => {
+                    // The CPU number is left out by engines using only one.
+                    if tokens.peek().is_some_and(|t| t.bytes().all(|b| b.is_ascii_digit())) {
+                        info.currline_cpu = tokens.value();
+                    }
+                    info.currline = tokens.until_key();
+                }
+                "pv" => {
+                    info.pv = tokens.rest();
+                    break;
+                }
+                "string" => {
+                    info.string = tokens.rest();
+                    break;
+                }
+                _ => {}
+            }
+        }
+
+        Some(info)
+    }
+
+    /// Returns the moves of the principal variation.
+    pub fn pv_moves(&self) -> SplitWhitespace<'a> {
+        self.pv.unwrap_or("").split_whitespace()
+    }
+
+    /// Returns the moves of the line being searched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use uci::InfoRef;
+    ///
+    /// let info = InfoRef::parse("info currline 2 e2e4 e7e5 g1f3 depth 12").unwrap();
+    /// assert_eq!(info.currline_cpu, Some(2));
+    /// assert_eq!(info.currline_moves().collect::<Vec<_>>(), vec!["e2e4", "e7e5", "g1f3"]);
+    /// assert_eq!(info.depth, Some(12));
+    /// ```
+    pub fn currline_moves(&self) -> SplitWhitespace<'a> {
+        self.currline.unwrap_or("").split_whitespace()
+    }
+
+    /// The root move the line says the engine is searching, if it names one.
+    pub fn current_move(&self) -> Option<CurrentMove> {
+        Some(CurrentMove {
+            mv: self.currmove?.to_string(),
+            number: self.currmovenumber,
+            depth: self.depth,
+        })
+    }
+
+    /// Copies the fields out of the line into an [`Info`].
+    ///
+    /// [`Info`]: struct.Info.html
+    pub fn to_info(&self) -> Info {
+        Info {
+            depth: self.depth,
+            seldepth: self.seldepth,
+            multipv: self.multipv,
+            score: self.score,
+            lowerbound: self.lowerbound,
+            upperbound: self.upperbound,
+            wdl: self.wdl,
+            nodes: self.nodes,
+            nps: self.nps,
+            time: self.time,
+            hashfull: self.hashfull,
+            tbhits: self.tbhits,
+            currmove: self.currmove.map(str::to_string),
+            currmovenumber: self.currmovenumber,
+            pv: self.pv_moves().map(str::to_string).collect(),
+            currline: self.currline_moves().map(str::to_string).collect(),
+            currline_cpu: self.currline_cpu,
+            string: self.string.map(str::to_string),
+        }
+    }
+}
+
+/// A parsed `info` line that owns its fields, for keeping beyond the line,
+/// as when handed out by [`Engine::bestmove_with_info`].
+///
+/// Parsed as an [`InfoRef`], so fields the engine didn't send are `None`,
+/// and an empty `pv` means the line had none.
+///
+/// # Examples
+///
+/// ```
+/// use uci::{Info, Score};
+///
+/// let info = Info::parse("info depth 12 score cp 31 wdl 250 700 50 pv e2e4 e7e5").unwrap();
+/// assert_eq!(info.score, Some(Score::Cp(31)));
+/// assert_eq!(info.pv, vec!["e2e4", "e7e5"]);
+/// ```
+///
+/// [`Engine::bestmove_with_info`]: struct.Engine.html#method.bestmove_with_info
+/// [`InfoRef`]: struct.InfoRef.html
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Info {
+    pub depth: Option<u32>,
+    pub seldepth: Option<u32>,
+    pub multipv: Option<u32>,
+    pub score: Option<Score>,
+
+    /// The score is only a lower bound (the engine failed high).
+    pub lowerbound: bool,
+
+    /// The score is only an upper bound (the engine failed low).
+    pub upperbound: bool,
+
+    /// Win, draw and loss chances, sent by engines with `UCI_ShowWDL`
+    /// enabled.
+    pub wdl: Option<Wdl>,
+
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+
+    /// Search time in milliseconds.
+    pub time: Option<u64>,
+
+    /// Hash table usage in permill.
+    pub hashfull: Option<u32>,
+    pub tbhits: Option<u64>,
+    pub currmove: Option<String>,
+    pub currmovenumber: Option<u32>,
+
+    /// The moves of the principal variation.
+    pub pv: Vec<String>,
+
+    /// The moves of the line being searched.
+    pub currline: Vec<String>,
+
+    /// The CPU searching the `currline`, for engines using several.
+    pub currline_cpu: Option<u32>,
+
+    /// Free-form text sent with `info string`.
+    pub string: Option<String>,
+}
+
+/// The root move an engine is searching, from the `currmove` and
+/// `currmovenumber` fields of its `info` lines, see
+/// [`Engine::bestmove_with_currmove`].
+///
+/// [`Engine::bestmove_with_currmove`]: struct.Engine.html#method.bestmove_with_currmove
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CurrentMove {
+    /// The move, in Coordinate notation.
+    pub mv: String,
+
+    /// Position of the move in the engine's order of root moves, starting
+    /// at 1.
+    pub number: Option<u32>,
+
+    /// Depth of the iteration the move is searched in.
+    pub depth: Option<u32>,
+}
+
+impl Info {
+    /// Parses an `info` line. Returns `None` for any other kind of line.
+    pub fn parse(line: &str) -> Option<Info> {
+        InfoRef::parse(line).map(|info| info.to_info())
+    }
+}
+
+/// Whitespace tokenizer that can hand out the unparsed rest of the line.
+struct Tokens<'a> {
+    line: &'a str,
+    pos: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn next(&mut self) -> Option<&'a str> {
+        let rest = &self.line[self.pos..];
+        let start = rest.len() - rest.trim_start().len();
+        let rest = &rest[start..];
+        if rest.is_empty() {
+            return None;
+        }
+        let len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        self.pos += start + len;
+        Some(&rest[..len])
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        Tokens { line: self.line, pos: self.pos }.next()
+    }
+
+    fn value<T: ::std::str::FromStr>(&mut self) -> Option<T> {
+        self.next().and_then(|t| t.parse().ok())
+    }
+
+    /// The tokens up to the next field of the line, as one slice.
+    fn until_key(&mut self) -> Option<&'a str> {
+        let start = self.pos;
+        let mut end = self.pos;
+        while let Some(token) = self.peek() {
+            if KEYS.contains(&token) {
+                break;
+            }
+            self.next();
+            end = self.pos;
+        }
+        let moves = self.line[start..end].trim();
+        if moves.is_empty() { None } else { Some(moves) }
+    }
+
+    fn rest(&mut self) -> Option<&'a str> {
+        let rest = self.line[self.pos..].trim();
+        self.pos = self.line.len();
+        if rest.is_empty() { None } else { Some(rest) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_full_line() {
+        let line = "info depth 25 seldepth 34 multipv 2 score mate -3 upperbound wdl 0 12 988 nodes 18521596 \
+                    nps 853018 hashfull 990 tbhits 0 time 21713 pv d2d3 e7e5\n";
+        let info = InfoRef::parse(line).unwrap();
+
+        assert_eq!(info.seldepth, Some(34));
+        assert_eq!(info.multipv, Some(2));
+        assert_eq!(info.score, Some(Score::Mate(-3)));
+        assert!(info.upperbound && !info.lowerbound);
+        assert_eq!(info.wdl, Some(Wdl { win: 0, draw: 12, loss: 988 }));
+        assert_eq!(info.nodes, Some(18521596));
+        assert_eq!(info.hashfull, Some(990));
+        assert_eq!(info.time, Some(21713));
+        assert_eq!(info.pv, Some("d2d3 e7e5"));
+    }
+
+    #[test]
+    fn test_parse_other_lines() {
+        assert_eq!(InfoRef::parse("bestmove e2e4"), None);
+
+        let info = InfoRef::parse("info string NNUE evaluation enabled").unwrap();
+        assert_eq!(info.string, Some("NNUE evaluation enabled"));
+        assert_eq!(info.depth, None);
+
+        let info = InfoRef::parse("info depth 3 currmove g1f3 currmovenumber 2").unwrap();
+        assert_eq!(info.currmove, Some("g1f3"));
+        assert_eq!(info.currmovenumber, Some(2));
+
+        assert_eq!(info.current_move(),
+                   Some(CurrentMove { mv: "g1f3".to_string(), number: Some(2), depth: Some(3) }));
+        let owned = info.to_info();
+        assert_eq!(owned.currmove.as_deref(), Some("g1f3"));
+        assert!(owned.pv.is_empty());
+
+        let info = Info::parse("info depth 5 currline d2d4 g8f6 nodes 900").unwrap();
+        assert_eq!(info.currline, vec!["d2d4", "g8f6"]);
+        assert_eq!(info.currline_cpu, None);
+        assert_eq!(info.nodes, Some(900));
+        assert_eq!(InfoRef::parse("info currline 1").unwrap().currline, None);
+    }
+}
@@ -0,0 +1,98 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use connection::Connection;
+use error::Result;
+
+/// Stops the searches of an [`Engine`] from another thread, see
+/// [`Engine::interrupter`].
+///
+/// [`Engine`]: struct.Engine.html
+/// [`Engine::interrupter`]: struct.Engine.html#method.interrupter
+#[derive(Clone)]
+pub struct Interrupter {
+    engine: Arc<Mutex<Connection>>,
+    searching: Arc<AtomicBool>,
+    requested: Arc<AtomicBool>,
+}
+
+impl Interrupter {
+    pub(crate) fn new(engine: Arc<Mutex<Connection>>) -> Interrupter {
+        Interrupter {
+            engine,
+            searching: Arc::new(AtomicBool::new(false)),
+            requested: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Sends `stop`, making the running search end early with what the
+    /// engine found so far. The blocking call waiting for the search returns
+    /// normally, with that partial result.
+    ///
+    /// Does nothing if no search is running, so a late interrupt doesn't cut
+    /// the next search short. One coming in while a search starts, before
+    /// the engine got its `go`, still stops it.
+    pub fn interrupt(&self) -> Result<()> {
+        let mut engine = self.engine.lock().unwrap();
+        if !self.searching.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        self.requested.store(true, Ordering::SeqCst);
+        engine.input().write_all(b"stop\n")?;
+        engine.input().flush()?;
+        Ok(())
+    }
+
+    /// Marks a search as started, before its `go` is sent. Taking the lock
+    /// `interrupt` holds orders the two: an interrupt either sees no search
+    /// and does nothing, or sees this one and is latched.
+    pub(crate) fn start_search(&self) {
+        let _engine = self.engine.lock().unwrap();
+        self.requested.store(false, Ordering::SeqCst);
+        self.searching.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether an interrupt came in since the search started, clearing it.
+    pub(crate) fn take_request(&self) -> bool {
+        self.requested.swap(false, Ordering::SeqCst)
+    }
+
+    /// Marks the search as over and tells whether it was interrupted.
+    pub(crate) fn finish_search(&self) -> bool {
+        let _engine = self.engine.lock().unwrap();
+        self.searching.store(false, Ordering::SeqCst);
+        self.take_request()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use Engine;
+
+    #[test]
+    fn test_interrupt() {
+        let engine = Engine::new("./stockfish").unwrap();
+        let interrupter = engine.interrupter();
+        let stopper = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            interrupter.interrupt().unwrap();
+        });
+
+        let lines = engine.search("infinite").unwrap();
+        assert!(lines.last().unwrap().starts_with("bestmove"));
+        stopper.join().unwrap();
+    }
+
+    #[test]
+    fn test_late_interrupt() {
+        let engine = Engine::new("./stockfish").unwrap();
+        engine.interrupter().interrupt().unwrap();
+        assert!(!engine.interrupter().take_request());
+
+        engine.search("depth 1").unwrap();
+        assert_eq!(engine.last_abort(), None);
+    }
+}
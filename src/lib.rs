@@ -1,243 +1,2647 @@
+#[cfg(feature = "log")]
 #[macro_use] extern crate log;
 
-use std::process::{Child, Command, Stdio};
+#[cfg(feature = "rayon")]
+extern crate rayon;
 
-use std::io::Read;
-use std::io::Write;
+// Without the `log` feature, log statements still type check but do nothing.
+#[cfg(not(feature = "log"))]
+macro_rules! noop_log {
+    ($($arg:tt)*) => {
+        if false {
+            let _ = format!($($arg)*);
+        }
+    }
+}
+#[cfg(not(feature = "log"))]
+macro_rules! info { ($($arg:tt)*) => { noop_log!($($arg)*) } }
+// Only the pool logs at debug level outside of an engine's own logger.
+#[cfg(not(feature = "log"))]
+#[allow(unused_macros)]
+macro_rules! debug { ($($arg:tt)*) => { noop_log!($($arg)*) } }
+#[cfg(not(feature = "log"))]
+macro_rules! warn { ($($arg:tt)*) => { noop_log!($($arg)*) } }
+
+// Logs through an engine's `Logger`, with its target and verbosity.
+macro_rules! engine_log {
+    ($logger:expr, $level:ident, $($arg:tt)*) => {
+        $logger.log(::logging::Verbosity::$level, format_args!($($arg)*))
+    }
+}
+
+
+use std::io::{self, BufRead, BufReader, Read};
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+mod abort;
+pub use abort::AbortReason;
+
+mod builder;
+pub use builder::{EngineBuilder, Protocol};
+
+mod chess960;
+
+mod connection;
+use connection::Connection;
+
+mod error;
+pub use error::{Result, EngineError};
+
+mod once;
+pub use once::analyze_once;
+
+mod logging;
+use logging::Logger;
+pub use logging::Verbosity;
+
+mod output;
+pub use output::{CommandOutput, LineKind, OutputLine};
+
+#[cfg(windows)]
+mod pipe;
+
+mod provenance;
+pub use provenance::{Provenance, Traced};
+
+mod retry;
+pub use retry::RetryPolicy;
+
+mod score;
+pub use score::{Normalization, Score, Wdl};
+
+mod info;
+pub use info::{CurrentMove, Info, InfoRef};
+
+mod interrupt;
+pub use interrupt::Interrupter;
+
+mod shared;
+pub use shared::SharedEngine;
+
+mod tail;
+use tail::OutputTail;
+
+mod snapshot;
+pub use snapshot::{SearchProbe, SearchSnapshot};
+
+mod stats;
+pub use stats::SearchStats;
+
+mod status;
+pub use status::PositionStatus;
+
+mod variation;
+pub use variation::Variation;
+
+mod verdict;
+pub use verdict::MoveVerdict;
+
+mod watchdog;
+use watchdog::{Action, Watchdog};
+
+mod workdir;
+mod xboard;
+
+pub mod batch;
+pub mod bench;
+pub mod clock;
+pub mod compare;
+pub mod events;
+pub mod failover;
+#[cfg(feature = "board")]
+pub mod game;
+#[cfg(feature = "match")]
+pub mod matches;
+pub mod options;
+pub mod overlay;
+#[cfg(feature = "pool")]
+pub mod pool;
+pub mod quality;
+pub mod review;
+#[cfg(all(feature = "pool", feature = "pgn", feature = "board"))]
+pub mod scan;
+pub mod session;
+pub mod threat;
+pub mod tree;
+
+pub struct Engine {
+    engine: Arc<Mutex<Connection>>,
+    interrupter: Interrupter,
+    reader: RefCell<BufReader<Box<dyn Read + Send>>>,
+    max_line_length: Option<usize>,
+
+    /// Whether lines with unusual bytes are logged in hex.
+    dump_bytes: bool,
+    logger: Logger,
+    label: Option<String>,
+    tail: Arc<OutputTail>,
+    retry: RetryPolicy,
+
+    limits: SearchLimits,
+    hard_limit: Option<Duration>,
+    partial_on_timeout: bool,
+
+    /// Whether searches get a hard limit once the engine is found to ignore
+    /// `movetime`, and whether it was found to.
+    enforce_movetime: bool,
+    movetime_enforced: Cell<bool>,
+
+    /// Searches in a row that took well over their `movetime`.
+    overshoot_streak: Cell<u32>,
+
+    /// How far the last search got, if it returned a partial result.
+    partial: RefCell<Option<SearchSnapshot>>,
+
+    /// Why the last search ended early, or will, while it runs.
+    abort: Cell<Option<AbortReason>>,
+    normalization: Normalization,
+
+    profile: bool,
+    last_timing: Cell<Option<SearchTiming>>,
+    latency: Cell<Option<Duration>>,
+
+    subscribers: RefCell<Vec<Arc<events::Queue>>>,
+
+    searching: Cell<bool>,
+    search_start: Cell<Option<Instant>>,
+    probe: SearchProbe,
+    stats: Cell<SessionStats>,
+    strict_options: bool,
+    pending_options: RefCell<Vec<(String, String)>>,
+
+    /// Whether non-protocol lines go to `noise` instead of command results.
+    route_noise: bool,
+    noise: RefCell<Vec<String>>,
+
+    /// Whether `debug on` was sent, so that `info string` lines are logged.
+    debug: Cell<bool>,
+    chess960: bool,
+
+    /// `id` and `option` lines of the `uci` handshake.
+    handshake: Vec<String>,
+
+    /// Anything else the engine printed at startup.
+    banner: Vec<String>,
+
+    /// Values set with `set_option`, by lowercase name.
+    option_values: RefCell<BTreeMap<String, String>>,
+}
+
+const DEFAULT_TIME: u32 = 100;
+
+/// Most time kept back from a deadline for the engine's `bestmove` to arrive.
+const MAX_DEADLINE_MARGIN: Duration = Duration::from_millis(50);
+
+/// Time an engine gets to answer `stop` at the hard limit before it's killed.
+const KILL_GRACE: Duration = Duration::from_secs(1);
+
+/// Searches in a row overshooting their `movetime` after which the engine is
+/// taken to ignore it.
+const OVERSHOOT_STREAK: u32 = 3;
+
+/// Time a search may take past its `movetime` without overshooting: half the
+/// movetime, plus some for pipe latency and scheduling.
+fn overshoot_allowance(movetime: Duration) -> Duration {
+    movetime / 2 + Duration::from_millis(100)
+}
+
+/// Commands of the protocol, whose answers are made of protocol lines only.
+const UCI_COMMANDS: [&str; 10] = ["uci", "debug", "isready", "setoption", "register",
+                                  "ucinewgame", "position", "go", "stop", "ponderhit"];
+
+/// First words of the lines an engine may send according to the protocol.
+const UCI_ANSWERS: [&str; 8] = ["id", "uciok", "readyok", "bestmove", "copyprotection",
+                                "registration", "info", "option"];
+
+const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// Where the time of a search went, as measured by the crate.
+///
+/// Only recorded when profiling is enabled with [`Engine::profile`].
+///
+/// [`Engine::profile`]: struct.Engine.html#method.profile
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchTiming {
+    /// Time from writing `go` to receiving the first `info` line.
+    pub first_info: Option<Duration>,
+
+    /// Time from writing `go` to receiving `bestmove`.
+    pub bestmove: Duration,
+
+    /// Search time the engine itself reported in its last `info` line.
+    pub engine_time: Option<Duration>,
+}
+
+impl SearchTiming {
+    /// Part of the search time not accounted for by the engine's own
+    /// thinking: pipe latency, process scheduling and the crate's parsing.
+    pub fn overhead(&self) -> Option<Duration> {
+        self.engine_time.map(|t| self.bestmove.checked_sub(t).unwrap_or_default())
+    }
+}
+
+/// When a search stops, see [`Engine::search_limits`] and
+/// [`Engine::bestmove_with_limits`].
+///
+/// Only the limits that are set are sent, and the search stops at the first
+/// one reached. Without any, the search lasts the default 100 milliseconds.
+///
+/// [`Engine::search_limits`]: struct.Engine.html#method.search_limits
+/// [`Engine::bestmove_with_limits`]: struct.Engine.html#method.bestmove_with_limits
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    /// Search time in milliseconds.
+    pub movetime: Option<u32>,
+
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+}
+
+impl SearchLimits {
+    /// Arguments of the `go` command.
+    pub(crate) fn go_args(&self) -> String {
+        let mut args = vec![];
+        if let Some(movetime) = self.movetime {
+            args.push(format!("movetime {}", movetime));
+        }
+        if let Some(depth) = self.depth {
+            args.push(format!("depth {}", depth));
+        }
+        if let Some(nodes) = self.nodes {
+            args.push(format!("nodes {}", nodes));
+        }
+        if args.is_empty() {
+            args.push(format!("movetime {}", DEFAULT_TIME));
+        }
+        args.join(" ")
+    }
+}
+
+/// Totals of all the searches of an engine, see [`Engine::stats`].
+///
+/// [`Engine::stats`]: struct.Engine.html#method.stats
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SessionStats {
+    pub searches: u64,
+
+    /// Nodes searched, as reported by the engine.
+    pub nodes: u64,
+
+    /// Time from starting to the end of the searches.
+    pub think_time: Duration,
+
+    /// Searches that took well over the `movetime` they were given, see
+    /// [`Engine::enforce_movetime`].
+    ///
+    /// [`Engine::enforce_movetime`]: struct.Engine.html#method.enforce_movetime
+    pub overshoots: u64,
+
+    depth_total: u64,
+    depth_samples: u64,
+}
+
+impl SessionStats {
+    /// Average depth reached, over the searches that reported one.
+    pub fn average_depth(&self) -> Option<f64> {
+        if self.depth_samples > 0 {
+            Some(self.depth_total as f64 / self.depth_samples as f64)
+        } else {
+            None
+        }
+    }
+
+    fn record(&mut self, lines: &[String], think_time: Duration) {
+        let last = |f: &dyn Fn(&InfoRef) -> Option<u64>| {
+            lines.iter().rev().filter_map(|line| InfoRef::parse(line)).find_map(|info| f(&info))
+        };
+        self.searches += 1;
+        self.nodes += last(&|info| info.nodes).unwrap_or(0);
+        self.think_time += think_time;
+        if let Some(depth) = last(&|info| info.depth.map(u64::from)) {
+            self.depth_total += depth;
+            self.depth_samples += 1;
+        }
+    }
+
+    /// Adds the totals of another session.
+    #[cfg_attr(not(feature = "pool"), allow(dead_code))]
+    pub(crate) fn merge(&mut self, other: &SessionStats) {
+        self.searches += other.searches;
+        self.nodes += other.nodes;
+        self.think_time += other.think_time;
+        self.overshoots += other.overshoots;
+        self.depth_total += other.depth_total;
+        self.depth_samples += other.depth_samples;
+    }
+
+    /// What was added to these totals since `earlier` was taken.
+    #[cfg_attr(not(feature = "pool"), allow(dead_code))]
+    pub(crate) fn since(&self, earlier: &SessionStats) -> SessionStats {
+        SessionStats {
+            searches: self.searches - earlier.searches,
+            nodes: self.nodes - earlier.nodes,
+            think_time: self.think_time - earlier.think_time,
+            overshoots: self.overshoots - earlier.overshoots,
+            depth_total: self.depth_total - earlier.depth_total,
+            depth_samples: self.depth_samples - earlier.depth_samples,
+        }
+    }
+}
+
+/// Outcome of an [`Engine::warmup`] run.
+///
+/// [`Engine::warmup`]: struct.Engine.html#method.warmup
+#[derive(Debug, Clone, PartialEq)]
+pub struct WarmupReport {
+    /// Number of searches run.
+    pub searches: usize,
+
+    /// Nodes per second reached by the last search.
+    pub nps: u64,
+
+    /// Highest nodes per second reached by any search.
+    pub peak_nps: u64,
+
+    /// Total time spent warming up.
+    pub elapsed: Duration,
+}
+
+impl Engine {
+    /// Create a new [`Engine`] instance.
+    ///
+    /// # Arguments
+    /// 
+    /// * `path` - The path to the engine executable.
+    ///
+    /// Fails with [`EngineError::Io`] if the engine couldn't be spawned (path
+    /// is invalid, execution permission denied, etc.)
+    ///
+    /// [`Engine`]: struct.Engine.html
+    /// [`EngineError::Io`]: enum.EngineError.html#variant.Io
+    pub fn new(path: &str) -> Result<Engine> {
+        EngineBuilder::new(path).build()
+    }
+
+    /// Uses an engine that is already running, through its input and output
+    /// streams, with the default settings. See [`EngineBuilder::attach`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::process::{Command, Stdio};
+    ///
+    /// let mut child = Command::new("stockfish")
+    ///     .stdin(Stdio::piped())
+    ///     .stdout(Stdio::piped())
+    ///     .spawn()
+    ///     .unwrap();
+    /// let engine = uci::Engine::attach(child.stdin.take().unwrap(),
+    ///                                  child.stdout.take().unwrap()).unwrap();
+    /// println!("{}", engine.bestmove().unwrap());
+    /// ```
+    ///
+    /// [`EngineBuilder::attach`]: struct.EngineBuilder.html#method.attach
+    pub fn attach<W, R>(input: W, output: R) -> Result<Engine>
+        where W: io::Write + Send + 'static,
+              R: Read + Send + 'static
+    {
+        EngineBuilder::new("attached engine").attach(input, output)
+    }
+
+    /// Changes the amount of time the engine spends looking for a move
+    ///
+    /// # Arguments
+    /// 
+    /// * `new_movetime` - New timelimit in milliseconds
+    pub fn movetime(mut self, new_movetime: u32) -> Engine {
+        self.limits.movetime = Some(new_movetime);
+        self
+    }
+
+    /// Changes the the engine depth when looking for a move
+    ///
+    /// # Arguments
+    ///
+    /// * `new_depth` - New depth, as an Option
+    pub fn depth(mut self, new_depth: Option<u32>) -> Engine {
+        self.limits.depth = new_depth;
+        self
+    }
+
+    /// Changes the number of nodes the engine searches when looking for a
+    /// move. Unlike time, a node limit gives the same result on any machine,
+    /// as long as the search time doesn't run out first.
+    ///
+    /// # Arguments
+    ///
+    /// * `new_nodes` - New node limit, as an Option
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap().nodes(Some(1_000_000));
+    /// println!("{}", engine.bestmove().unwrap());
+    /// ```
+    pub fn nodes(mut self, new_nodes: Option<u64>) -> Engine {
+        self.limits.nodes = new_nodes;
+        self
+    }
+
+    /// Replaces all the search limits at once. Unlike [`movetime`], this
+    /// allows searching without a time limit, to a depth or a number of
+    /// nodes only.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use uci::SearchLimits;
+    ///
+    /// let engine = uci::Engine::new("stockfish").unwrap()
+    ///     .search_limits(SearchLimits { depth: Some(12), ..SearchLimits::default() });
+    /// println!("{}", engine.bestmove().unwrap());
+    /// ```
+    ///
+    /// [`movetime`]: #method.movetime
+    pub fn search_limits(mut self, limits: SearchLimits) -> Engine {
+        self.limits = limits;
+        self
+    }
+
+    /// Sets a hard limit on the duration of searches.
+    ///
+    /// The engine is still asked for the movetime, but a search that is still
+    /// going at the hard limit is stopped by the crate. Engines that don't
+    /// answer `stop` within a second are killed and the search fails with
+    /// [`EngineError::Timeout`].
+    ///
+    /// # Arguments
+    ///
+    /// * `hard_limit` - Longest time a search may take, as an Option
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// let engine = uci::Engine::new("stockfish").unwrap()
+    ///     .movetime(1000)
+    ///     .hard_limit(Some(Duration::from_millis(1500)));
+    /// let mv = engine.bestmove().unwrap();
+    /// ```
+    ///
+    /// [`EngineError::Timeout`]: enum.EngineError.html#variant.Timeout
+    pub fn hard_limit(mut self, hard_limit: Option<Duration>) -> Engine {
+        self.hard_limit = hard_limit;
+        self
+    }
+
+    /// Makes the crate stop searches itself once the engine is found to
+    /// ignore `movetime`.
+    ///
+    /// Searches taking well over their movetime are always counted in
+    /// [`SessionStats::overshoots`], and after 3 of them in a row a warning
+    /// is logged and an [`Event::MovetimeIgnored`] is sent. With enforcement
+    /// enabled, from then on every search with a movetime is stopped at the
+    /// movetime and a half, as with a [`hard_limit`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap()
+    ///     .movetime(500)
+    ///     .enforce_movetime(true);
+    /// let mv = engine.bestmove().unwrap();
+    /// ```
+    ///
+    /// [`SessionStats::overshoots`]: struct.SessionStats.html#structfield.overshoots
+    /// [`Event::MovetimeIgnored`]: events/enum.Event.html#variant.MovetimeIgnored
+    /// [`hard_limit`]: #method.hard_limit
+    pub fn enforce_movetime(mut self, enabled: bool) -> Engine {
+        self.enforce_movetime = enabled;
+        self
+    }
+
+    /// Makes a search cut short by the [`hard_limit`] return what the engine
+    /// found until then, instead of failing with [`EngineError::Timeout`].
+    ///
+    /// The search then ends with the first move of the last pv the engine
+    /// sent, and [`last_partial`] tells how far it got. Searches without any
+    /// pv yet still fail. The engine is killed all the same, so this is the
+    /// last answer it gives.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// let engine = uci::Engine::new("stockfish").unwrap()
+    ///     .movetime(1000)
+    ///     .hard_limit(Some(Duration::from_millis(1500)))
+    ///     .partial_on_timeout(true);
+    /// let mv = engine.bestmove().unwrap();
+    /// if let Some(reached) = engine.last_partial() {
+    ///     println!("{} is only from depth {:?}", mv, reached.depth);
+    /// }
+    /// ```
+    ///
+    /// [`hard_limit`]: #method.hard_limit
+    /// [`last_partial`]: #method.last_partial
+    /// [`EngineError::Timeout`]: enum.EngineError.html#variant.Timeout
+    pub fn partial_on_timeout(mut self, enabled: bool) -> Engine {
+        self.partial_on_timeout = enabled;
+        self
+    }
+
+    /// How far the last search got, if it was cut short and returned a
+    /// partial result, see [`partial_on_timeout`].
+    ///
+    /// [`partial_on_timeout`]: #method.partial_on_timeout
+    pub fn last_partial(&self) -> Option<SearchSnapshot> {
+        self.partial.borrow().clone()
+    }
+
+    /// Why the last search ended before reaching its limits, or `None` if it
+    /// ended on its own. Set for searches returning a result as well as for
+    /// failed ones, so that a cancelled search can be told from a broken
+    /// engine when logging or retrying.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// engine.go_infinite().unwrap();
+    /// engine.stop().unwrap();
+    /// assert_eq!(engine.last_abort(), Some(uci::AbortReason::Stopped));
+    /// ```
+    pub fn last_abort(&self) -> Option<AbortReason> {
+        self.abort.get()
+    }
+
+    /// Sets how the engine's centipawns are converted to the crate's scale,
+    /// see [`Normalization`].
+    ///
+    /// Applies to [`evaluation`] and [`normalize`].
+    ///
+    /// # Arguments
+    ///
+    /// * `normalization` - The engine's profile.
+    ///
+    /// [`Normalization`]: enum.Normalization.html
+    /// [`evaluation`]: #method.evaluation
+    /// [`normalize`]: #method.normalize
+    pub fn score_normalization(mut self, normalization: Normalization) -> Engine {
+        self.normalization = normalization;
+        self
+    }
+
+    /// Converts a score reported by this engine according to its
+    /// [`score_normalization`].
+    ///
+    /// [`score_normalization`]: #method.score_normalization
+    pub fn normalize(&self, score: Score) -> Score {
+        score.normalize(self.normalization)
+    }
+
+    /// Makes [`set_option`] fail with [`EngineError::Searching`] during a
+    /// search instead of deferring the change until the search is over.
+    ///
+    /// [`set_option`]: #method.set_option
+    /// [`EngineError::Searching`]: enum.EngineError.html#variant.Searching
+    pub fn strict_options(mut self, strict: bool) -> Engine {
+        self.strict_options = strict;
+        self
+    }
+
+    /// Retries reads and writes that fail with transient I/O errors, see
+    /// [`RetryPolicy`]. There are no retries by default.
+    ///
+    /// [`RetryPolicy`]: struct.RetryPolicy.html
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Engine {
+        self.retry = policy;
+        self
+    }
+
+    /// Sends whatever the engine prints that isn't part of the protocol, like
+    /// debug output or unsolicited messages, to a side channel read with
+    /// [`take_noise`], instead of mixing it with the answers to protocol
+    /// commands.
+    ///
+    /// Commands outside the protocol, like `d` or `bench`, still return all
+    /// their output. [`set_option`] only reports errors for lines mentioning
+    /// an option.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap().route_noise(true);
+    /// engine.set_option("Skill Level", "5").unwrap();
+    /// for line in engine.take_noise() {
+    ///     println!("engine said: {}", line);
+    /// }
+    /// ```
+    ///
+    /// [`take_noise`]: #method.take_noise
+    /// [`set_option`]: #method.set_option
+    pub fn route_noise(mut self, enabled: bool) -> Engine {
+        self.route_noise = enabled;
+        self
+    }
+
+    /// Returns and forgets the lines collected since the last call, if
+    /// [`route_noise`] is enabled. Startup output is kept apart in
+    /// [`banner`].
+    ///
+    /// [`route_noise`]: #method.route_noise
+    /// [`banner`]: #method.banner
+    pub fn take_noise(&self) -> Vec<String> {
+        self.noise.borrow_mut().drain(..).collect()
+    }
+
+    /// Enables or disables timing of searches, see [`last_timing`].
+    ///
+    /// [`last_timing`]: #method.last_timing
+    pub fn profile(mut self, enabled: bool) -> Engine {
+        self.profile = enabled;
+        self
+    }
+
+    /// Returns the timing of the last search, if profiling is enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap().profile(true);
+    /// engine.bestmove().unwrap();
+    /// let timing = engine.last_timing().unwrap();
+    /// println!("overhead: {:?}", timing.overhead());
+    /// ```
+    pub fn last_timing(&self) -> Option<SearchTiming> {
+        self.last_timing.get()
+    }
+
+    /// Returns the measured latency of talking to the engine: the delay
+    /// between the engine finishing a search and its move reaching the
+    /// crate, or the round trip of [`ping`].
+    ///
+    /// It's a moving average, available after the first measurement.
+    /// [`bestmove_on_clock`] adds it to the safety margin of the clocks.
+    ///
+    /// [`ping`]: #method.ping
+    /// [`bestmove_on_clock`]: #method.bestmove_on_clock
+    pub fn latency(&self) -> Option<Duration> {
+        self.latency.get()
+    }
+
+    fn record_latency(&self, sample: Duration) {
+        let latency = match self.latency.get() {
+            Some(latency) => (latency * 3 + sample) / 4,
+            None => sample,
+        };
+        self.latency.set(Some(latency));
+    }
+
+    /// Returns a [`Subscription`] receiving every `info` and `bestmove` line
+    /// the engine sends from now on.
+    ///
+    /// Events are delivered while the engine is being read from, e.g. during
+    /// [`bestmove`], so the subscription is usually handed to another thread.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - What to do when the subscriber can't keep up.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::thread;
+    /// use uci::events::{ChannelPolicy, Event};
+    ///
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// let events = engine.subscribe(ChannelPolicy::DropOldest(100));
+    /// let printer = thread::spawn(move || {
+    ///     while let Some(event) = events.recv() {
+    ///         println!("{:?}", event);
+    ///     }
+    /// });
+    /// engine.bestmove().unwrap();
+    /// drop(engine);
+    /// printer.join().unwrap();
+    /// ```
+    ///
+    /// [`Subscription`]: events/struct.Subscription.html
+    /// [`bestmove`]: #method.bestmove
+    pub fn subscribe(&self, policy: events::ChannelPolicy) -> events::Subscription {
+        let (subscription, queue) = events::Subscription::new(policy);
+        self.subscribers.borrow_mut().push(queue);
+        subscription.labelled(self.label())
+    }
+
+    /// Asks the engine to play the given moves from the initial position on it's internal board.
+    /// 
+    /// # Arguments
+    ///
+    /// * `moves` - A list of moves for the engine to play. Uses Coordinate notation
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// let moves = vec!["e2e4".to_string(), "e7e5".to_string()];
+    /// engine.make_moves(&moves).unwrap();
+    /// ```
+    pub fn make_moves(&self, moves: &[String]) -> Result<()> {
+        self.supersede();
+        self.write_fmt(format_args!("position startpos moves {}\n",
+                                    moves.join(" ")))?;
+        Ok(())
+    }
+    
+    /// Tells the engine the next positions belong to a new game, so it
+    /// clears its hash table and history, and waits until it's done. Timing
+    /// and search state kept by the crate are reset too.
+    ///
+    /// Fails with [`EngineError::Searching`] during a search.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// engine.bestmove().unwrap();
+    ///
+    /// engine.new_game().unwrap();
+    /// engine.make_moves(&["e2e4".to_string()]).unwrap();
+    /// engine.bestmove().unwrap();
+    /// ```
+    ///
+    /// [`EngineError::Searching`]: enum.EngineError.html#variant.Searching
+    pub fn new_game(&self) -> Result<()> {
+        if self.searching.get() {
+            return Err(EngineError::Searching);
+        }
+        self.write_fmt(format_args!("ucinewgame\n"))?;
+        self.drain_output(|_| {})?;
+        self.last_timing.set(None);
+        self.probe.finish();
+        Ok(())
+    }
+
+    /// Asks the engine to use the position represented by the given FEN string
+    /// 
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// engine.set_position("2k4R/8/3K4/8/8/8/8/8 b - - 0 1").unwrap();
+    /// assert_eq!(engine.bestmove().unwrap(), "c8b7");
+    /// ```
+    pub fn set_position(&self, fen: &str) -> Result<()> {
+        let moves: Vec<String> = vec![];
+        self.make_moves_from_position(fen, &moves)
+    }
+    
+    /// Asks the engine to use the position represented by the given FEN string
+    /// and then play the given moves from that position
+    ///
+    /// For [`chess960`] engines, X-FEN castling rights (`KQkq`) are sent as
+    /// Shredder-FEN rook files, which no engine can read ambiguously.
+    ///
+    /// [`chess960`]: struct.EngineBuilder.html#method.chess960
+    pub fn make_moves_from_position(&self, fen: &str, moves: &[String]) -> Result<()> {
+        let fen = if self.chess960 { chess960::shredder_fen(fen) } else { fen.to_string() };
+        self.supersede();
+        self.write_fmt(format_args!("position fen {} moves {}\n",
+                                    fen, moves.join(" ")))?;
+        Ok(())
+    }
+
+    /// Records that the running search, if any, is now about an old
+    /// position.
+    fn supersede(&self) {
+        if self.searching.get() {
+            self.abort.set(Some(AbortReason::Superseded));
+        }
+    }
+
+    /// Whether the engine plays Chess960, see [`EngineBuilder::chess960`].
+    ///
+    /// [`EngineBuilder::chess960`]: struct.EngineBuilder.html#method.chess960
+    pub fn is_chess960(&self) -> bool {
+        self.chess960
+    }
+    
+    /// The limits set with [`movetime`], [`depth`], [`nodes`] or
+    /// [`search_limits`].
+    ///
+    /// [`movetime`]: #method.movetime
+    /// [`depth`]: #method.depth
+    /// [`nodes`]: #method.nodes
+    /// [`search_limits`]: #method.search_limits
+    pub fn limits(&self) -> SearchLimits {
+        self.limits
+    }
+
+    fn go_args(&self) -> String {
+        self.limits().go_args()
+    }
+
+    /// Runs throwaway searches so the engine's caches are populated and the
+    /// CPU clocks have settled before timing-sensitive measurements.
+    ///
+    /// The engine's current position is replaced.
+    ///
+    /// # Arguments
+    ///
+    /// * `positions` - FEN strings to search. The starting position is used if empty.
+    /// * `duration`  - Total time to spend, split evenly over the positions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// let report = engine.warmup(&[], Duration::from_millis(500)).unwrap();
+    /// println!("warmed up at {} nps", report.nps);
+    /// ```
+    pub fn warmup(&self, positions: &[&str], duration: Duration) -> Result<WarmupReport> {
+        let start = Instant::now();
+        let startpos = [START_FEN];
+        let positions = if positions.is_empty() { &startpos[..] } else { positions };
+        let movetime = (duration / positions.len() as u32).as_millis().max(1);
+
+        let mut report = WarmupReport {
+            searches: 0,
+            nps: 0,
+            peak_nps: 0,
+            elapsed: Duration::default(),
+        };
+        for fen in positions {
+            self.set_position(fen)?;
+            let nps = self.search(&format!("movetime {}", movetime))?.iter()
+                .rev()
+                .find_map(|line| InfoRef::parse(line).and_then(|info| info.nps))
+                .unwrap_or(0);
+
+            report.searches += 1;
+            report.nps = nps;
+            report.peak_nps = report.peak_nps.max(nps);
+        }
+        report.elapsed = start.elapsed();
+
+        Ok(report)
+    }
+
+    /// Starts a search with the given `go` arguments and collects the engine
+    /// output up to and including the `bestmove` line.
+    pub(crate) fn search(&self, args: &str) -> Result<Vec<String>> {
+        self.search_with(args, |_| {})
+    }
+
+    /// Like [`search`], but calls `progress` with every line as it arrives.
+    ///
+    /// [`search`]: #method.search
+    fn search_with<F>(&self, args: &str, mut progress: F) -> Result<Vec<String>>
+        where F: FnMut(&str)
+    {
+        self.abort.set(None);
+        self.write_search(format_args!("go {}\n", args))?;
+        let start = Instant::now();
+        let movetime = requested_movetime(args);
+        let enforced = movetime.filter(|_| self.movetime_enforced.get())
+            .map(|movetime| movetime + overshoot_allowance(movetime));
+        let hard_limit = match (self.hard_limit, enforced) {
+            (Some(limit), Some(enforced)) => Some(limit.min(enforced)),
+            (limit, enforced) => limit.or(enforced),
+        };
+        let watchdog = hard_limit.map(|limit| {
+            Watchdog::arm(self.engine.clone(), self.logger.clone(), vec![
+                (start + limit, Action::Stop),
+                (start + limit + KILL_GRACE, Action::Kill),
+            ])
+        });
+
+        let mut first_info = None;
+        let res = self.read_search_output(|s| {
+            if first_info.is_none() && s.starts_with("info") {
+                first_info = Some(start.elapsed());
+            }
+            progress(s);
+        });
+        let fired = watchdog.as_ref().and_then(Watchdog::fired);
+        let killed = fired == Some(Action::Kill);
+        if fired.is_some() {
+            self.abort.set(Some(AbortReason::Deadline));
+        }
+        drop(watchdog);
+        let reached = self.probe.last_reached();
+        self.partial.replace(None);
+        let lines = match res {
+            Err(_) if killed => match reached.as_ref().and_then(SearchSnapshot::as_output) {
+                Some(lines) if self.partial_on_timeout => {
+                    engine_log!(self.logger, Warn, "Search cut short, returning a partial result");
+                    self.partial.replace(reached);
+                    lines
+                }
+                _ => return Err(EngineError::Timeout(self.tail.lines()).labelled(self.label())),
+            },
+            res => res?,
+        };
+        let lines = if self.route_noise { self.split_noise(lines) } else { lines };
+
+        let engine_time = lines.iter()
+            .rev()
+            .find_map(|line| InfoRef::parse(line).and_then(|info| info.time))
+            .map(Duration::from_millis);
+        let timing = SearchTiming {
+            first_info,
+            bestmove: start.elapsed(),
+            engine_time,
+        };
+        if let Some(overhead) = timing.overhead() {
+            self.record_latency(overhead);
+        }
+        if let Some(movetime) = movetime {
+            self.check_overshoot(movetime, timing.bestmove);
+        }
+        if self.profile {
+            engine_log!(self.logger, Debug, "Search timing: {:?}", timing);
+            self.last_timing.set(Some(timing));
+        }
+
+        Ok(lines)
+    }
+
+    /// Counts a search that took well over its `movetime`, and acts once
+    /// the engine has been doing it for a while.
+    fn check_overshoot(&self, movetime: Duration, took: Duration) {
+        if took <= movetime + overshoot_allowance(movetime) {
+            self.overshoot_streak.set(0);
+            return;
+        }
+        let mut stats = self.stats.get();
+        stats.overshoots += 1;
+        self.stats.set(stats);
+
+        let streak = self.overshoot_streak.get() + 1;
+        self.overshoot_streak.set(streak);
+        if streak != OVERSHOOT_STREAK {
+            return;
+        }
+        let mut reason = format!("{} searches in a row overshot their movetime, the last took {:?} for {:?}",
+                                 streak, took, movetime);
+        if self.enforce_movetime && !self.movetime_enforced.get() {
+            self.movetime_enforced.set(true);
+            reason.push_str(", enforcing it from now on");
+        }
+        engine_log!(self.logger, Warn, "Engine ignores movetime: {}", reason);
+        let event = events::Event::MovetimeIgnored(reason);
+        self.subscribers.borrow_mut().retain(|queue| queue.push(event.clone()));
+    }
+
+    /// Returns a handle that stops this engine's searches from another
+    /// thread, for aborting a deep [`evaluation`] or any other blocking call
+    /// without killing the engine.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// let engine = uci::Engine::new("stockfish").unwrap().depth(Some(30));
+    /// let interrupter = engine.interrupter();
+    /// thread::spawn(move || {
+    ///     thread::sleep(Duration::from_millis(200));
+    ///     interrupter.interrupt().unwrap();
+    /// });
+    /// println!("best move after 200ms: {}", engine.bestmove().unwrap());
+    /// ```
+    ///
+    /// [`evaluation`]: #method.evaluation
+    pub fn interrupter(&self) -> Interrupter {
+        self.interrupter.clone()
+    }
+
+    /// Returns the depth, score, nodes and principal variation reported so
+    /// far by the search in progress, or `None` if the engine isn't
+    /// searching. Use a [`search_probe`] to poll from other threads.
+    ///
+    /// [`search_probe`]: #method.search_probe
+    pub fn current_search(&self) -> Option<SearchSnapshot> {
+        self.probe.get()
+    }
+
+    /// Returns the totals of all the searches run so far, for reporting how
+    /// busy the engine is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// engine.bestmove().unwrap();
+    ///
+    /// let stats = engine.stats();
+    /// println!("{} nodes in {:?}, average depth {:?}",
+    ///          stats.nodes, stats.think_time, stats.average_depth());
+    /// ```
+    pub fn stats(&self) -> SessionStats {
+        self.stats.get()
+    }
+
+    /// Returns a handle giving the [`current_search`] state of this engine
+    /// from any thread.
+    ///
+    /// [`current_search`]: #method.current_search
+    pub fn search_probe(&self) -> SearchProbe {
+        self.probe.clone()
+    }
+
+    /// Returns the best move in the current position according to the engine
+    pub fn bestmove(&self) -> Result<String> {
+        self.bestmove_with_args(&self.go_args())
+    }
+
+    /// Starts searching the current position until [`stop`] is called.
+    ///
+    /// The engine's output isn't read meanwhile, so [`current_search`]
+    /// stays empty until the search is stopped. Options set in between are
+    /// applied afterwards, as during any search.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::thread;
+    /// use std::time::Duration;
+    ///
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// engine.go_infinite().unwrap();
+    /// thread::sleep(Duration::from_millis(200));
+    /// println!("{}", engine.stop().unwrap());
+    /// ```
+    ///
+    /// [`stop`]: #method.stop
+    /// [`current_search`]: #method.current_search
+    pub fn go_infinite(&self) -> Result<()> {
+        self.start_open_search("infinite")
+    }
+
+    /// Stops the search started by [`go_infinite`] or [`go_ponder`] and
+    /// returns the best move the engine had found. Fails with [`EngineError::NotFound`] if there's
+    /// no such search.
+    ///
+    /// [`go_infinite`]: #method.go_infinite
+    /// [`go_ponder`]: #method.go_ponder
+    /// [`EngineError::NotFound`]: enum.EngineError.html#variant.NotFound
+    pub fn stop(&self) -> Result<String> {
+        if !self.searching.get() {
+            return Err(EngineError::NotFound);
+        }
+        if self.abort.get().is_none() {
+            self.abort.set(Some(AbortReason::Stopped));
+        }
+        self.write_fmt(format_args!("stop\n"))?;
+        let lines = self.read_search_output(|_| {})?;
+        best_of(&lines)
+    }
+
+    /// Starts pondering: searching the current position, which should end
+    /// with the opponent's expected move, while the opponent thinks.
+    ///
+    /// Once the opponent moves, call [`ponderhit`] if they played the
+    /// expected move, making the engine go on with the given limits, or
+    /// [`stop`] otherwise and search the actual position.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// engine.make_moves(&["e2e4".to_string(), "e7e5".to_string()]).unwrap();
+    /// engine.go_ponder(&engine.limits()).unwrap();
+    ///
+    /// // The opponent played e7e5, as expected.
+    /// println!("{}", engine.ponderhit().unwrap());
+    /// ```
+    ///
+    /// [`ponderhit`]: #method.ponderhit
+    /// [`stop`]: #method.stop
+    pub fn go_ponder(&self, limits: &SearchLimits) -> Result<()> {
+        self.start_open_search(&format!("ponder {}", limits.go_args()))
+    }
+
+    /// Tells the pondering engine that the expected move was played and
+    /// waits for its best move. Fails with [`EngineError::NotFound`] if the
+    /// engine isn't searching.
+    ///
+    /// [`EngineError::NotFound`]: enum.EngineError.html#variant.NotFound
+    pub fn ponderhit(&self) -> Result<String> {
+        self.ponderhit_with_ponder().map(|(best, _)| best)
+    }
+
+    /// Like [`ponderhit`], but also returns the reply the engine expects.
+    ///
+    /// [`ponderhit`]: #method.ponderhit
+    pub(crate) fn ponderhit_with_ponder(&self) -> Result<(String, Option<String>)> {
+        if !self.searching.get() {
+            return Err(EngineError::NotFound);
+        }
+        self.write_fmt(format_args!("ponderhit\n"))?;
+        let lines = self.read_search_output(|_| {})?;
+        bestmove_of(&lines)
+    }
+
+    /// Starts a search that only ends when told to.
+    fn start_open_search(&self, args: &str) -> Result<()> {
+        if self.searching.get() {
+            return Err(EngineError::Searching);
+        }
+        self.abort.set(None);
+        self.write_search(format_args!("go {}\n", args))?;
+        self.searching.set(true);
+        self.search_start.set(Some(Instant::now()));
+        Ok(())
+    }
+
+    /// Looks for a forced mate in at most `moves` moves from the current
+    /// position, with `go mate`, and returns the mating line if there's one.
+    ///
+    /// The search runs until the engine finds the mate or gives up, which
+    /// can take long for large `moves`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// engine.set_position("r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5Q2/PPPP1PPP/RNB1K1NR w KQkq - 4 4").unwrap();
+    /// if let Some(line) = engine.find_mate(1).unwrap() {
+    ///     println!("mate with {}", line[0]);
+    /// }
+    /// ```
+    pub fn find_mate(&self, moves: u32) -> Result<Option<Vec<String>>> {
+        let lines = self.search(&format!("mate {}", moves))?;
+        let mate = lines.iter()
+            .rev()
+            .filter_map(|line| InfoRef::parse(line))
+            .find(|info| info.score.is_some() && info.multipv.unwrap_or(1) == 1)
+            .filter(|info| match info.score {
+                Some(Score::Mate(n)) => n > 0 && n as u32 <= moves,
+                _ => false,
+            });
+        Ok(mate.map(|info| info.pv_moves().map(String::from).collect()))
+    }
+
+    /// Returns the best move in the current position, along with the reply
+    /// the engine expects, if it suggested one to ponder on.
+    ///
+    /// Fails with [`EngineError::NotFound`] if the engine ends the search
+    /// with a bare `bestmove`, as some do when there's no legal move.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// let (best, ponder) = engine.bestmove_with_ponder().unwrap();
+    /// println!("{} {:?}", best, ponder);
+    /// ```
+    ///
+    /// [`EngineError::NotFound`]: enum.EngineError.html#variant.NotFound
+    pub fn bestmove_with_ponder(&self) -> Result<(String, Option<String>)> {
+        let lines = self.search(&self.go_args())?;
+        bestmove_of(&lines)
+    }
+
+    /// Returns the best move in the current position, searching with the
+    /// given limits instead of the engine's own.
+    ///
+    /// Useful when the limits change from one search to the next, or when
+    /// the engine is shared and can't be rebuilt with new limits.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use uci::SearchLimits;
+    ///
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// for depth in 1..4 {
+    ///     let limits = SearchLimits { depth: Some(depth), ..engine.limits() };
+    ///     println!("{}", engine.bestmove_with_limits(&limits).unwrap());
+    /// }
+    /// ```
+    pub fn bestmove_with_limits(&self, limits: &SearchLimits) -> Result<String> {
+        self.bestmove_with_args(&limits.go_args())
+    }
+
+    /// Returns the best move in the current position, letting the engine
+    /// decide how long to think from the players' clocks, as GUIs do.
+    ///
+    /// # Arguments
+    ///
+    /// * `white_ms`  - Time left on White's clock, in milliseconds.
+    /// * `black_ms`  - Time left on Black's clock, in milliseconds.
+    /// * `winc`      - White's increment per move, in milliseconds.
+    /// * `binc`      - Black's increment per move, in milliseconds.
+    /// * `movestogo` - Moves left until the next time control, if there's one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// let mv = engine.go_clock(60_000, 60_000, 1_000, 1_000, None).unwrap();
+    /// ```
+    pub fn go_clock(&self, white_ms: u64, black_ms: u64, winc: u64, binc: u64,
+                    movestogo: Option<u32>) -> Result<String> {
+        let mut args = format!("wtime {} btime {} winc {} binc {}", white_ms, black_ms, winc, binc);
+        if let Some(moves) = movestogo {
+            args.push_str(&format!(" movestogo {}", moves));
+        }
+        self.bestmove_with_args(&args)
+    }
+
+    /// Returns the best move in the current position, with the search time
+    /// decided by `manager` from the clocks.
+    ///
+    /// The measured [`latency`] is taken from the clocks on top of the
+    /// manager's safety margin. See [`TimeManager`] for an example.
+    ///
+    /// [`latency`]: #method.latency
+    /// [`TimeManager`]: clock/struct.TimeManager.html
+    pub fn bestmove_on_clock(&self, manager: &clock::TimeManager, clocks: &clock::Clocks)
+                             -> Result<String> {
+        let manager = manager.compensate(self.latency().unwrap_or_default());
+        self.bestmove_with_args(&manager.go_args(clocks))
+    }
+
+    /// Sets the engine's `Move Overhead` option, the time it keeps back from
+    /// its clock for communication delays, when it manages its own time.
+    ///
+    /// # Arguments
+    ///
+    /// * `overhead` - The delay to account for. Sent in milliseconds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// engine.set_move_overhead(Duration::from_millis(100)).unwrap();
+    /// ```
+    pub fn set_move_overhead(&self, overhead: Duration) -> Result<()> {
+        self.set_option("Move Overhead", &overhead.as_millis().to_string())
+    }
+
+    /// Returns the best move in the current position, searching until just
+    /// before `deadline`.
+    ///
+    /// The configured movetime is ignored. If the engine is still searching
+    /// at the deadline it's sent `stop`, so the call returns right after it,
+    /// as soon as the engine answers with its move.
+    ///
+    /// # Arguments
+    ///
+    /// * `deadline` - When the move is needed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// let mv = engine.bestmove_by(Instant::now() + Duration::from_millis(200)).unwrap();
+    /// ```
+    pub fn bestmove_by(&self, deadline: Instant) -> Result<String> {
+        let left = deadline.saturating_duration_since(Instant::now());
+        let margin = (left / 10).min(MAX_DEADLINE_MARGIN);
+        let movetime = (left - margin).as_millis().max(1) as u32;
+        let args = SearchLimits { movetime: Some(movetime), ..self.limits }.go_args();
+
+        let _watchdog = Watchdog::arm(self.engine.clone(), self.logger.clone(), vec![(deadline, Action::Stop)]);
+        self.bestmove_with_args(&args)
+    }
+
+    /// Returns the best of the given moves in the current position,
+    /// restricting the search with `searchmoves`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// let mv = engine.bestmove_among(&["e2e4", "d2d4"]).unwrap();
+    /// ```
+    pub fn bestmove_among(&self, moves: &[&str]) -> Result<String> {
+        self.bestmove_with_args(&format!("{} searchmoves {}", self.go_args(), moves.join(" ")))
+    }
+
+    /// Evaluates particular moves of the current position, each searched
+    /// on its own with `searchmoves` and the engine's limits.
+    ///
+    /// Returns the moves with their scores, from the point of view of the
+    /// side to move and normalized, in the order given. A score is `None` if
+    /// the engine didn't report one, like for an illegal move.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// for (mv, score) in engine.evaluate_moves(&["e2e4", "a2a3"]).unwrap() {
+    ///     println!("{}: {:?}", mv, score);
+    /// }
+    /// ```
+    pub fn evaluate_moves(&self, moves: &[&str]) -> Result<Vec<(String, Option<Score>)>> {
+        let args = self.go_args();
+        moves.iter()
+            .map(|mv| {
+                let lines = self.search(&format!("{} searchmoves {}", args, mv))?;
+                let score = lines.iter()
+                    .rev()
+                    .filter_map(|line| InfoRef::parse(line))
+                    .find_map(|info| info.score)
+                    .map(|score| self.normalize(score));
+                Ok((mv.to_string(), score))
+            })
+            .collect()
+    }
+
+    /// Checks whether a move is within a margin of the engine's best move,
+    /// as a second opinion for move validation or cheat screening.
+    ///
+    /// The position is searched once for the engine's choice and, if the
+    /// move isn't it, once more restricted to the move with `searchmoves`.
+    ///
+    /// # Arguments
+    ///
+    /// * `fen`    - The position, in FEN notation.
+    /// * `mv`     - The move to check, in Coordinate notation.
+    /// * `margin` - Centipawns the move may lose against the engine's choice.
+    /// * `limits` - Limits of both searches.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    /// let verdict = engine.verify_move(fen, "d2d4", 30, &engine.limits()).unwrap();
+    /// println!("{}: {} (best {}, loss {:?})", verdict.mv, verdict.accepted,
+    ///          verdict.bestmove, verdict.loss);
+    /// ```
+    pub fn verify_move(&self, fen: &str, mv: &str, margin: i32, limits: &SearchLimits)
+                       -> Result<MoveVerdict> {
+        self.set_position(fen)?;
+        let last_score = |lines: &[String]| lines.iter()
+            .rev()
+            .filter_map(|line| InfoRef::parse(line))
+            .find(|info| info.score.is_some() && info.multipv.unwrap_or(1) == 1)
+            .map(|info| (info.score.map(|score| self.normalize(score)), info.depth));
+
+        let lines = self.search(&limits.go_args())?;
+        let bestmove = best_of(&lines)?;
+        let (best_score, depth) = last_score(&lines).unwrap_or((None, None));
+
+        let score = if bestmove == mv {
+            best_score
+        } else {
+            let lines = self.search(&format!("{} searchmoves {}", limits.go_args(), mv))?;
+            // Engines ignore an illegal move in `searchmoves` and search them all.
+            if best_of(&lines)? == mv { last_score(&lines).and_then(|(score, _)| score) } else { None }
+        };
+
+        Ok(MoveVerdict::new(mv, score, &bestmove, best_score, depth, margin))
+    }
+
+    fn bestmove_with_args(&self, args: &str) -> Result<String> {
+        let lines = self.search(args)?;
+        best_of(&lines)
+    }
+
+    pub fn evaluation(&self) -> Result<i32> {
+        let lines = self.search(&self.go_args())?;
+        self.evaluation_of(&lines)
+    }
+
+    /// Like [`evaluation`], but also returns how far and how fast the search
+    /// went.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// if let Ok((cp, stats)) = engine.evaluation_with_stats() {
+    ///     println!("{} at depth {:?}, {:?} nodes per second", cp, stats.depth, stats.nps);
+    /// }
+    /// ```
+    ///
+    /// [`evaluation`]: #method.evaluation
+    pub fn evaluation_with_stats(&self) -> Result<(i32, SearchStats)> {
+        let lines = self.search(&self.go_args())?;
+        Ok((self.evaluation_of(&lines)?, SearchStats::from_lines(&lines)))
+    }
+
+    /// Searches the current position within the engine's limits, calling
+    /// `on_move` every time the engine reports the root move it's searching,
+    /// and returns the best move. Lets a GUI show where a long search is.
+    ///
+    /// Engines usually only report root moves after searching for a while,
+    /// Stockfish after three seconds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// let mv = engine.bestmove_with_currmove(|current| {
+    ///     println!("depth {:?}: searching {} ({:?})", current.depth, current.mv, current.number);
+    /// }).unwrap();
+    /// ```
+    pub fn bestmove_with_currmove<F>(&self, mut on_move: F) -> Result<String>
+        where F: FnMut(&CurrentMove)
+    {
+        let lines = self.search_with(&self.go_args(), |line| {
+            if let Some(current) = InfoRef::parse(line).and_then(|info| info.current_move()) {
+                on_move(&current);
+            }
+        })?;
+        best_of(&lines)
+    }
+
+    /// Like [`bestmove`], but also returns how far and how fast the search
+    /// went.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// let (mv, stats) = engine.bestmove_with_stats().unwrap();
+    /// println!("{} after {:?} nodes, depth {:?}", mv, stats.nodes, stats.depth);
+    /// ```
+    ///
+    /// [`bestmove`]: #method.bestmove
+    pub fn bestmove_with_stats(&self) -> Result<(String, SearchStats)> {
+        let lines = self.search(&self.go_args())?;
+        Ok((best_of(&lines)?, SearchStats::from_lines(&lines)))
+    }
+
+    /// The normalized centipawn score of the main line of a search output.
+    fn evaluation_of(&self, lines: &[String]) -> Result<i32> {
+        let score = lines.iter()
+            .rev()
+            .filter_map(|line| Info::parse(line))
+            .find(|info| info.score.is_some() && info.multipv.unwrap_or(1) == 1)
+            .and_then(|info| info.score);
+
+        match score.map(|score| self.normalize(score)) {
+            Some(Score::Cp(cp)) => Ok(cp),
+            _ => Err(EngineError::NotFound),
+        }
+    }
+
+    /// Searches the current position within the engine's limits, calling
+    /// `on_info` with every `info` line as it arrives, and returns the best
+    /// move.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// let mv = engine.bestmove_with_info(|info| {
+    ///     if let (Some(depth), Some(score)) = (info.depth, info.score) {
+    ///         println!("depth {}: {:?} {}", depth, score, info.pv.join(" "));
+    ///     }
+    /// }).unwrap();
+    /// ```
+    pub fn bestmove_with_info<F>(&self, mut on_info: F) -> Result<String>
+        where F: FnMut(Info)
+    {
+        let lines = self.search_with(&self.go_args(), |line| {
+            if let Some(info) = Info::parse(line) {
+                on_info(info);
+            }
+        })?;
+        best_of(&lines)
+    }
+    
+    /// Analyses the current position within the given limits, e.g. those of
+    /// a [`Quality`] preset, and returns the candidate moves.
+    ///
+    /// `MultiPV` is set to the limits' value for this search only, and only
+    /// if the engine declares it; otherwise just its best line is returned.
+    ///
+    /// See [`Presets`] for an example.
+    ///
+    /// [`Quality`]: quality/enum.Quality.html
+    /// [`Presets`]: quality/struct.Presets.html
+    pub fn analyze(&self, limits: &quality::AnalysisLimits) -> Result<overlay::Overlay> {
+        let declared = self.declared_options();
+        let _multipv = if declared.iter().any(|option| option.name.eq_ignore_ascii_case("MultiPV")) {
+            Some(self.with_option("MultiPV", &limits.multipv.to_string())?)
+        } else {
+            None
+        };
+        let lines = self.search(&format!("movetime {}", limits.movetime))?;
+        Ok(overlay::Overlay::from_info_lines(lines.iter().map(String::as_str)))
+    }
+
+    /// Returns the evaluation of the current position along with the win,
+    /// draw and loss chances the engine reported, if [`show_wdl`] is enabled.
+    ///
+    /// The score is normalized as for [`evaluation`]. Fails with
+    /// [`EngineError::NotFound`] if the engine sent no score.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// engine.show_wdl(true).unwrap();
+    /// let (score, wdl) = engine.evaluation_with_wdl().unwrap();
+    /// if let Some(wdl) = wdl {
+    ///     println!("{:?}: {} permill of draws", score, wdl.draw);
+    /// }
+    /// ```
+    ///
+    /// [`show_wdl`]: #method.show_wdl
+    /// [`evaluation`]: #method.evaluation
+    /// [`EngineError::NotFound`]: enum.EngineError.html#variant.NotFound
+    pub fn evaluation_with_wdl(&self) -> Result<(Score, Option<Wdl>)> {
+        let lines = self.search(&self.go_args())?;
+        let last = lines.iter()
+            .rev()
+            .filter_map(|line| InfoRef::parse(line))
+            .find(|info| info.score.is_some() && info.multipv.unwrap_or(1) == 1)
+            .ok_or(EngineError::NotFound)?;
+
+        Ok((self.normalize(last.score.unwrap()), last.wdl))
+    }
+
+    /// Makes the engine send win, draw and loss chances with its scores, by
+    /// setting `UCI_ShowWDL`. Fails with [`EngineError::UnknownOption`] if
+    /// the engine doesn't declare it.
+    ///
+    /// [`EngineError::UnknownOption`]: enum.EngineError.html#variant.UnknownOption
+    pub fn show_wdl(&self, enabled: bool) -> Result<()> {
+        self.set_declared_check("UCI_ShowWDL", enabled)
+    }
+
+    /// Makes the engine send the line it's searching in its `info` lines, by
+    /// setting `UCI_ShowCurrLine`. Read it with [`InfoRef::currline_moves`],
+    /// from a [`subscribe`]d stream or [`bestmove_with_info`]. Fails with
+    /// [`EngineError::UnknownOption`] if the engine doesn't declare the
+    /// option.
+    ///
+    /// [`InfoRef::currline_moves`]: struct.InfoRef.html#method.currline_moves
+    /// [`subscribe`]: #method.subscribe
+    /// [`bestmove_with_info`]: #method.bestmove_with_info
+    /// [`EngineError::UnknownOption`]: enum.EngineError.html#variant.UnknownOption
+    pub fn show_currline(&self, enabled: bool) -> Result<()> {
+        self.set_declared_check("UCI_ShowCurrLine", enabled)
+    }
+
+    /// Sets a `check` option, failing if the engine doesn't declare it even
+    /// when it declares no options at all.
+    fn set_declared_check(&self, name: &str, enabled: bool) -> Result<()> {
+        if !self.declared_options().iter().any(|option| option.name.eq_ignore_ascii_case(name)) {
+            return Err(EngineError::UnknownOption(name.to_string()));
+        }
+        self.set_option(name, if enabled { "true" } else { "false" })
+    }
+
+    /// Sets how many lines the engine searches and reports, with the
+    /// `MultiPV` option. Read them with [`variations`].
+    ///
+    /// Fails as [`set_spin_option`] does, if the engine doesn't declare
+    /// `MultiPV` or `lines` is out of its range.
+    ///
+    /// [`variations`]: #method.variations
+    /// [`set_spin_option`]: #method.set_spin_option
+    pub fn multipv(&self, lines: u32) -> Result<()> {
+        self.set_spin_option("MultiPV", i64::from(lines))
+    }
+
+    /// Searches the current position within the engine's limits and returns
+    /// the lines it reported, best first, as many as set with [`multipv`].
+    ///
+    /// For every line, the last version sent is kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// engine.multipv(3).unwrap();
+    /// for variation in engine.variations().unwrap() {
+    ///     println!("{}. {:?} {}", variation.rank, variation.score, variation.pv.join(" "));
+    /// }
+    /// ```
+    ///
+    /// [`multipv`]: #method.multipv
+    pub fn variations(&self) -> Result<Vec<Variation>> {
+        let lines = self.search(&self.go_args())?;
+        let overlay = overlay::Overlay::from_info_lines(lines.iter().map(String::as_str));
+        Ok(overlay.candidates.into_iter().map(Variation::from).collect())
+    }
+
+    /// Tells whether the current position is mate, stalemate or neither.
+    ///
+    /// Runs a shallow search. When the engine doesn't say whether a position
+    /// without moves is mate, its `d` command is used to find out if the side
+    /// to move is in check, which Stockfish supports.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use uci::PositionStatus;
+    ///
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// engine.set_position("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+    /// println!("{:?}", engine.position_status().unwrap());
+    /// ```
+    pub fn position_status(&self) -> Result<PositionStatus> {
+        let lines = self.search("depth 1")?;
+        if let Some(status) = PositionStatus::from_search(lines.iter().map(String::as_str)) {
+            return Ok(status);
+        }
+
+        match status::in_check(&self.command("d")?) {
+            Some(true) => Ok(PositionStatus::Checkmate),
+            Some(false) => Ok(PositionStatus::Stalemate),
+            None => Err(EngineError::NotFound),
+        }
+    }
+
+    /// Finds out what the opponent threatens in a position: the side to move
+    /// passes and the engine searches the resulting position.
+    ///
+    /// Returns `None` when the side to move is in check, as passing isn't
+    /// possible then, which is found out with the engine's `d` command. The
+    /// engine's position is replaced.
+    ///
+    /// # Arguments
+    ///
+    /// * `fen` - The position, in FEN notation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// let fen = "r1bqkbnr/pppp1ppp/2n5/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR b KQkq - 3 3";
+    /// if let Some(threat) = engine.threat(fen).unwrap() {
+    ///     println!("threatening {:?}", threat.mv());
+    /// }
+    /// ```
+    pub fn threat(&self, fen: &str) -> Result<Option<threat::Threat>> {
+        let passed = threat::null_move(fen).ok_or(EngineError::NotFound)?;
+        self.set_position(fen)?;
+        if status::in_check(&self.command("d")?) == Some(true) {
+            return Ok(None);
+        }
+
+        self.set_position(&passed)?;
+        let lines = self.search(&self.go_args())?;
+        Ok(Some(threat::Threat::from_search(passed, &lines)))
+    }
+
+    /// Starts a [`Game`] from the position, to play moves while keeping
+    /// track of draws by repetition and the fifty-move rule.
+    ///
+    /// # Arguments
+    ///
+    /// * `fen` - The starting position, in FEN notation.
+    ///
+    /// [`Game`]: game/struct.Game.html
+    #[cfg(feature = "board")]
+    pub fn game(&self, fen: &str) -> Result<game::Game<'_>> {
+        game::Game::new(self, fen)
+    }
+
+    /// Starts an analysis of the position that can be continued with
+    /// [`AnalysisSession::deepen`].
+    ///
+    /// # Arguments
+    ///
+    /// * `fen` - The position to analyse, in FEN notation.
+    ///
+    /// [`AnalysisSession::deepen`]: session/struct.AnalysisSession.html#method.deepen
+    pub fn session(&self, fen: &str) -> Result<session::AnalysisSession<'_>> {
+        session::AnalysisSession::new(self, fen)
+    }
+
+    /// Sets an engine specific option to the given value
+    ///
+    /// The protocol doesn't allow changing options during a search. Options
+    /// set while one is running are applied as soon as it finishes, unless
+    /// [`strict_options`] is enabled.
+    ///
+    /// Options the engine didn't declare in the handshake fail with
+    /// [`EngineError::UnknownOption`] without being sent, as many engines
+    /// ignore them silently. For engines that declare no options at all,
+    /// any option is sent. Values of `combo` options must be one of the
+    /// [`combo_values`], or the call fails with
+    /// [`EngineError::InvalidOptionValue`].
+    ///
+    /// `info` lines the engine sends in answer, like the name of a network
+    /// it loaded, go to [`subscribe`]rs rather than being taken for errors.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`  - Name of the option
+    /// * `value` - New value for the option
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// engine.set_option("Skill Level", "5").unwrap();
+    /// assert!(engine.set_option("No Such Option", "5").is_err());
+    /// ```
+    ///
+    /// [`strict_options`]: #method.strict_options
+    /// [`combo_values`]: #method.combo_values
+    /// [`subscribe`]: #method.subscribe
+    /// [`EngineError::UnknownOption`]: enum.EngineError.html#variant.UnknownOption
+    /// [`EngineError::InvalidOptionValue`]: enum.EngineError.html#variant.InvalidOptionValue
+    pub fn set_option(&self, name: &str, value: &str) -> Result<()> {
+        let declared = self.declared_options();
+        match declared.iter().find(|option| option.name.eq_ignore_ascii_case(name)) {
+            None if !declared.is_empty() => return Err(EngineError::UnknownOption(name.to_string())),
+            Some(option) if option.kind == "combo" && !option.vars.iter().any(|var| var == value) => {
+                return Err(EngineError::InvalidOptionValue(
+                    name.to_string(),
+                    format!("'{}' isn't one of {}", value, option.vars.join(", "))));
+            }
+            _ => {}
+        }
+
+        if self.searching.get() {
+            if self.strict_options {
+                return Err(EngineError::Searching);
+            }
+            self.pending_options.borrow_mut().push((name.to_string(), value.to_string()));
+            return Ok(());
+        }
+
+        self.write_fmt(format_args!("setoption name {} value {}\n",
+                                    name, value))?;
+        if self.read_option_errors()?.trim().is_empty() {
+            self.option_values.borrow_mut().insert(name.to_lowercase(), value.to_string());
+            Ok(())
+        } else {
+            Err(EngineError::UnknownOption(name.to_string()))
+        }
+    }
+
+    /// Triggers a `button` option, like `Clear Hash`, by sending
+    /// `setoption name <name>` without a value, as the protocol specifies.
+    ///
+    /// Fails with [`EngineError::Searching`] during a search, with
+    /// [`EngineError::UnknownOption`] for options the engine didn't declare
+    /// and with [`EngineError::InvalidOptionValue`] for options of another
+    /// type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// engine.push_button("Clear Hash").unwrap();
+    /// ```
+    ///
+    /// [`EngineError::Searching`]: enum.EngineError.html#variant.Searching
+    /// [`EngineError::UnknownOption`]: enum.EngineError.html#variant.UnknownOption
+    /// [`EngineError::InvalidOptionValue`]: enum.EngineError.html#variant.InvalidOptionValue
+    pub fn push_button(&self, name: &str) -> Result<()> {
+        if self.searching.get() {
+            return Err(EngineError::Searching);
+        }
+        let declared = self.declared_options();
+        match declared.iter().find(|option| option.name.eq_ignore_ascii_case(name)) {
+            None if !declared.is_empty() => return Err(EngineError::UnknownOption(name.to_string())),
+            Some(option) if option.kind != "button" => {
+                return Err(EngineError::InvalidOptionValue(
+                    name.to_string(), format!("a {} option isn't a button", option.kind)));
+            }
+            _ => {}
+        }
+
+        self.write_fmt(format_args!("setoption name {}\n", name))?;
+        if self.read_option_errors()?.trim().is_empty() {
+            Ok(())
+        } else {
+            Err(EngineError::UnknownOption(name.to_string()))
+        }
+    }
+
+    /// Reads what the engine answers to a `setoption`, which is only ever an
+    /// error. With [`route_noise`], lines that don't mention options are
+    /// moved to the noise.
+    ///
+    /// [`route_noise`]: #method.route_noise
+    fn read_option_errors(&self) -> Result<String> {
+        // `info` lines, like an engine reporting the network it just loaded,
+        // have been published already and aren't errors.
+        let output: Vec<String> = self.drain_output(|_| {})?
+            .into_iter()
+            .filter(|line| !line.starts_with("info"))
+            .collect();
+        if !self.route_noise {
+            return Ok(output.join("\n"));
+        }
+
+        let (errors, noise): (Vec<String>, Vec<String>) = output.into_iter()
+            .partition(|line| line.to_lowercase().contains("option"));
+        self.noise.borrow_mut().extend(noise);
+        Ok(errors.join("\n"))
+    }
+    
+    /// Returns the values a `combo` option may take, in the order the engine
+    /// declared them, for presenting a choice. `None` if the engine didn't
+    /// declare a `combo` option of that name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// if let Some(values) = engine.combo_values("Style") {
+    ///     engine.set_option("Style", &values[0]).unwrap();
+    /// }
+    /// ```
+    pub fn combo_values(&self, name: &str) -> Option<Vec<String>> {
+        self.options().into_iter().find_map(|option| match option {
+            options::EngineOption::Combo { name: declared, vars, .. } if declared.eq_ignore_ascii_case(name) => {
+                Some(vars)
+            }
+            _ => None,
+        })
+    }
+
+    /// Sets a `spin` option after checking the value against the range the
+    /// engine declared, instead of leaving the engine to clamp or ignore it.
+    ///
+    /// Fails with [`EngineError::InvalidOptionValue`] for values out of
+    /// range and options of another type, and with
+    /// [`EngineError::UnknownOption`] for options the engine didn't declare.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`  - Name of the option
+    /// * `value` - New value for the option
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// engine.set_spin_option("Skill Level", 5).unwrap();
+    /// assert!(engine.set_spin_option("Skill Level", 50).is_err());
+    /// ```
+    ///
+    /// [`EngineError::InvalidOptionValue`]: enum.EngineError.html#variant.InvalidOptionValue
+    /// [`EngineError::UnknownOption`]: enum.EngineError.html#variant.UnknownOption
+    pub fn set_spin_option(&self, name: &str, value: i64) -> Result<()> {
+        let option = self.options().into_iter()
+            .find(|option| option.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| EngineError::UnknownOption(name.to_string()))?;
+        let invalid = |reason: String| Err(EngineError::InvalidOptionValue(name.to_string(), reason));
+
+        match option {
+            options::EngineOption::Spin { min: Some(min), .. } if value < min => {
+                invalid(format!("{} is below the minimum of {}", value, min))
+            }
+            options::EngineOption::Spin { max: Some(max), .. } if value > max => {
+                invalid(format!("{} is above the maximum of {}", value, max))
+            }
+            options::EngineOption::Spin { .. } => self.set_option(name, &value.to_string()),
+            _ => invalid("not a spin option".to_string()),
+        }
+    }
+
+    /// Makes the engine avoid draws, or seek them with a negative level, by
+    /// setting whichever of the usual options it declares: `Contempt`, or
+    /// else `DrawScore`, which counts the other way around.
+    ///
+    /// The value is clamped to the range the engine declares. Fails with
+    /// [`EngineError::UnknownOption`] if the engine declares neither option.
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - How much worse a draw looks to the engine, in centipawns. 0 is neutral.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// engine.set_draw_aversion(24).unwrap();
+    /// ```
+    ///
+    /// [`EngineError::UnknownOption`]: enum.EngineError.html#variant.UnknownOption
+    pub fn set_draw_aversion(&self, level: i32) -> Result<()> {
+        let declared = self.declared_options();
+        let find = |name: &str| declared.iter().find(|option| option.name.eq_ignore_ascii_case(name));
+        let (option, value) = match (find("Contempt"), find("DrawScore")) {
+            (Some(contempt), _) => (contempt, i64::from(level)),
+            (None, Some(draw_score)) => (draw_score, -i64::from(level)),
+            (None, None) => return Err(EngineError::UnknownOption("Contempt".to_string())),
+        };
+
+        let bound = |limit: &Option<String>| limit.as_ref().and_then(|n| n.parse::<i64>().ok());
+        let value = bound(&option.min).map_or(value, |min| value.max(min));
+        let value = bound(&option.max).map_or(value, |max| value.min(max));
+        self.set_option(&option.name, &value.to_string())
+    }
+
+    /// Makes the engine play at about the given Elo rating.
+    ///
+    /// Engines declaring `UCI_LimitStrength` and `UCI_Elo` get both set, the
+    /// rating clamped to the range they declare. Otherwise, the rating is
+    /// mapped onto the range of `Skill Level`, taking 1320 to 3190 as the
+    /// span of its levels as Stockfish does. Fails with
+    /// [`EngineError::UnknownOption`] if the engine declares neither.
+    ///
+    /// # Arguments
+    ///
+    /// * `elo` - The rating to play at
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// engine.limit_elo(1500).unwrap();
+    /// ```
+    ///
+    /// [`EngineError::UnknownOption`]: enum.EngineError.html#variant.UnknownOption
+    pub fn limit_elo(&self, elo: u32) -> Result<()> {
+        const ELO_RANGE: (i64, i64) = (1320, 3190);
+
+        let declared = self.declared_options();
+        let find = |name: &str| declared.iter().find(|option| option.name.eq_ignore_ascii_case(name));
+        let bound = |limit: &Option<String>| limit.as_ref().and_then(|n| n.parse::<i64>().ok());
+
+        if let (Some(limit), Some(rating)) = (find("UCI_LimitStrength"), find("UCI_Elo")) {
+            let elo = i64::from(elo);
+            let elo = bound(&rating.min).map_or(elo, |min| elo.max(min));
+            let elo = bound(&rating.max).map_or(elo, |max| elo.min(max));
+            self.set_option(&limit.name, "true")?;
+            return self.set_option(&rating.name, &elo.to_string());
+        }
+
+        let skill = find("Skill Level").ok_or_else(|| EngineError::UnknownOption("UCI_Elo".to_string()))?;
+        let (min, max) = (bound(&skill.min).unwrap_or(0), bound(&skill.max).unwrap_or(20));
+        let elo = i64::from(elo).max(ELO_RANGE.0).min(ELO_RANGE.1);
+        let level = min + (elo - ELO_RANGE.0) * (max - min) / (ELO_RANGE.1 - ELO_RANGE.0);
+        self.set_option(&skill.name, &level.to_string())
+    }
+
+    /// Changes an option until the returned guard is dropped, when its
+    /// previous value is set again.
+    ///
+    /// The previous value is the last one set, or the declared default. An
+    /// option with neither, like a button, isn't restored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// {
+    ///     let _wide = engine.with_option("MultiPV", "3").unwrap();
+    ///     println!("{}", engine.command("go depth 10").unwrap());
+    /// }
+    /// // Back to a single line.
+    /// engine.bestmove().unwrap();
+    /// ```
+    pub fn with_option(&self, name: &str, value: &str) -> Result<options::OptionGuard<'_>> {
+        let previous = self.option_value(name);
+        self.set_option(name, value)?;
+        Ok(options::OptionGuard::new(self, name, previous))
+    }
+
+    /// Prepares the engine for analysis rather than play, or back.
+    ///
+    /// Enabling sets `UCI_AnalyseMode`, turns off `UCI_LimitStrength` and
+    /// sets `Contempt` to zero, skipping any option the engine doesn't
+    /// declare. Left as they are, these skew the evaluations of a review.
+    /// Disabling only resets `UCI_AnalyseMode`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// engine.analysis_mode(true).unwrap();
+    /// ```
+    pub fn analysis_mode(&self, enabled: bool) -> Result<()> {
+        let mut changes = vec![("UCI_AnalyseMode", if enabled { "true" } else { "false" })];
+        if enabled {
+            changes.push(("UCI_LimitStrength", "false"));
+            changes.push(("Contempt", "0"));
+        }
+
+        let declared = self.declared_options();
+        for (name, value) in changes {
+            if declared.iter().any(|option| option.name.eq_ignore_ascii_case(name)) {
+                self.set_option(name, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Switches the engine's debug mode with `debug on` or `debug off`.
+    ///
+    /// Engines in debug mode send extra `info string` lines. While it's on,
+    /// these are logged at debug level; subscribers get them as
+    /// [`Event::Info`] either way.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// engine.set_debug(true).unwrap();
+    /// engine.set_position("8/8/8/8/8/8/8/K1k5 w - - 0 1").unwrap();
+    /// engine.set_debug(false).unwrap();
+    /// ```
+    ///
+    /// [`Event::Info`]: events/enum.Event.html#variant.Info
+    pub fn set_debug(&self, enabled: bool) -> Result<()> {
+        self.command(if enabled { "debug on" } else { "debug off" })?;
+        self.debug.set(enabled);
+        Ok(())
+    }
+
+    /// Sends a command to the engine and returns the output
+    ///
+    /// Output is collected until the engine answers a following `isready`,
+    /// or, for a `go` command with a limit, until it sends `bestmove`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// let analysis = engine.command("go depth 10").unwrap();
+    /// println!("{}", analysis);
+    /// ```
+    pub fn command(&self, cmd: &str) -> Result<String> {
+        self.command_with_progress(cmd, |_| {})
+    }
+
+    /// Like [`command`], but calls `progress` with every line as soon as it
+    /// arrives. Useful for commands that keep printing for a long time, like
+    /// `bench` or `perft`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// let output = engine.command_with_progress("bench", |line| println!("{}", line)).unwrap();
+    /// assert!(output.contains("Nodes searched"));
+    /// ```
+    ///
+    /// [`command`]: #method.command
+    pub fn command_with_progress<F>(&self, cmd: &str, progress: F) -> Result<String>
+        where F: FnMut(&str)
+    {
+        Ok(self.collect_output(cmd, progress)?.join("\n"))
+    }
+
+    /// Like [`command`], but returns the lines apart and classified by kind,
+    /// for interpreting the output of engines whose answers aren't known in
+    /// advance.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use uci::LineKind;
+    ///
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// let output = engine.command_output("go depth 5").unwrap();
+    /// for line in output.of_kind(LineKind::Info) {
+    ///     println!("depth {:?}", line.info().unwrap().depth);
+    /// }
+    /// assert!(output.bestmove().is_some());
+    /// ```
+    ///
+    /// [`command`]: #method.command
+    pub fn command_output(&self, cmd: &str) -> Result<CommandOutput> {
+        Ok(CommandOutput::new(self.collect_output(cmd, |_| {})?))
+    }
 
-use std::fmt;
-use std::thread;
-use std::time::Duration;
+    fn collect_output<F>(&self, cmd: &str, mut progress: F) -> Result<Vec<String>>
+        where F: FnMut(&str)
+    {
+        let cmd = cmd.trim();
+        let mut words = cmd.split_whitespace();
+        let search = words.next() == Some("go") && words.all(|w| w != "infinite" && w != "ponder");
+        if search {
+            self.write_search(format_args!("{}\n", cmd))?;
+        } else {
+            self.write_fmt(format_args!("{}\n", cmd))?;
+        }
 
-use std::cell::RefCell;
+        let mut s: Vec<String> = vec![];
+        if search {
+            s = self.read_search_output(|line| progress(line.trim()))?
+                .iter()
+                .map(|line| line.trim().to_string())
+                .collect();
+        }
 
-mod error;
-pub use error::{Result, EngineError};
+        s.extend(self.drain_output(progress)?);
+        if self.route_noise && cmd.split_whitespace().next().is_some_and(|w| UCI_COMMANDS.contains(&w)) {
+            s = self.split_noise(s);
+        }
+        Ok(s)
+    }
 
-pub struct Engine {
-    engine: RefCell<Child>,
+    /// Reads the output of a search that was just started, up to and
+    /// including the `bestmove` line. Options set meanwhile are applied once
+    /// the search is over.
+    fn read_search_output<F>(&self, mut progress: F) -> Result<Vec<String>>
+        where F: FnMut(&str)
+    {
+        self.searching.set(true);
+        if self.interrupter.take_request() {
+            self.abort.set(Some(AbortReason::Stopped));
+            self.write_fmt(format_args!("stop\n"))?;
+        }
+        self.probe.start();
+        let start = self.search_start.take().unwrap_or_else(Instant::now);
+        let mut lines = vec![];
+        let res = loop {
+            match self.read_line() {
+                Ok(s) => {
+                    self.probe.update(&s);
+                    progress(&s);
+                    let done = s.starts_with("bestmove");
+                    lines.push(s);
+                    if done {
+                        break Ok(lines);
+                    }
+                }
+                Err(e) => break Err(e),
+            }
+        };
+        self.searching.set(false);
+        let interrupted = self.interrupter.finish_search();
+        if let Some(reason) = res.as_ref().err().and_then(AbortReason::of) {
+            self.abort.set(Some(reason));
+        } else if interrupted && self.abort.get().is_none() {
+            self.abort.set(Some(AbortReason::Stopped));
+        }
+        self.probe.finish();
+        if let Ok(ref lines) = res {
+            let mut stats = self.stats.get();
+            stats.record(lines, start.elapsed());
+            self.stats.set(stats);
+        }
 
-    movetime: u32,
-    depth: Option<u32>,
-}
+        let pending: Vec<(String, String)> = self.pending_options.borrow_mut().drain(..).collect();
+        for (name, value) in pending {
+            if let Err(e) = self.set_option(&name, &value) {
+                engine_log!(self.logger, Warn, "Deferred option '{}' could not be set: {}", name, e);
+            }
+        }
 
-const DEFAULT_TIME: u32 = 100;
+        res
+    }
 
-impl Engine {
-    /// Create a new [`Engine`] instance.
+    /// Checks that the engine is responsive and returns how long it took to
+    /// answer `isready`.
     ///
-    /// # Arguments
-    /// 
-    /// * `path` - The path to the engine executable.
+    /// An engine that doesn't answer within `timeout` is killed, as it can't
+    /// be talked to anymore, and [`EngineError::Timeout`] is returned.
+    ///
+    /// # Examples
     ///
-    /// # Panics
+    /// ```
+    /// use std::time::Duration;
     ///
-    /// * Panics if the engine couldn't be spawned (path is invalid, execution permission denied, etc.)
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// let latency = engine.ping(Duration::from_secs(1)).unwrap();
+    /// println!("isready answered after {:?}", latency);
+    /// ```
     ///
-    /// [`Engine`]: struct.Engine.html
-    pub fn new(path: &str) -> Result<Engine> {
-        let cmd = Command::new(path)
-                          .stdin(Stdio::piped())
-                          .stdout(Stdio::piped())
-                          .spawn()
-                          .expect("Unable to run engine");
-
-        let res = Engine {
-            engine: RefCell::new(cmd),
-            movetime: DEFAULT_TIME,
-            depth: None,
+    /// [`EngineError::Timeout`]: enum.EngineError.html#variant.Timeout
+    pub fn ping(&self, timeout: Duration) -> Result<Duration> {
+        let start = Instant::now();
+        self.write_fmt(format_args!("isready\n"))?;
+
+        let watchdog = Watchdog::arm(self.engine.clone(), self.logger.clone(), vec![(start + timeout, Action::Kill)]);
+        let res = loop {
+            match self.read_line() {
+                Ok(ref s) if s.trim() == "readyok" => {
+                    self.record_latency(start.elapsed());
+                    break Ok(start.elapsed());
+                }
+                Ok(_) => {}
+                Err(e) => break Err(e),
+            }
         };
+        match res {
+            Err(_) if watchdog.fired().is_some() => {
+                Err(EngineError::Timeout(self.tail.lines()).labelled(self.label()))
+            }
+            res => res,
+        }
+    }
+
+    /// Kills the engine process.
+    pub(crate) fn kill(&self) {
+        let _ = self.engine.lock().unwrap().kill();
+    }
+
+    /// Performs the `uci` handshake, keeping the protocol lines apart from
+    /// whatever else the engine prints before, during and right after it.
+    pub(crate) fn handshake(&mut self, registration: Option<&(String, String)>) -> Result<()> {
+        self.write_fmt(format_args!("uci\n"))?;
+        loop {
+            let line = self.read_line()?;
+            let line = line.trim();
+            if line == "uciok" {
+                break;
+            } else if line.starts_with("id ") || line.starts_with("option ") {
+                self.handshake.push(line.to_string());
+            } else if !line.is_empty() {
+                self.banner.push(line.to_string());
+            }
+        }
 
-        res.read_line()?;
-        res.command("uci")?;
+        let after = self.drain_output(|_| {})?;
+        self.banner.extend(after.into_iter().filter(|line| !line.is_empty()));
 
-        Ok(res)
+        if !self.check_passed("copyprotection")? {
+            return Err(EngineError::CopyProtection);
+        }
+        if !self.check_passed("registration")? {
+            match registration {
+                Some((name, code)) => {
+                    self.write_fmt(format_args!("register name {} code {}\n", name, code))?;
+                    if self.wait_for_check("registration")? != "ok" {
+                        return Err(EngineError::Registration);
+                    }
+                }
+                None => {
+                    engine_log!(self.logger, Warn, "Engine asks for a registration, none was given");
+                    self.write_fmt(format_args!("register later\n"))?;
+                }
+            }
+        }
+        Ok(())
     }
 
-    /// Changes the amount of time the engine spends looking for a move
-    ///
-    /// # Arguments
-    /// 
-    /// * `new_movetime` - New timelimit in milliseconds
-    pub fn movetime(mut self, new_movetime: u32) -> Engine {
-        self.movetime = new_movetime;
-        self
+    /// Whether the copy protection or registration check announced by the
+    /// engine passed, waiting for its outcome if it's still running. Engines
+    /// that don't check count as passing.
+    fn check_passed(&mut self, check: &str) -> Result<bool> {
+        let last = self.banner.iter()
+            .rev()
+            .find(|line| line.split_whitespace().next() == Some(check))
+            .and_then(|line| line.split_whitespace().nth(1))
+            .map(str::to_string);
+        self.banner.retain(|line| line.split_whitespace().next() != Some(check));
+
+        let outcome = match last.as_deref() {
+            None => return Ok(true),
+            Some("checking") => self.wait_for_check(check)?,
+            Some(outcome) => outcome.to_string(),
+        };
+        Ok(outcome == "ok")
     }
 
-    /// Changes the the engine depth when looking for a move
-    ///
-    /// # Arguments
-    ///
-    /// * `new_depth` - New depth, as an Option
-    pub fn depth(mut self, new_depth: Option<u32>) -> Engine {
-        self.depth = new_depth;
-        self
+    /// Reads up to the `ok` or `error` outcome of a check, which is returned.
+    fn wait_for_check(&mut self, check: &str) -> Result<String> {
+        loop {
+            let line = self.read_line()?;
+            let mut words = line.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some(word), Some(outcome)) if word == check && outcome != "checking" => {
+                    return Ok(outcome.to_string());
+                }
+                (Some(word), _) if word == check => {}
+                _ if !line.trim().is_empty() => self.banner.push(line.trim().to_string()),
+                _ => {}
+            }
+        }
     }
 
-    /// Asks the engine to play the given moves from the initial position on it's internal board.
-    /// 
-    /// # Arguments
+    /// The label given with [`EngineBuilder::label`], if any.
     ///
-    /// * `moves` - A list of moves for the engine to play. Uses Coordinate notation
+    /// [`EngineBuilder::label`]: struct.EngineBuilder.html#method.label
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Returns the lines the engine printed at startup that aren't part of
+    /// the protocol, like its version, authors or build information.
     ///
     /// # Examples
     ///
     /// ```
     /// let engine = uci::Engine::new("stockfish").unwrap();
-    /// let moves = vec!["e2e4".to_string(), "e7e5".to_string()];
-    /// engine.make_moves(&moves).unwrap();
+    /// for line in engine.banner() {
+    ///     println!("{}", line);
+    /// }
     /// ```
-    pub fn make_moves(&self, moves: &[String]) -> Result<()> {
-        self.write_fmt(format_args!("position startpos moves {}\n",
-                                    moves.join(" ")))?;
-        Ok(())
+    pub fn banner(&self) -> &[String] {
+        &self.banner
     }
-    
-    /// Asks the engine to use the position represented by the given FEN string
-    /// 
+
+    /// The engine's name, as sent with `id name` in the handshake.
+    ///
     /// # Examples
     ///
     /// ```
     /// let engine = uci::Engine::new("stockfish").unwrap();
-    /// engine.set_position("2k4R/8/3K4/8/8/8/8/8 b - - 0 1").unwrap();
-    /// assert_eq!(engine.bestmove().unwrap(), "c8b7");
+    /// println!("{} by {}", engine.name().unwrap_or("?"), engine.author().unwrap_or("?"));
     /// ```
-    pub fn set_position(&self, fen: &str) -> Result<()> {
-        let moves: Vec<String> = vec![];
-        self.make_moves_from_position(fen, &moves)
+    pub fn name(&self) -> Option<&str> {
+        self.identity("name")
     }
-    
-    /// Asks the engine to use the position represented by the given FEN string
-    /// and then play the given moves from that position
-    pub fn make_moves_from_position(&self, fen: &str, moves: &Vec<String>) -> Result<()> {
-        self.write_fmt(format_args!("position fen {} moves {}\n",
-                                    fen, moves.join(" ")))?;
-        Ok(())
-    }
-    
-    fn do_move(&self) -> Result<()> {
-        let movetime = self.movetime;
-        if let Some(depth) = self.depth {
-            self.write_fmt(format_args!("go movetime {movetime} depth {depth}\n"))?;
-        } else {
-            self.write_fmt(format_args!("go movetime {movetime}\n"))?;
-        }
 
-        Ok(())
+    /// The engine's authors, as sent with `id author` in the handshake.
+    pub fn author(&self) -> Option<&str> {
+        self.identity("author")
     }
 
-    /// Returns the best move in the current position according to the engine
-    pub fn bestmove(&self) -> Result<String> {
-        self.do_move()?;
-        loop {
-            let s = self.read_line()?;
-            if s.starts_with("bestmove") {
-                return Ok(s.split(" ").collect::<Vec<&str>>()[1].trim().to_string());
-            }
-        }
+    /// The value of an `id` line of the handshake.
+    fn identity(&self, field: &str) -> Option<&str> {
+        self.handshake.iter().find_map(|line| {
+            let rest = line.strip_prefix("id ")?.trim_start();
+            let value = rest.strip_prefix(field)?;
+            if value.starts_with(char::is_whitespace) { Some(value.trim()) } else { None }
+        })
     }
 
-    pub fn evaluation(&self) -> Result<i32> {
-        self.do_move()?;
-        let mut info = String::from("");
-        loop {
-            let s = self.read_line()?;
-            if s.starts_with("info") {
-                info = s.clone();
-            }
-            if s.starts_with("bestmove") {
-                break;
-            }
-        }
-
-        // info depth 25 seldepth 34 multipv 1 score cp -1933 nodes 18521596 nps 853018 hashfull 990 tbhits 0 time 21713 pv d2d3
-        let parts = info.split(' ').collect::<Vec<&str>>();
-        let cp_index = match parts.iter().enumerate().find(|(_i, v)| *v == &"cp") {
-            Some((i, _v)) => i + 1,
-            None => return Err(EngineError::NotFound)
-        };
-
-        match parts[cp_index].parse::<i32>() {
-            Err(_e) => Err(EngineError::NotFound),
-            Ok(n) => Ok(n),
-        }
-    }
-    
-    /// Sets an engine specific option to the given value
+    /// Returns the options the engine declared in the `uci` handshake, in
+    /// the order it declared them.
     ///
-    /// # Arguments
+    /// # Examples
     ///
-    /// * `name`  - Name of the option
-    /// * `value` - New value for the option
+    /// ```
+    /// use uci::options::EngineOption;
+    ///
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// for option in engine.options() {
+    ///     if let EngineOption::Spin { name, min, max, .. } = option {
+    ///         println!("{}: {:?} to {:?}", name, min, max);
+    ///     }
+    /// }
+    /// ```
+    pub fn options(&self) -> Vec<options::EngineOption> {
+        self.declared_options().iter().filter_map(options::Declared::typed).collect()
+    }
+
+    /// Returns a hash of the engine's name and authors and the current
+    /// values of its options, whether set or left at their defaults.
+    ///
+    /// The hash is stable across runs and platforms, so results stored along
+    /// with it can be told apart from those of a differently configured
+    /// engine.
     ///
     /// # Examples
     ///
     /// ```
     /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// let before = engine.config_fingerprint();
     /// engine.set_option("Skill Level", "5").unwrap();
+    /// assert_ne!(engine.config_fingerprint(), before);
     /// ```
-    pub fn set_option(&self, name: &str, value: &str) -> Result<()> {
-        self.write_fmt(format_args!("setoption name {} value {}\n",
-                                    name, value))?;
-        let error_msg =  self.read_left_output()?;
-        
-        if error_msg.trim().is_empty() {
-            Ok(())
-        } else {
-            Err(EngineError::UnknownOption(name.to_string()))
-        }
+    pub fn config_fingerprint(&self) -> u64 {
+        let identity = [self.name(), self.author()].iter()
+            .flat_map(|id| id.unwrap_or("").bytes().chain(Some(0)))
+            .collect::<Vec<u8>>();
+        provenance::fnv1a(identity.into_iter().chain(provenance::options_bytes(self)))
     }
-    
-    /// Sends a command to the engine and returns the output
+
+    /// Describes how a result produced by the engine right now comes about:
+    /// its name, the options set, its search limits, the crate version and
+    /// the time.
+    ///
+    /// See [`traced`] to attach it to a result.
+    ///
+    /// [`traced`]: #method.traced
+    pub fn provenance(&self) -> Provenance {
+        Provenance::new(self)
+    }
+
+    /// Runs an analysis and returns its result along with its
+    /// [`provenance`], as taken when the analysis starts, so stored results
+    /// can be reproduced or kept apart.
     ///
     /// # Examples
     ///
     /// ```
     /// let engine = uci::Engine::new("stockfish").unwrap();
-    /// let analysis = engine.command("go depth 10").unwrap();
-    /// println!("{}", analysis);
+    /// let traced = engine.traced(|engine| engine.bestmove()).unwrap();
+    /// println!("{} by {:?} at {:?}", traced.result, traced.provenance.engine,
+    ///          traced.provenance.timestamp);
     /// ```
-    pub fn command(&self, cmd: &str) -> Result<String> {
-        self.write_fmt(format_args!("{}\n", cmd.trim()))?;
-        thread::sleep(Duration::from_millis(100));
-        self.read_left_output()
+    ///
+    /// [`provenance`]: #method.provenance
+    pub fn traced<T, F>(&self, analysis: F) -> Result<Traced<T>>
+        where F: FnOnce(&Engine) -> Result<T>
+    {
+        Traced::run(self, analysis)
+    }
+
+    /// Options declared in the `uci` handshake.
+    pub(crate) fn declared_options(&self) -> Vec<options::Declared> {
+        self.handshake.iter().filter_map(|line| options::Declared::parse(line)).collect()
+    }
+
+    /// Current value of an option: the last one set, or the declared
+    /// default.
+    pub(crate) fn option_value(&self, name: &str) -> Option<String> {
+        let key = name.to_lowercase();
+        if let Some(value) = self.option_values.borrow().get(&key) {
+            return Some(value.clone());
+        }
+        self.declared_options().into_iter()
+            .find(|declared| declared.name.to_lowercase() == key)
+            .and_then(|declared| declared.default)
     }
 
-    fn read_left_output(&self) -> Result<String> {
+    /// Keeps the protocol lines and moves the others to `noise`.
+    fn split_noise(&self, lines: Vec<String>) -> Vec<String> {
+        let (protocol, noise): (Vec<String>, Vec<String>) = lines.into_iter()
+            .partition(|line| line.is_empty() || is_protocol_line(line));
+        self.noise.borrow_mut().extend(noise);
+        protocol
+    }
+
+    /// Collects everything the engine prints before answering `isready`.
+    fn drain_output<F>(&self, mut progress: F) -> Result<Vec<String>>
+        where F: FnMut(&str)
+    {
         let mut s: Vec<String> = vec![];
 
         self.write_fmt(format_args!("isready\n"))?;
         loop {
             let next_line = self.read_line()?;
             match next_line.trim() {
-                "readyok" => return Ok(s.join("\n")),
-                other     => s.push(other.to_string())
+                "readyok" => return Ok(s),
+                other     => {
+                    progress(other);
+                    s.push(other.to_string())
+                }
             }
         }
     }
 
+    /// Writes a command to the engine. Errors carry the engine's label.
     fn write_fmt(&self, args: fmt::Arguments) -> Result<()> {
-        info!("Command: {:?}", fmt::format(args));
-        self.engine.borrow_mut().stdin.as_mut().unwrap().write_fmt(args)?;
+        self.write_command(args).map_err(|e| e.labelled(self.label()))
+    }
+
+    /// Writes the `go` command of a search, which interrupters can stop from
+    /// then on.
+    fn write_search(&self, args: fmt::Arguments) -> Result<()> {
+        self.interrupter.start_search();
+        let res = self.write_fmt(args);
+        if res.is_err() {
+            self.interrupter.finish_search();
+        }
+        res
+    }
+
+    fn write_command(&self, args: fmt::Arguments) -> Result<()> {
+        let cmd = fmt::format(args);
+        engine_log!(self.logger, Info, "Command: {:?}", cmd);
+
+        // Whatever was written before a failed attempt isn't written again.
+        let mut written = 0;
+        self.retry.run(|| {
+            let mut engine = self.engine.lock().unwrap();
+            let stdin = engine.input();
+            while written < cmd.len() {
+                match stdin.write(&cmd.as_bytes()[written..])? {
+                    0 => return Err(io::Error::new(io::ErrorKind::WriteZero, "engine closed its input")),
+                    n => written += n,
+                }
+            }
+            stdin.flush()
+        })?;
         Ok(())
     }
 
+    /// Reads a line of the engine's output. Errors carry the engine's label.
     fn read_line(&self) -> Result<String> {
-        let mut s = String::new();
-        let mut buf: Vec<u8> = vec![0];
+        self.read_output_line().map_err(|e| e.labelled(self.label()))
+    }
+
+    fn read_output_line(&self) -> Result<String> {
+        let mut reader = self.reader.borrow_mut();
+        let mut line: Vec<u8> = vec![];
+        let mut too_long = false;
 
         loop {
-            self.engine.borrow_mut().stdout.as_mut().unwrap().read(&mut buf)?;
-            s.push(buf[0] as char);
-            if buf[0] == '\n' as u8 {
-                break
+            let (done, used) = {
+                // Once filled, the buffer is handed out without reading again.
+                self.retry.run(|| reader.fill_buf().map(|_| ()))?;
+                let buf = reader.fill_buf()?;
+                if buf.is_empty() {
+                    return Err(EngineError::ProcessExited(self.tail.lines()));
+                }
+                let end = buf.iter().position(|&b| b == b'\n');
+                let used = end.map(|i| i + 1).unwrap_or(buf.len());
+                if !too_long {
+                    line.extend_from_slice(&buf[..used]);
+                }
+                (end.is_some(), used)
+            };
+            reader.consume(used);
+
+            // Once too long, the rest of the line isn't kept, so there's
+            // nothing left to measure.
+            if let Some(max) = self.max_line_length.filter(|_| !too_long) {
+                let len = if done { line.len() - 1 } else { line.len() };
+                if len > max {
+                    too_long = true;
+                    line.clear();
+                }
             }
+            if done {
+                break;
+            }
+        }
+
+        if too_long {
+            return Err(EngineError::LineTooLong(self.max_line_length.unwrap()));
         }
+        if self.dump_bytes && self.logger.enabled(Verbosity::Debug) && has_unusual_bytes(&line) {
+            engine_log!(self.logger, Debug, "Engine sent unusual bytes: {}", hex_dump(&line));
+        }
+        let s = String::from_utf8_lossy(&line).into_owned();
+        self.tail.push("stdout", &s);
+        self.publish(&s);
         Ok(s)
     }
+
+    fn publish(&self, line: &str) {
+        if self.debug.get() && line.starts_with("info string") {
+            engine_log!(self.logger, Debug, "Engine says: {}", line[11..].trim());
+        }
+
+        let event = if line.starts_with("info") {
+            events::Event::Info(line.trim_end().to_string())
+        } else if line.starts_with("bestmove") {
+            events::Event::BestMove(line.trim_end().to_string())
+        } else {
+            return;
+        };
+
+        self.subscribers.borrow_mut().retain(|queue| queue.push(event.clone()));
+    }
+}
+
+/// The move of the `bestmove` line ending a search's output.
+fn best_of(lines: &[String]) -> Result<String> {
+    bestmove_of(lines).map(|(best, _)| best)
+}
+
+/// The move and ponder move of the `bestmove` line ending a search's output.
+/// Fails with `EngineError::NotFound` if there's no such line, or it has no
+/// move.
+fn bestmove_of(lines: &[String]) -> Result<(String, Option<String>)> {
+    let mut words = lines.last().map(|line| line.split_whitespace()).ok_or(EngineError::NotFound)?;
+    if words.next() != Some("bestmove") {
+        return Err(EngineError::NotFound);
+    }
+    let best = words.next().ok_or(EngineError::NotFound)?.to_string();
+    let ponder = match (words.next(), words.next()) {
+        (Some("ponder"), Some(mv)) => Some(mv.to_string()),
+        _ => None,
+    };
+    Ok((best, ponder))
+}
+
+/// Whether a line has bytes other than printable ASCII, tabs and line
+/// terminators.
+fn has_unusual_bytes(line: &[u8]) -> bool {
+    line.iter().any(|&b| !(b == b'\t' || b == b'\r' || b == b'\n' || (b' '..=b'~').contains(&b)))
+}
+
+/// Writes bytes as space separated hex pairs.
+fn hex_dump(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(" ")
+}
+
+/// The `movetime` of `go` arguments, if they have one.
+fn requested_movetime(args: &str) -> Option<Duration> {
+    let mut words = args.split_whitespace();
+    words.by_ref().find(|&word| word == "movetime")?;
+    words.next()?.parse().ok().map(Duration::from_millis)
+}
+
+/// Whether the line is one the protocol defines.
+fn is_protocol_line(line: &str) -> bool {
+    line.split_whitespace().next().is_some_and(|word| UCI_ANSWERS.contains(&word))
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        for queue in self.subscribers.borrow().iter() {
+            queue.close();
+        }
+    }
 }
 
 #[cfg(test)]
+#[allow(unused_variables)]
 mod tests {
     use super::*;
 
@@ -248,6 +2652,184 @@ mod tests {
         let t = engine.bestmove().unwrap();
     }
 
+    #[test]
+    fn test_attach() {
+        use std::process::{Command, Stdio};
+
+        let mut child = Command::new("./stockfish")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let engine = Engine::attach(child.stdin.take().unwrap(), child.stdout.take().unwrap()).unwrap();
+        assert!(!engine.declared_options().is_empty());
+        assert!(engine.name().is_some());
+        engine.bestmove().unwrap();
+
+        drop(engine);
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+
+    #[test]
+    fn test_registration() {
+        let script = |lines: &str| io::Cursor::new(lines.as_bytes().to_vec());
+
+        let protected = script("uciok\ncopyprotection checking\nreadyok\ncopyprotection error\n");
+        match Engine::attach(io::sink(), protected) {
+            Err(EngineError::CopyProtection) => {}
+            other => panic!("unexpected handshake result: {:?}", other.map(|_| ())),
+        }
+
+        let registered = script("uciok\nregistration error\nreadyok\nregistration checking\nregistration ok\n");
+        EngineBuilder::new("attached engine")
+            .register("Jane Doe", "1234")
+            .attach(io::sink(), registered)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_long_line_over_several_reads() {
+        let output = format!("id name {}\nuciok\nreadyok\n", "x".repeat(32));
+        let res = EngineBuilder::new("attached engine")
+            .read_buffer_size(16)
+            .max_line_length(20)
+            .attach(io::sink(), io::Cursor::new(output.into_bytes()));
+        match res {
+            Err(EngineError::LineTooLong(20)) => {}
+            other => panic!("unexpected handshake result: {:?}", other.map(|_| ())),
+        }
+
+        let output = "id author José\nuciok\nreadyok\n";
+        let engine = EngineBuilder::new("attached engine")
+            .read_buffer_size(0)
+            .attach(io::sink(), io::Cursor::new(output.as_bytes().to_vec()))
+            .unwrap();
+        assert_eq!(engine.author(), Some("José"));
+    }
+
+    #[test]
+    fn test_analyze_scopes_multipv() {
+        let limits = quality::AnalysisLimits { movetime: 10, multipv: 5 };
+
+        let engine = Engine::new("./stockfish").unwrap();
+        engine.analyze(&limits).unwrap();
+        assert_eq!(engine.option_value("MultiPV").as_deref(), Some("1"));
+
+        let output = "uciok\nreadyok\ninfo depth 1 score cp 20 pv e2e4\nbestmove e2e4\n";
+        let engine = Engine::attach(io::sink(), io::Cursor::new(output.as_bytes().to_vec())).unwrap();
+        assert_eq!(engine.analyze(&limits).unwrap().candidates.len(), 1);
+    }
+
+    #[test]
+    fn test_info_during_commands() {
+        let output = "option name EvalFile type string default nn.nnue\nuciok\nreadyok\n\
+                      info string NNUE evaluation using big.nnue enabled\nreadyok\n\
+                      info string NNUE evaluation using big.nnue enabled\nreadyok\n";
+        let engine = Engine::attach(io::sink(), io::Cursor::new(output.as_bytes().to_vec())).unwrap();
+        let events = engine.subscribe(events::ChannelPolicy::Unbounded);
+
+        engine.set_option("EvalFile", "big.nnue").unwrap();
+        engine.new_game().unwrap();
+        assert_eq!(engine.option_value("EvalFile").as_deref(), Some("big.nnue"));
+        match events.try_recv() {
+            Some(events::Event::Info(line)) => assert!(line.contains("big.nnue")),
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_protocol_lines() {
+        assert!(is_protocol_line("info depth 1 score cp 20"));
+        assert!(is_protocol_line("bestmove e2e4"));
+        assert!(!is_protocol_line("information"));
+        assert!(!is_protocol_line("Stockfish 16 by the Stockfish developers"));
+    }
+
+    #[test]
+    fn test_option_validation() {
+        let engine = Engine::new("./stockfish").unwrap();
+        assert_eq!(engine.combo_values("style").unwrap(), vec!["Solid", "Normal", "Risky"]);
+        engine.set_option("Style", "Risky").unwrap();
+        match engine.set_option("Style", "Wild") {
+            Err(EngineError::InvalidOptionValue(name, _)) => assert_eq!(name, "Style"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match engine.set_option("Unknown", "1") {
+            Err(EngineError::UnknownOption(_)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(engine.set_spin_option("Skill Level", 21).is_err());
+
+        engine.limit_elo(1000).unwrap();
+        assert_eq!(engine.option_value("UCI_LimitStrength").as_deref(), Some("true"));
+        assert_eq!(engine.option_value("UCI_Elo").as_deref(), Some("1320"));
+    }
+
+    #[test]
+    fn test_byte_dump() {
+        assert!(!has_unusual_bytes(b"info string NNUE\tenabled\r\n"));
+        assert!(has_unusual_bytes(b"id name Fish\x00\n"));
+        assert!(has_unusual_bytes("id author José\n".as_bytes()));
+        assert_eq!(hex_dump(b"ok\x1b\n"), "6f 6b 1b 0a");
+    }
+
+    #[test]
+    fn test_movetime_overshoot() {
+        assert_eq!(requested_movetime("depth 10 movetime 250"), Some(Duration::from_millis(250)));
+        assert_eq!(requested_movetime("infinite"), None);
+
+        let engine = Engine::new("./stockfish").unwrap().enforce_movetime(true);
+        let events = engine.subscribe(events::ChannelPolicy::Unbounded);
+        let movetime = Duration::from_millis(100);
+        engine.check_overshoot(movetime, Duration::from_millis(120));
+        for _ in 0..OVERSHOOT_STREAK {
+            assert!(!engine.movetime_enforced.get());
+            engine.check_overshoot(movetime, Duration::from_secs(1));
+        }
+
+        assert!(engine.movetime_enforced.get());
+        assert_eq!(engine.stats().overshoots, u64::from(OVERSHOOT_STREAK));
+        match events.try_recv() {
+            Some(events::Event::MovetimeIgnored(..)) => {}
+            other => panic!("unexpected event: {:?}", other),
+        }
+        engine.bestmove().unwrap();
+    }
+
+    #[test]
+    fn test_limits_go_args() {
+        let depth = SearchLimits { depth: Some(30), ..SearchLimits::default() };
+        assert_eq!(depth.go_args(), "depth 30");
+        let all = SearchLimits { movetime: Some(500), depth: Some(10), nodes: Some(1000) };
+        assert_eq!(all.go_args(), "movetime 500 depth 10 nodes 1000");
+        assert_eq!(SearchLimits::default().go_args(), "movetime 100");
+    }
+
+    #[test]
+    fn test_best_of() {
+        assert!(best_of(&[]).is_err());
+        assert!(best_of(&["bestmove".into()]).is_err());
+        assert_eq!(best_of(&["bestmove (none)".into()]).unwrap(), "(none)");
+        assert_eq!(bestmove_of(&["info depth 1".into(), "bestmove e2e4 ponder e7e5".into()]).unwrap(),
+                   ("e2e4".to_string(), Some("e7e5".to_string())));
+    }
+
+    #[test]
+    fn test_session_stats() {
+        let mut stats = SessionStats::default();
+        stats.record(&["info depth 10 nodes 5000".to_string(), "bestmove e2e4".to_string()],
+                     Duration::from_millis(100));
+        stats.record(&["bestmove e2e4".to_string()], Duration::from_millis(50));
+        let before = stats;
+        stats.record(&["info depth 20 nodes 1000".to_string()], Duration::from_millis(10));
+
+        assert_eq!(stats.searches, 3);
+        assert_eq!(stats.nodes, 6000);
+        assert_eq!(stats.average_depth(), Some(15.0));
+        assert_eq!(stats.since(&before).think_time, Duration::from_millis(10));
+    }
+
     #[test]
     fn test_depth() {
         let engine = Engine::new("./stockfish").unwrap().movetime(50).depth(Some(1));
@@ -0,0 +1,85 @@
+use std::fmt;
+
+/// How much an engine logs, see [`EngineBuilder::log_verbosity`].
+///
+/// Applies on top of the level of the logger: a message is only written if
+/// both allow it.
+///
+/// [`EngineBuilder::log_verbosity`]: struct.EngineBuilder.html#method.log_verbosity
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Verbosity {
+    /// Nothing is logged.
+    Off,
+
+    /// Only failures the crate can't recover from.
+    Error,
+
+    /// Also what the crate worked around, like a search cut short.
+    Warn,
+
+    /// Also every command sent to the engine.
+    Info,
+
+    /// Also timings and what the engine printed on stderr. The default.
+    Debug,
+}
+
+/// Where and how much an engine logs.
+#[derive(Debug, Clone)]
+pub(crate) struct Logger {
+    target: String,
+    verbosity: Verbosity,
+}
+
+impl Default for Logger {
+    fn default() -> Logger {
+        Logger::new("uci", Verbosity::Debug)
+    }
+}
+
+impl Logger {
+    pub(crate) fn new(target: &str, verbosity: Verbosity) -> Logger {
+        Logger { target: target.to_string(), verbosity }
+    }
+
+    /// Writes a message, unless it's more verbose than allowed.
+    pub(crate) fn log(&self, level: Verbosity, args: fmt::Arguments) {
+        if level == Verbosity::Off || level > self.verbosity {
+            return;
+        }
+
+        #[cfg(feature = "log")]
+        {
+            let level = match level {
+                Verbosity::Error | Verbosity::Off => ::log::LogLevel::Error,
+                Verbosity::Warn => ::log::LogLevel::Warn,
+                Verbosity::Info => ::log::LogLevel::Info,
+                Verbosity::Debug => ::log::LogLevel::Debug,
+            };
+            log!(target: &self.target, level, "{}", args);
+        }
+        #[cfg(not(feature = "log"))]
+        let _ = (&self.target, args);
+    }
+
+    /// Whether messages of the given level are written at all, for skipping
+    /// work done only to log.
+    pub(crate) fn enabled(&self, level: Verbosity) -> bool {
+        level != Verbosity::Off && level <= self.verbosity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verbosity() {
+        let logger = Logger::new("sf16-main", Verbosity::Warn);
+        assert!(logger.enabled(Verbosity::Error));
+        assert!(logger.enabled(Verbosity::Warn));
+        assert!(!logger.enabled(Verbosity::Info));
+        assert!(!Logger::new("quiet", Verbosity::Off).enabled(Verbosity::Error));
+        assert_eq!(Logger::default().target, "uci");
+    }
+}
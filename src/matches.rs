@@ -0,0 +1,289 @@
+use std::ptr;
+
+use clock::{Clocks, GameClock, Side, TimeManager};
+use error::{EngineError, Result};
+use {bestmove_of, Engine};
+
+/// How a match ended, see [`Match::play`].
+///
+/// [`Match::play`]: struct.Match.html#method.play
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The player ran out of time.
+    Flag(Side),
+
+    /// The player's engine had no move to play, because it's checkmated or
+    /// stalemated. Telling which needs a move generator, which the crate
+    /// doesn't have.
+    NoMove(Side),
+
+    /// The game reached the ply limit set with [`Match::max_plies`].
+    ///
+    /// [`Match::max_plies`]: struct.Match.html#method.max_plies
+    PlyLimit,
+}
+
+/// A finished match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchResult {
+    /// The moves played, in Coordinate notation.
+    pub moves: Vec<String>,
+
+    pub outcome: Outcome,
+
+    /// The clock as the game ended.
+    pub clock: GameClock,
+
+    /// Moves the opponent played as the pondering engine expected, so it
+    /// went on with `ponderhit`.
+    pub ponder_hits: u32,
+
+    /// Moves the pondering engine didn't expect, so it was stopped and
+    /// searched the actual position.
+    pub ponder_misses: u32,
+}
+
+/// A timed game between two engines.
+///
+/// With [`ponder`] the engines think on the opponent's time: after each of
+/// its moves an engine is sent `go ponder` on the reply it expects. If the
+/// opponent plays that reply the engine gets `ponderhit` and goes on from
+/// where it got, otherwise it's stopped and searches the actual position.
+/// Either way, its clock only runs from the opponent's move; the time spent
+/// pondering is the opponent's.
+///
+/// An engine playing both sides never ponders.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use uci::clock::GameClock;
+/// use uci::matches::Match;
+///
+/// let white = uci::Engine::new("stockfish").unwrap();
+/// let black = uci::Engine::new("stockfish").unwrap();
+///
+/// let clock = GameClock::new(Duration::from_secs(10), Duration::from_millis(100));
+/// let result = Match::new(&white, &black, clock)
+///     .ponder(true)
+///     .max_plies(20)
+///     .play("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+///     .unwrap();
+/// println!("{:?} after {} plies, {} ponder hits", result.outcome, result.moves.len(),
+///          result.ponder_hits);
+/// ```
+///
+/// [`ponder`]: #method.ponder
+pub struct Match<'a> {
+    white: &'a Engine,
+    black: &'a Engine,
+    clock: GameClock,
+    manager: TimeManager,
+    ponder: bool,
+    max_plies: Option<usize>,
+}
+
+impl<'a> Match<'a> {
+    /// Creates a match played on `clock`, with time allocated by a default
+    /// [`TimeManager`].
+    ///
+    /// [`TimeManager`]: ../clock/struct.TimeManager.html
+    pub fn new(white: &'a Engine, black: &'a Engine, clock: GameClock) -> Match<'a> {
+        Match {
+            white,
+            black,
+            clock,
+            manager: TimeManager::new(),
+            ponder: false,
+            max_plies: None,
+        }
+    }
+
+    /// Sets how the engines' time is allocated.
+    pub fn manager(mut self, manager: TimeManager) -> Match<'a> {
+        self.manager = manager;
+        self
+    }
+
+    /// Lets the engines think on the opponent's time. Engines with a `Ponder`
+    /// option should have it enabled too, as the protocol expects.
+    pub fn ponder(mut self, ponder: bool) -> Match<'a> {
+        self.ponder = ponder;
+        self
+    }
+
+    /// Ends the game after `plies` moves of either side.
+    pub fn max_plies(mut self, plies: usize) -> Match<'a> {
+        self.max_plies = Some(plies);
+        self
+    }
+
+    /// Plays the game from `fen` until a flag falls, an engine has no move
+    /// or the ply limit is reached.
+    ///
+    /// Engines still pondering when the game ends are stopped.
+    pub fn play(self, fen: &str) -> Result<MatchResult> {
+        let mut game = Running {
+            fen,
+            moves: vec![],
+            clock: self.clock.clone(),
+            pondering: [None, None],
+            ponder_hits: 0,
+            ponder_misses: 0,
+        };
+        let res = self.run(&mut game);
+
+        for side in &[Side::White, Side::Black] {
+            if game.pondering[index(*side)].take().is_some() {
+                let _ = self.engine(*side).stop();
+            }
+        }
+
+        let outcome = res?;
+        Ok(MatchResult {
+            moves: game.moves,
+            outcome,
+            clock: game.clock,
+            ponder_hits: game.ponder_hits,
+            ponder_misses: game.ponder_misses,
+        })
+    }
+
+    fn run(&self, game: &mut Running) -> Result<Outcome> {
+        game.clock.start();
+        loop {
+            if self.max_plies.is_some_and(|max| game.moves.len() >= max) {
+                return Ok(Outcome::PlyLimit);
+            }
+
+            let side = game.clock.to_move();
+            let (mv, expected) = match self.next_move(game, side) {
+                Ok((ref mv, _)) if mv == "(none)" => return Ok(Outcome::NoMove(side)),
+                Err(ref e) if matches!(e.unlabelled(), EngineError::NotFound) => {
+                    return Ok(Outcome::NoMove(side));
+                }
+                res => res?,
+            };
+            if !game.clock.press() {
+                return Ok(Outcome::Flag(side));
+            }
+            game.moves.push(mv);
+
+            if let Some(expected) = expected.filter(|_| self.ponders()) {
+                let engine = self.engine(side);
+                let mut moves = game.moves.clone();
+                moves.push(expected.clone());
+                engine.make_moves_from_position(game.fen, &moves)?;
+                let args = self.go_args(engine, &ponder_clocks(&game.clock, side));
+                engine.start_open_search(&format!("ponder {}", args))?;
+                game.pondering[index(side)] = Some(expected);
+            }
+        }
+    }
+
+    /// Gets the move of the side to move and the reply it expects, going on
+    /// from its ponder search if there's one.
+    fn next_move(&self, game: &mut Running, side: Side) -> Result<(String, Option<String>)> {
+        let engine = self.engine(side);
+        if let Some(expected) = game.pondering[index(side)].take() {
+            if game.moves.last() == Some(&expected) {
+                game.ponder_hits += 1;
+                return engine.ponderhit_with_ponder();
+            }
+            game.ponder_misses += 1;
+            engine.stop()?;
+        }
+
+        engine.make_moves_from_position(game.fen, &game.moves)?;
+        let lines = engine.search(&self.go_args(engine, &game.clock.clocks()))?;
+        bestmove_of(&lines)
+    }
+
+    fn go_args(&self, engine: &Engine, clocks: &Clocks) -> String {
+        self.manager.compensate(engine.latency().unwrap_or_default()).go_args(clocks)
+    }
+
+    fn engine(&self, side: Side) -> &'a Engine {
+        match side {
+            Side::White => self.white,
+            Side::Black => self.black,
+        }
+    }
+
+    fn ponders(&self) -> bool {
+        self.ponder && !ptr::eq(self.white, self.black)
+    }
+}
+
+/// The state of a game in progress.
+struct Running<'f> {
+    fen: &'f str,
+    moves: Vec<String>,
+    clock: GameClock,
+
+    /// The reply each side's engine is pondering on, if it is.
+    pondering: [Option<String>; 2],
+    ponder_hits: u32,
+    ponder_misses: u32,
+}
+
+fn index(side: Side) -> usize {
+    match side {
+        Side::White => 0,
+        Side::Black => 1,
+    }
+}
+
+/// The clocks as they will be on `side`'s next move, which it ponders on
+/// while the opponent's clock runs.
+fn ponder_clocks(clock: &GameClock, side: Side) -> Clocks {
+    let mut clocks = clock.clocks();
+    clocks.white_to_move = side == Side::White;
+    if side == Side::White {
+        clocks.move_number += 1;
+    }
+    clocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_ponder_clocks() {
+        let mut clock = GameClock::new(Duration::from_secs(60), Duration::from_secs(1));
+        clock.record(Duration::from_secs(5));
+
+        let clocks = ponder_clocks(&clock, Side::White);
+        assert!(clocks.white_to_move);
+        assert_eq!(clocks.move_number, 2);
+        assert_eq!(clocks.white.remaining, Duration::from_secs(56));
+
+        clock.record(Duration::from_secs(10));
+        let clocks = ponder_clocks(&clock, Side::Black);
+        assert!(!clocks.white_to_move);
+        assert_eq!(clocks.move_number, 2);
+        assert_eq!(clocks.black.remaining, Duration::from_secs(51));
+    }
+
+    #[test]
+    fn test_pondering_match() {
+        let white = Engine::new("./stockfish").unwrap();
+        let black = Engine::new("./stockfish").unwrap();
+
+        let clock = GameClock::new(Duration::from_secs(60), Duration::from_secs(1));
+        let result = Match::new(&white, &black, clock)
+            .ponder(true)
+            .max_plies(4)
+            .play("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+            .unwrap();
+        assert_eq!(result.outcome, Outcome::PlyLimit);
+        assert_eq!(result.moves.len(), 4);
+        // Both engines always expect e7e5 and play e2e4.
+        assert_eq!((result.ponder_hits, result.ponder_misses), (0, 2));
+        assert!(!white.searching.get() && !black.searching.get());
+        assert_eq!(result.clock.move_number(), 3);
+    }
+}
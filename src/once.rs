@@ -0,0 +1,59 @@
+use std::time::Duration;
+
+use batch::PositionResult;
+use builder::EngineBuilder;
+use error::Result;
+use SearchLimits;
+
+/// Time a search gets past its `movetime` before it's stopped.
+const GRACE: Duration = Duration::from_secs(5);
+
+/// Analyses a single position with a fresh engine, for scripts that don't
+/// want to keep an [`Engine`] around.
+///
+/// The engine is started in analysis mode, searches `fen` within `limits`,
+/// and is shut down before returning. A search outliving its `movetime` by
+/// a few seconds is stopped, so a hung engine can't block the script.
+///
+/// # Arguments
+///
+/// * `path`   - The path to the engine executable.
+/// * `fen`    - The position to analyse.
+/// * `limits` - When the search stops.
+///
+/// # Examples
+///
+/// ```
+/// use uci::SearchLimits;
+///
+/// let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+/// let limits = SearchLimits { movetime: Some(200), ..SearchLimits::default() };
+/// let result = uci::analyze_once("stockfish", fen, &limits).unwrap();
+/// println!("{} {:?}", result.bestmove, result.score);
+/// ```
+///
+/// [`Engine`]: struct.Engine.html
+pub fn analyze_once(path: &str, fen: &str, limits: &SearchLimits) -> Result<PositionResult> {
+    let engine = EngineBuilder::new(path)
+        .startup_timeout(Some(Duration::from_secs(10)))
+        .analyse_mode(true)
+        .build()?
+        .hard_limit(limits.movetime.map(|ms| Duration::from_millis(u64::from(ms)) + GRACE));
+    engine.new_game()?;
+    engine.set_position(fen)?;
+    let lines = engine.search(&limits.go_args())?;
+    PositionResult::from_search(fen, &lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze_once() {
+        let fen = "8/8/8/8/8/8/4k3/4K2R w K - 0 1";
+        let result = analyze_once("./stockfish", fen, &SearchLimits::default()).unwrap();
+        assert_eq!(result.fen, fen);
+        assert!(!result.bestmove.is_empty());
+    }
+}
@@ -0,0 +1,298 @@
+use std::collections::BTreeMap;
+
+use Engine;
+
+/// Words separating the fields of an `option` line.
+const KEYWORDS: [&str; 6] = ["name", "type", "default", "min", "max", "var"];
+
+/// An option as declared by an `option` line of the `uci` handshake.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Declared {
+    pub(crate) name: String,
+    pub(crate) kind: String,
+    pub(crate) default: Option<String>,
+    pub(crate) min: Option<String>,
+    pub(crate) max: Option<String>,
+    pub(crate) vars: Vec<String>,
+}
+
+impl Declared {
+    /// Parses `option name <id> type <t> [default <x>] [min <x>] [max <x>] [var <x>]*`.
+    ///
+    /// Names and values may contain spaces, so each field runs until the
+    /// next keyword.
+    pub(crate) fn parse(line: &str) -> Option<Declared> {
+        let mut words = line.split_whitespace();
+        if words.next() != Some("option") {
+            return None;
+        }
+
+        let mut fields: Vec<(&str, Vec<&str>)> = vec![];
+        for word in words {
+            // A string default is free text and may contain keywords.
+            let in_string_default = fields.last().is_some_and(|&(key, ref value)| {
+                key == "default" && !value.is_empty()
+            }) && fields.iter().any(|&(key, ref value)| key == "type" && value[..] == ["string"]);
+
+            if KEYWORDS.contains(&word) && !in_string_default {
+                fields.push((word, vec![]));
+            } else if let Some(&mut (_, ref mut value)) = fields.last_mut() {
+                value.push(word);
+            } else {
+                return None;
+            }
+        }
+
+        let mut declared = Declared {
+            name: String::new(),
+            kind: String::new(),
+            default: None,
+            min: None,
+            max: None,
+            vars: vec![],
+        };
+        for (key, value) in fields {
+            let value = value.join(" ");
+            match key {
+                "name" => declared.name = value,
+                "type" => declared.kind = value,
+                "default" => declared.default = Some(value),
+                "min" => declared.min = Some(value),
+                "max" => declared.max = Some(value),
+                _ => declared.vars.push(value),
+            }
+        }
+
+        if declared.name.is_empty() {
+            None
+        } else {
+            Some(declared)
+        }
+    }
+
+    /// The option with its values parsed for its type. `None` for types the
+    /// protocol doesn't define.
+    pub(crate) fn typed(&self) -> Option<EngineOption> {
+        let name = self.name.clone();
+        let number = |value: &Option<String>| value.as_ref().and_then(|v| v.parse().ok());
+        Some(match self.kind.as_str() {
+            "check" => EngineOption::Check {
+                name,
+                default: self.default.as_ref().and_then(|v| v.parse().ok()),
+            },
+            "spin" => EngineOption::Spin {
+                name,
+                default: number(&self.default),
+                min: number(&self.min),
+                max: number(&self.max),
+            },
+            "combo" => EngineOption::Combo { name, default: self.default.clone(), vars: self.vars.clone() },
+            "button" => EngineOption::Button { name },
+            "string" => EngineOption::String { name, default: self.default.clone() },
+            _ => return None,
+        })
+    }
+}
+
+/// An option the engine declares, see [`Engine::options`].
+///
+/// Values the engine declares but that don't parse for the type are `None`.
+///
+/// [`Engine::options`]: ../struct.Engine.html#method.options
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EngineOption {
+    /// A boolean, set with `true` or `false`.
+    Check { name: String, default: Option<bool> },
+
+    /// An integer within a range.
+    Spin { name: String, default: Option<i64>, min: Option<i64>, max: Option<i64> },
+
+    /// One of a list of values.
+    Combo { name: String, default: Option<String>, vars: Vec<String> },
+
+    /// An action, triggered by setting the option without a value.
+    Button { name: String },
+
+    /// Free text.
+    String { name: String, default: Option<String> },
+}
+
+impl EngineOption {
+    pub fn name(&self) -> &str {
+        match *self {
+            EngineOption::Check { ref name, .. } => name,
+            EngineOption::Spin { ref name, .. } => name,
+            EngineOption::Combo { ref name, .. } => name,
+            EngineOption::Button { ref name } => name,
+            EngineOption::String { ref name, .. } => name,
+        }
+    }
+}
+
+/// Restores an option to its previous value when dropped, see
+/// [`Engine::with_option`].
+///
+/// [`Engine::with_option`]: ../struct.Engine.html#method.with_option
+pub struct OptionGuard<'a> {
+    engine: &'a Engine,
+    name: String,
+    previous: Option<String>,
+}
+
+impl<'a> OptionGuard<'a> {
+    pub(crate) fn new(engine: &'a Engine, name: &str, previous: Option<String>) -> OptionGuard<'a> {
+        OptionGuard { engine, name: name.to_string(), previous }
+    }
+}
+
+impl<'a> Drop for OptionGuard<'a> {
+    fn drop(&mut self) {
+        if let Some(ref previous) = self.previous {
+            if let Err(e) = self.engine.set_option(&self.name, previous) {
+                engine_log!(self.engine.logger, Warn, "Option '{}' could not be restored: {}", self.name, e);
+            }
+        }
+    }
+}
+
+/// An option whose default or value differs between two engines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptionChange {
+    pub name: String,
+
+    /// Default or value in the first engine.
+    pub a: Option<String>,
+
+    /// Default or value in the second engine.
+    pub b: Option<String>,
+}
+
+/// Differences between the options of two engines, see [`diff_options`].
+///
+/// [`diff_options`]: fn.diff_options.html
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OptionDiff {
+    /// Options only the second engine declares.
+    pub added: Vec<String>,
+
+    /// Options only the first engine declares.
+    pub removed: Vec<String>,
+
+    /// Options declared with different defaults.
+    pub defaults: Vec<OptionChange>,
+
+    /// Options whose current values differ. The current value is the last
+    /// one set through [`Engine::set_option`], or the default.
+    ///
+    /// [`Engine::set_option`]: ../struct.Engine.html#method.set_option
+    pub values: Vec<OptionChange>,
+}
+
+impl OptionDiff {
+    /// Whether both engines have the same options and values.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+            && self.defaults.is_empty() && self.values.is_empty()
+    }
+}
+
+/// Compares the options two engines declare and their current values.
+///
+/// Options are matched by name, ignoring case as the protocol specifies.
+///
+/// # Examples
+///
+/// ```
+/// use uci::options::diff_options;
+///
+/// let a = uci::Engine::new("stockfish").unwrap();
+/// let b = uci::Engine::new("stockfish").unwrap();
+/// b.set_option("Skill Level", "5").unwrap();
+///
+/// let diff = diff_options(&a, &b);
+/// assert!(diff.added.is_empty());
+/// assert_eq!(diff.values[0].b, Some("5".to_string()));
+/// ```
+pub fn diff_options(a: &Engine, b: &Engine) -> OptionDiff {
+    let index = |engine: &Engine| -> BTreeMap<String, (Declared, Option<String>)> {
+        engine.declared_options().into_iter()
+            .map(|declared| {
+                let value = engine.option_value(&declared.name);
+                (declared.name.to_lowercase(), (declared, value))
+            })
+            .collect()
+    };
+    let (a, b) = (index(a), index(b));
+
+    let mut diff = OptionDiff::default();
+    for (key, (declared, value)) in &a {
+        let other = match b.get(key) {
+            Some(other) => other,
+            None => {
+                diff.removed.push(declared.name.clone());
+                continue;
+            }
+        };
+        if declared.default != other.0.default {
+            diff.defaults.push(OptionChange {
+                name: declared.name.clone(),
+                a: declared.default.clone(),
+                b: other.0.default.clone(),
+            });
+        }
+        if *value != other.1 {
+            diff.values.push(OptionChange {
+                name: declared.name.clone(),
+                a: value.clone(),
+                b: other.1.clone(),
+            });
+        }
+    }
+    diff.added = b.iter()
+        .filter(|&(key, _)| !a.contains_key(key))
+        .map(|(_, (declared, _))| declared.name.clone())
+        .collect();
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_declared() {
+        let spin = Declared::parse("option name Skill Level type spin default 20 min 0 max 20").unwrap();
+        assert_eq!(spin.name, "Skill Level");
+        assert_eq!(spin.kind, "spin");
+        assert_eq!(spin.default.as_deref(), Some("20"));
+        assert_eq!(spin.max.as_deref(), Some("20"));
+
+        let combo = Declared::parse("option name Style type combo default Normal var Solid var Normal").unwrap();
+        assert_eq!(combo.vars, vec!["Solid", "Normal"]);
+
+        let string = Declared::parse("option name Book type string default my max book.bin").unwrap();
+        assert_eq!(string.default.as_deref(), Some("my max book.bin"));
+
+        let button = Declared::parse("option name Clear Hash type button").unwrap();
+        assert_eq!(button.default, None);
+        assert!(Declared::parse("info depth 1").is_none());
+    }
+
+    #[test]
+    fn test_typed() {
+        let typed = |line| Declared::parse(line).unwrap().typed();
+
+        assert_eq!(typed("option name Hash type spin default 16 min 1 max 33554432"),
+                   Some(EngineOption::Spin {
+                       name: "Hash".to_string(),
+                       default: Some(16),
+                       min: Some(1),
+                       max: Some(33554432),
+                   }));
+        assert_eq!(typed("option name Ponder type check default false"),
+                   Some(EngineOption::Check { name: "Ponder".to_string(), default: Some(false) }));
+        assert_eq!(typed("option name Clear Hash type button").unwrap().name(), "Clear Hash");
+        assert_eq!(typed("option name Odd type color default red"), None);
+    }
+}
@@ -0,0 +1,115 @@
+use std::fmt;
+
+use info::InfoRef;
+
+/// What kind of line an engine sent, see [`CommandOutput`].
+///
+/// [`CommandOutput`]: struct.CommandOutput.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    /// `id name ...` or `id author ...`.
+    Id,
+
+    /// An option declaration.
+    Option,
+
+    /// Search information.
+    Info,
+
+    /// The engine's move at the end of a search.
+    BestMove,
+
+    /// Anything the protocol doesn't define, like the output of `d`.
+    Unknown,
+}
+
+impl LineKind {
+    fn of(line: &str) -> LineKind {
+        match line.split_whitespace().next() {
+            Some("id") => LineKind::Id,
+            Some("option") => LineKind::Option,
+            Some("info") => LineKind::Info,
+            Some("bestmove") => LineKind::BestMove,
+            _ => LineKind::Unknown,
+        }
+    }
+}
+
+/// A line of engine output, with its kind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputLine {
+    pub kind: LineKind,
+
+    /// The line as the engine sent it, without the line terminator.
+    pub raw: String,
+}
+
+impl OutputLine {
+    /// Parses the line if it's an `info` line.
+    pub fn info(&self) -> Option<InfoRef<'_>> {
+        InfoRef::parse(&self.raw)
+    }
+}
+
+/// The classified output of a command, see [`Engine::command_output`].
+///
+/// Formatting it gives back the joined lines, as returned by
+/// [`Engine::command`].
+///
+/// [`Engine::command_output`]: struct.Engine.html#method.command_output
+/// [`Engine::command`]: struct.Engine.html#method.command
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommandOutput {
+    pub lines: Vec<OutputLine>,
+}
+
+impl CommandOutput {
+    pub(crate) fn new<I: IntoIterator<Item = String>>(lines: I) -> CommandOutput {
+        CommandOutput {
+            lines: lines.into_iter()
+                .map(|raw| OutputLine { kind: LineKind::of(&raw), raw })
+                .collect(),
+        }
+    }
+
+    /// The lines of the given kind, in the order they were received.
+    pub fn of_kind(&self, kind: LineKind) -> impl Iterator<Item = &OutputLine> {
+        self.lines.iter().filter(move |line| line.kind == kind)
+    }
+
+    /// The `bestmove` line, if the command was a search.
+    pub fn bestmove(&self) -> Option<&str> {
+        self.of_kind(LineKind::BestMove).last().map(|line| line.raw.as_str())
+    }
+}
+
+impl fmt::Display for CommandOutput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, line) in self.lines.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", line.raw)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let output = CommandOutput::new(vec![
+            "Stockfish 16".to_string(),
+            "info depth 1 score cp 20 pv e2e4".to_string(),
+            "bestmove e2e4 ponder e7e5".to_string(),
+        ]);
+        let kinds: Vec<LineKind> = output.lines.iter().map(|line| line.kind).collect();
+        assert_eq!(kinds, vec![LineKind::Unknown, LineKind::Info, LineKind::BestMove]);
+        assert_eq!(output.of_kind(LineKind::Info).next().unwrap().info().unwrap().depth, Some(1));
+        assert_eq!(output.bestmove(), Some("bestmove e2e4 ponder e7e5"));
+        assert_eq!(output.to_string(), "Stockfish 16\ninfo depth 1 score cp 20 pv e2e4\nbestmove e2e4 ponder e7e5");
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+
+use info::InfoRef;
+#[cfg(feature = "pgn")]
+use review::pgn_eval;
+use score::Score;
+
+/// Winning chances (in percentage points) behind the best move at which a
+/// candidate's strength drops to zero.
+const STRENGTH_WINDOW: f64 = 20.0;
+
+/// A candidate move to draw on the board.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate {
+    /// The candidate move. Uses Coordinate notation.
+    pub mv: String,
+
+    /// Rank of the move, as reported by `multipv`. 1 is the best move.
+    pub rank: u32,
+
+    pub score: Score,
+
+    /// The engine's line, starting with the move.
+    pub pv: Vec<String>,
+
+    /// Depth the line was searched to.
+    pub depth: Option<u32>,
+
+    /// How close the move is to the best one, from 0.0 to 1.0 (the best
+    /// move). Meant to be used directly as arrow width or heat intensity.
+    pub strength: f64,
+}
+
+/// Candidate moves of a search, reduced to what a GUI needs to draw arrows or
+/// a heatmap.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Overlay {
+    /// Candidates ordered by rank.
+    pub candidates: Vec<Candidate>,
+
+    /// The root move the engine was searching last, if it reported one.
+    pub currmove: Option<String>,
+
+    /// Deepest depth the engine reported.
+    pub depth: Option<u32>,
+}
+
+impl Overlay {
+    /// Builds the overlay out of the engine's `info` output.
+    ///
+    /// For every `multipv` index only the most recent line carrying a score
+    /// and a pv is kept. Non-`info` lines are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use uci::overlay::Overlay;
+    ///
+    /// let overlay = Overlay::from_info_lines(vec![
+    ///     "info depth 20 multipv 1 score cp 35 pv e2e4 e7e5",
+    ///     "info depth 20 multipv 2 score cp 10 pv d2d4 d7d5",
+    ///     "info depth 21 currmove g1f3 currmovenumber 3",
+    /// ]);
+    /// assert_eq!(overlay.candidates[0].mv, "e2e4");
+    /// assert_eq!(overlay.candidates[0].strength, 1.0);
+    /// assert_eq!(overlay.currmove, Some("g1f3".to_string()));
+    /// ```
+    pub fn from_info_lines<'a, I>(lines: I) -> Overlay
+        where I: IntoIterator<Item = &'a str>
+    {
+        let mut lines_by_rank: BTreeMap<u32, (Vec<String>, Score, Option<u32>)> = BTreeMap::new();
+        let mut currmove = None;
+        let mut depth = None;
+
+        for info in lines.into_iter().filter_map(InfoRef::parse) {
+            depth = depth.max(info.depth);
+            if let Some(mv) = info.currmove {
+                currmove = Some(mv.to_string());
+            }
+
+            let pv: Vec<String> = info.pv_moves().map(str::to_string).collect();
+            if let (Some(score), false) = (info.score, pv.is_empty()) {
+                lines_by_rank.insert(info.multipv.unwrap_or(1), (pv, score, info.depth));
+            }
+        }
+
+        let best = lines_by_rank.values().next()
+            .map(|&(_, score, _)| score.win_probability())
+            .unwrap_or(0.0);
+        let candidates = lines_by_rank.into_iter()
+            .map(|(rank, (pv, score, depth))| {
+                let behind = best - score.win_probability();
+                Candidate {
+                    mv: pv[0].clone(),
+                    rank,
+                    score,
+                    pv,
+                    depth,
+                    strength: (1.0 - behind / STRENGTH_WINDOW).clamp(0.0, 1.0),
+                }
+            })
+            .collect();
+
+        Overlay { candidates, currmove, depth }
+    }
+
+    /// Formats the candidates as a PGN comment, one line after the other
+    /// with its score and depth, each cut to `plies` moves.
+    ///
+    /// Scores are from the point of view of the side to move, and moves are
+    /// written as the engine sent them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use uci::overlay::Overlay;
+    ///
+    /// let overlay = Overlay::from_info_lines(vec![
+    ///     "info depth 20 multipv 1 score cp 35 pv e2e4 e7e5 g1f3",
+    ///     "info depth 19 multipv 2 score mate 4 pv d2d4 d7d5",
+    /// ]);
+    /// assert_eq!(overlay.pgn_comment(2), "{ 1) 0.35/20 e2e4 e7e5; 2) #4/19 d2d4 d7d5 }");
+    /// ```
+    #[cfg(feature = "pgn")]
+    pub fn pgn_comment(&self, plies: usize) -> String {
+        let lines: Vec<String> = self.candidates.iter()
+            .map(|candidate| {
+                let mut line = format!("{}) {}", candidate.rank, pgn_eval(candidate.score));
+                if let Some(depth) = candidate.depth {
+                    line.push_str(&format!("/{}", depth));
+                }
+                for mv in candidate.pv.iter().take(plies) {
+                    line.push(' ');
+                    line.push_str(mv);
+                }
+                line
+            })
+            .collect();
+        format!("{{ {} }}", lines.join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overlay_keeps_latest_line() {
+        let overlay = Overlay::from_info_lines(vec![
+            "info depth 10 multipv 2 score cp -500 pv a2a3",
+            "info depth 10 multipv 1 score cp 20 pv e2e4",
+            "info depth 11 multipv 2 score cp 15 pv d2d4",
+            "bestmove e2e4",
+        ]);
+
+        assert_eq!(overlay.candidates.len(), 2);
+        assert_eq!(overlay.candidates[1].mv, "d2d4");
+        assert!(overlay.candidates[1].strength > 0.9);
+        assert_eq!(overlay.currmove, None);
+        assert_eq!(overlay.depth, Some(11));
+        assert_eq!(overlay.candidates[0].pv, vec!["e2e4"]);
+        assert_eq!(overlay.candidates[0].depth, Some(10));
+    }
+}
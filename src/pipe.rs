@@ -0,0 +1,124 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::raw::c_void;
+use std::os::windows::fs::OpenOptionsExt;
+use std::os::windows::io::{AsRawHandle, FromRawHandle, OwnedHandle, RawHandle};
+use std::path::Path;
+use std::ptr;
+use std::sync::Arc;
+
+const FILE_FLAG_OVERLAPPED: u32 = 0x4000_0000;
+const ERROR_BROKEN_PIPE: i32 = 109;
+const ERROR_MORE_DATA: i32 = 234;
+const ERROR_IO_PENDING: i32 = 997;
+
+#[repr(C)]
+struct Overlapped {
+    internal: usize,
+    internal_high: usize,
+    offset: u32,
+    offset_high: u32,
+    event: RawHandle,
+}
+
+#[link(name = "kernel32")]
+extern "system" {
+    fn ReadFile(file: RawHandle, buf: *mut u8, len: u32, read: *mut u32, overlapped: *mut Overlapped) -> i32;
+    fn WriteFile(file: RawHandle, buf: *const u8, len: u32, written: *mut u32,
+                 overlapped: *mut Overlapped) -> i32;
+    fn GetOverlappedResult(file: RawHandle, overlapped: *mut Overlapped, transferred: *mut u32,
+                           wait: i32) -> i32;
+    fn CreateEventW(attributes: *mut c_void, manual_reset: i32, initial_state: i32,
+                    name: *const u16) -> RawHandle;
+}
+
+/// One direction of a connection to a named pipe.
+///
+/// The pipe is opened for overlapped I/O, so that a read blocked waiting for
+/// the engine doesn't hold up a `stop` written meanwhile, as it would on a
+/// synchronous handle. Each half waits for its own operations on its own
+/// event.
+pub(crate) struct PipeHalf {
+    pipe: Arc<File>,
+    event: OwnedHandle,
+}
+
+/// Opens the named pipe at `path`, returning the half commands are written
+/// to and the half the engine's output is read from.
+pub(crate) fn open(path: &Path) -> io::Result<(PipeHalf, PipeHalf)> {
+    let pipe = Arc::new(OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(FILE_FLAG_OVERLAPPED)
+        .open(path)?);
+    Ok((PipeHalf::new(pipe.clone())?, PipeHalf::new(pipe)?))
+}
+
+impl PipeHalf {
+    fn new(pipe: Arc<File>) -> io::Result<PipeHalf> {
+        let event = unsafe { CreateEventW(ptr::null_mut(), 1, 0, ptr::null()) };
+        if event.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(PipeHalf { pipe, event: unsafe { OwnedHandle::from_raw_handle(event) } })
+    }
+
+    /// Starts an operation with `start` and waits for it to complete,
+    /// returning the number of bytes transferred.
+    fn complete<F>(&self, start: F) -> io::Result<usize>
+        where F: FnOnce(RawHandle, *mut Overlapped) -> i32
+    {
+        let file = self.pipe.as_raw_handle();
+        let mut overlapped = Overlapped {
+            internal: 0,
+            internal_high: 0,
+            offset: 0,
+            offset_high: 0,
+            event: self.event.as_raw_handle(),
+        };
+        if start(file, &mut overlapped) == 0 {
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() != Some(ERROR_IO_PENDING) {
+                return Err(err);
+            }
+        }
+        // Waits even if the operation failed to start as pending, so the
+        // operation never outlives `overlapped`.
+        let mut transferred = 0;
+        if unsafe { GetOverlappedResult(file, &mut overlapped, &mut transferred, 1) } == 0 {
+            let err = io::Error::last_os_error();
+            // A message longer than the buffer; the rest comes with the next read.
+            if err.raw_os_error() != Some(ERROR_MORE_DATA) {
+                return Err(err);
+            }
+        }
+        Ok(transferred as usize)
+    }
+}
+
+impl Read for PipeHalf {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(u32::MAX as usize) as u32;
+        let res = self.complete(|file, overlapped| unsafe {
+            ReadFile(file, buf.as_mut_ptr(), len, ptr::null_mut(), overlapped)
+        });
+        match res {
+            // The engine closed its end.
+            Err(ref e) if e.raw_os_error() == Some(ERROR_BROKEN_PIPE) => Ok(0),
+            res => res,
+        }
+    }
+}
+
+impl Write for PipeHalf {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(u32::MAX as usize) as u32;
+        self.complete(|file, overlapped| unsafe {
+            WriteFile(file, buf.as_ptr(), len, ptr::null_mut(), overlapped)
+        })
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
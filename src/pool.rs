@@ -0,0 +1,971 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::future::Future;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "rayon")]
+use rayon::iter::ParallelIterator;
+
+use builder::EngineBuilder;
+use error::{EngineError, Result};
+use events::{ChannelPolicy, Event, Queue, Subscription};
+use info::InfoRef;
+use {Engine, Interrupter, SessionStats};
+
+/// Runs a queued job, or fails it with the error if there's no engine left
+/// to run it.
+type Job = Box<dyn FnOnce(Result<&Engine>) + Send>;
+
+struct Queued {
+    id: u64,
+    job: Job,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Scaling {
+    min: usize,
+    max: usize,
+    idle_timeout: Duration,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Health {
+    interval: Duration,
+    timeout: Duration,
+}
+
+struct State {
+    jobs: VecDeque<Queued>,
+    next_id: u64,
+    next_worker: usize,
+    shutdown: bool,
+
+    /// Engines running or starting up.
+    workers: usize,
+
+    /// Engines waiting for a job.
+    idle: usize,
+    scaling: Option<Scaling>,
+    health: Option<Health>,
+    min_nps_ratio: Option<f64>,
+    telemetry: BTreeMap<usize, Telemetry>,
+
+    /// Searches of all the engines, including replaced ones.
+    totals: SessionStats,
+
+    /// Why the last engine the pool tried to start didn't.
+    start_failure: Option<String>,
+}
+
+/// Running totals behind a [`WorkerStats`].
+///
+/// [`WorkerStats`]: struct.WorkerStats.html
+struct Telemetry {
+    jobs: u64,
+    depth_total: u64,
+    depth_samples: u64,
+    nps_total: u64,
+    nps_samples: u64,
+    restarts: u32,
+    started: Instant,
+}
+
+impl Telemetry {
+    fn new() -> Telemetry {
+        Telemetry {
+            jobs: 0,
+            depth_total: 0,
+            depth_samples: 0,
+            nps_total: 0,
+            nps_samples: 0,
+            restarts: 0,
+            started: Instant::now(),
+        }
+    }
+
+    fn record(&mut self, sample: &Sample) {
+        self.jobs += 1;
+        if let Some(depth) = sample.depth {
+            self.depth_total += u64::from(depth);
+            self.depth_samples += 1;
+        }
+        if let Some(nps) = sample.nps {
+            self.nps_total += nps;
+            self.nps_samples += 1;
+        }
+    }
+
+    fn stats(&self, id: usize, label: Option<&str>) -> WorkerStats {
+        let average = |total, samples| if samples > 0 {
+            Some(total as f64 / samples as f64)
+        } else {
+            None
+        };
+        WorkerStats {
+            id,
+            label: label.map(str::to_string),
+            jobs: self.jobs,
+            average_depth: average(self.depth_total, self.depth_samples),
+            average_nps: average(self.nps_total, self.nps_samples),
+            restarts: self.restarts,
+            uptime: self.started.elapsed(),
+        }
+    }
+}
+
+/// Statistics of one engine of an [`EnginePool`], see
+/// [`EnginePool::worker_stats`].
+///
+/// [`EnginePool`]: struct.EnginePool.html
+/// [`EnginePool::worker_stats`]: struct.EnginePool.html#method.worker_stats
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkerStats {
+    /// Number of the worker, unique within the pool.
+    pub id: usize,
+
+    /// The [`label`] of the pool's engines, if they were given one.
+    ///
+    /// [`label`]: ../struct.EngineBuilder.html#method.label
+    pub label: Option<String>,
+
+    /// Jobs the worker has run, including failed ones.
+    pub jobs: u64,
+
+    /// Average of the last depth the engine reported in each job that
+    /// searched.
+    pub average_depth: Option<f64>,
+
+    /// Average of the last nodes per second the engine reported in each job
+    /// that searched.
+    pub average_nps: Option<f64>,
+
+    /// Times the engine was replaced by the pool's health checks.
+    pub restarts: u32,
+
+    /// Time since the worker's current engine was started.
+    pub uptime: Duration,
+}
+
+struct Shared {
+    builder: EngineBuilder,
+    state: Mutex<State>,
+    available: Condvar,
+    threads: Mutex<Vec<JoinHandle<()>>>,
+    subscribers: Mutex<Vec<Arc<Queue>>>,
+}
+
+impl Shared {
+    fn publish(&self, event: Event) {
+        self.subscribers.lock().unwrap().retain(|queue| queue.push(event.clone()));
+    }
+}
+
+/// A fixed set of engine processes working through a shared job queue.
+///
+/// Every engine runs on its own thread. Jobs are closures that get exclusive
+/// use of one engine and are started in submission order.
+///
+/// # Examples
+///
+/// ```
+/// let pool = uci::pool::EnginePool::new(uci::EngineBuilder::new("stockfish"), 2).unwrap();
+///
+/// let handles: Vec<_> = vec!["e2e4", "d2d4", "c2c4"].into_iter().map(|mv| {
+///     pool.submit(move |engine| {
+///         engine.make_moves(&[mv.to_string()])?;
+///         engine.bestmove()
+///     })
+/// }).collect();
+///
+/// for handle in handles {
+///     println!("{}", handle.wait().unwrap());
+/// }
+/// ```
+pub struct EnginePool {
+    shared: Arc<Shared>,
+}
+
+impl EnginePool {
+    /// Starts `size` engines configured by `builder`.
+    ///
+    /// Fails if an engine couldn't be started, see [`EngineBuilder::build`].
+    ///
+    /// [`EngineBuilder::build`]: ../struct.EngineBuilder.html#method.build
+    pub fn new(builder: EngineBuilder, size: usize) -> Result<EnginePool> {
+        let mut engines = vec![];
+        for _ in 0..size {
+            engines.push(builder.clone().build()?);
+        }
+
+        let shared = Arc::new(Shared {
+            builder,
+            state: Mutex::new(State {
+                jobs: VecDeque::new(),
+                next_id: 0,
+                next_worker: size,
+                shutdown: false,
+                workers: size,
+                idle: 0,
+                scaling: None,
+                health: None,
+                min_nps_ratio: None,
+                telemetry: BTreeMap::new(),
+                totals: SessionStats::default(),
+                start_failure: None,
+            }),
+            available: Condvar::new(),
+            threads: Mutex::new(vec![]),
+            subscribers: Mutex::new(vec![]),
+        });
+        for (id, engine) in engines.into_iter().enumerate() {
+            spawn_worker(&shared, id, Some(engine));
+        }
+
+        Ok(EnginePool { shared })
+    }
+
+    /// Lets the pool start more engines while jobs are waiting for one, and
+    /// stop engines that have been idle for a while.
+    ///
+    /// The pool never shrinks below the size it was created with.
+    ///
+    /// # Arguments
+    ///
+    /// * `max`          - Maximum number of engines. Defaults to the number of CPU cores.
+    /// * `idle_timeout` - How long an extra engine may wait for a job before it's stopped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use uci::pool::EnginePool;
+    ///
+    /// let pool = EnginePool::new(uci::EngineBuilder::new("stockfish"), 1).unwrap()
+    ///     .autoscale(Some(4), Duration::from_secs(30));
+    /// ```
+    pub fn autoscale(self, max: Option<usize>, idle_timeout: Duration) -> EnginePool {
+        let max = max.unwrap_or_else(|| thread::available_parallelism().map(|n| n.get()).unwrap_or(1));
+        {
+            let mut state = self.shared.state.lock().unwrap();
+            state.scaling = Some(Scaling {
+                min: state.workers,
+                max,
+                idle_timeout,
+            });
+        }
+        self.shared.available.notify_all();
+        self
+    }
+
+    /// Makes idle engines answer `isready` every `interval`. Engines that
+    /// take longer than `timeout` are killed and replaced by a new one.
+    ///
+    /// Replacements are logged and sent to [`subscribe`]rs as
+    /// [`Event::Replaced`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use uci::pool::EnginePool;
+    ///
+    /// let pool = EnginePool::new(uci::EngineBuilder::new("stockfish"), 2).unwrap()
+    ///     .health_checks(Duration::from_secs(10), Duration::from_secs(1))
+    ///     .replace_below_nps(0.3);
+    /// ```
+    ///
+    /// [`subscribe`]: #method.subscribe
+    /// [`Event::Replaced`]: ../events/enum.Event.html#variant.Replaced
+    pub fn health_checks(self, interval: Duration, timeout: Duration) -> EnginePool {
+        self.shared.state.lock().unwrap().health = Some(Health { interval, timeout });
+        self.shared.available.notify_all();
+        self
+    }
+
+    /// Replaces engines whose speed drops below `ratio` times the best speed
+    /// they reached before, as measured by the nodes per second of jobs that
+    /// searched for at least 100 ms.
+    pub fn replace_below_nps(self, ratio: f64) -> EnginePool {
+        self.shared.state.lock().unwrap().min_nps_ratio = Some(ratio);
+        self
+    }
+
+    /// Returns a [`Subscription`] receiving the pool's [`Event::Replaced`]
+    /// events.
+    ///
+    /// [`Subscription`]: ../events/struct.Subscription.html
+    /// [`Event::Replaced`]: ../events/enum.Event.html#variant.Replaced
+    pub fn subscribe(&self, policy: ChannelPolicy) -> Subscription {
+        let (subscription, queue) = Subscription::new(policy);
+        self.shared.subscribers.lock().unwrap().push(queue);
+        subscription.labelled(self.shared.builder.label_ref())
+    }
+
+    /// Returns statistics for every engine that is currently running, ordered
+    /// by worker id.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let pool = uci::pool::EnginePool::new(uci::EngineBuilder::new("stockfish"), 2).unwrap();
+    /// pool.submit(|engine| engine.bestmove()).wait().unwrap();
+    ///
+    /// for stats in pool.worker_stats() {
+    ///     println!("engine {}: {} jobs, {:?} nps", stats.id, stats.jobs, stats.average_nps);
+    /// }
+    /// ```
+    pub fn worker_stats(&self) -> Vec<WorkerStats> {
+        self.shared.state.lock().unwrap().telemetry.iter()
+            .map(|(&id, telemetry)| telemetry.stats(id, self.shared.builder.label_ref()))
+            .collect()
+    }
+
+    /// Returns the totals of the searches of all the pool's engines,
+    /// including the ones that were replaced or retired.
+    pub fn stats(&self) -> SessionStats {
+        self.shared.state.lock().unwrap().totals
+    }
+
+    /// Number of engines currently running or starting up.
+    pub fn size(&self) -> usize {
+        self.shared.state.lock().unwrap().workers
+    }
+
+    /// Queues a job and returns a handle to its result.
+    ///
+    /// The handle can be blocked on with [`JobHandle::wait`] or awaited, as it
+    /// implements `Future`.
+    ///
+    /// Once the pool has no engines left, because none could be started to
+    /// replace or add one, queued and new jobs fail with an I/O error giving
+    /// the reason.
+    ///
+    /// [`JobHandle::wait`]: struct.JobHandle.html#method.wait
+    pub fn submit<F, T>(&self, f: F) -> JobHandle<T>
+        where F: FnOnce(&Engine) -> Result<T> + Send + 'static,
+              T: Send + 'static
+    {
+        let job = Arc::new(JobState {
+            status: Mutex::new(Status::Queued),
+            done: Condvar::new(),
+            waker: Mutex::new(None),
+            interrupter: Mutex::new(None),
+        });
+        let completion = Completion { job: job.clone() };
+
+        let mut state = self.shared.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+        state.jobs.push_back(Queued {
+            id,
+            job: Box::new(move |engine: Result<&Engine>| {
+                let engine = match engine {
+                    Ok(engine) => engine,
+                    Err(e) => return completion.finish(Err(e)),
+                };
+                completion.job.set(Status::Running);
+                *completion.job.interrupter.lock().unwrap() = Some(engine.interrupter());
+                let result = f(engine).map_err(|e| e.labelled(engine.label()));
+                *completion.job.interrupter.lock().unwrap() = None;
+                completion.finish(result);
+            }),
+        });
+        self.shared.available.notify_one();
+
+        if let Some(scaling) = state.scaling {
+            if state.jobs.len() > state.idle && state.workers < scaling.max {
+                state.workers += 1;
+                state.next_worker += 1;
+                debug!("Growing engine pool to {} engines", state.workers);
+                spawn_worker(&self.shared, state.next_worker - 1, None);
+            }
+        }
+
+        // Every engine failed to start, so none would ever take the job.
+        if state.workers == 0 {
+            let queued = state.jobs.pop_back().unwrap();
+            let error = no_engine(&self.shared, state.start_failure.as_deref());
+            drop(state);
+            (queued.job)(Err(error));
+        }
+
+        JobHandle {
+            id,
+            job,
+            pool: self.shared.clone(),
+        }
+    }
+
+    /// Runs `f` over every item in parallel on the pool's engines and returns
+    /// the results in the order of the items.
+    ///
+    /// To analyse the items of a rayon pipeline, see [`ParAnalyze`] with the
+    /// `rayon` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let pool = uci::pool::EnginePool::new(uci::EngineBuilder::new("stockfish"), 4).unwrap();
+    /// let fens = vec![
+    ///     "6b1/8/1k5P/8/1P3B2/5pp1/8/4K3 b - - 0 1".to_string(),
+    ///     "2k4R/8/3K4/8/8/8/8/8 b - - 0 1".to_string(),
+    /// ];
+    ///
+    /// let moves = pool.par_analyze(fens, |engine, fen| {
+    ///     engine.set_position(&fen)?;
+    ///     engine.bestmove()
+    /// });
+    /// assert_eq!(moves.len(), 2);
+    /// ```
+    ///
+    /// [`ParAnalyze`]: trait.ParAnalyze.html
+    pub fn par_analyze<I, F, T>(&self, items: I, f: F) -> Vec<Result<T>>
+        where I: IntoIterator,
+              I::Item: Send + 'static,
+              F: Fn(&Engine, I::Item) -> Result<T> + Send + Sync + 'static,
+              T: Send + 'static
+    {
+        let f = Arc::new(f);
+        let handles: Vec<JobHandle<T>> = items.into_iter()
+            .map(|item| {
+                let f = f.clone();
+                self.submit(move |engine| f(engine, item))
+            })
+            .collect();
+
+        handles.into_iter().map(JobHandle::wait).collect()
+    }
+
+    /// Number of jobs waiting for an engine.
+    pub fn queued(&self) -> usize {
+        self.shared.state.lock().unwrap().jobs.len()
+    }
+}
+
+/// Analysis of the items of a rayon parallel iterator on an engine pool.
+///
+/// Each item is handed to the next free engine of the pool, while rayon's
+/// work stealing spreads the items over its threads, so slow positions don't
+/// hold up the rest. A rayon thread blocks while its item is analysed, so at
+/// most as many items are analysed at once as the smaller of the pool's size
+/// and rayon's number of threads.
+///
+/// # Examples
+///
+/// ```
+/// extern crate rayon;
+/// # extern crate uci;
+/// use rayon::prelude::*;
+/// use uci::pool::{EnginePool, ParAnalyze};
+///
+/// # fn main() {
+/// let pool = EnginePool::new(uci::EngineBuilder::new("stockfish"), 4).unwrap();
+/// let fens = vec![
+///     "6b1/8/1k5P/8/1P3B2/5pp1/8/4K3 b - - 0 1".to_string(),
+///     "2k4R/8/3K4/8/8/8/8/8 b - - 0 1".to_string(),
+/// ];
+///
+/// let moves: Vec<_> = fens.into_par_iter()
+///     .par_analyze(&pool, |engine, fen| {
+///         engine.set_position(&fen)?;
+///         engine.bestmove()
+///     })
+///     .collect();
+/// assert_eq!(moves.len(), 2);
+/// # }
+/// ```
+#[cfg(feature = "rayon")]
+pub trait ParAnalyze: ParallelIterator {
+    /// Maps every item to the result of `f` on one of `pool`'s engines.
+    fn par_analyze<'a, F, T>(self, pool: &'a EnginePool, f: F) -> impl ParallelIterator<Item = Result<T>> + 'a
+        where Self: 'a,
+              Self::Item: 'static,
+              F: Fn(&Engine, Self::Item) -> Result<T> + Send + Sync + 'static,
+              T: Send + 'static;
+}
+
+#[cfg(feature = "rayon")]
+impl<I: ParallelIterator> ParAnalyze for I {
+    fn par_analyze<'a, F, T>(self, pool: &'a EnginePool, f: F) -> impl ParallelIterator<Item = Result<T>> + 'a
+        where Self: 'a,
+              Self::Item: 'static,
+              F: Fn(&Engine, Self::Item) -> Result<T> + Send + Sync + 'static,
+              T: Send + 'static
+    {
+        let f = Arc::new(f);
+        self.map(move |item| {
+            let f = f.clone();
+            pool.submit(move |engine| f(engine, item)).wait()
+        })
+    }
+}
+
+impl Drop for EnginePool {
+    /// Cancels the queued jobs and waits for the running ones to finish.
+    fn drop(&mut self) {
+        {
+            let mut state = self.shared.state.lock().unwrap();
+            state.shutdown = true;
+            state.jobs.clear();
+        }
+        self.shared.available.notify_all();
+        let threads: Vec<JoinHandle<()>> = self.shared.threads.lock().unwrap().drain(..).collect();
+        for thread in threads {
+            let _ = thread.join();
+        }
+        for queue in self.shared.subscribers.lock().unwrap().iter() {
+            queue.close();
+        }
+    }
+}
+
+/// Starts a worker thread, spawning its engine first if none is given.
+fn spawn_worker(shared: &Arc<Shared>, id: usize, engine: Option<Engine>) {
+    let worker = shared.clone();
+    let thread = thread::spawn(move || {
+        let engine = match engine {
+            Some(engine) => engine,
+            None => match start_engine(&worker) {
+                Some(engine) => engine,
+                None => return,
+            },
+        };
+        work(&worker, id, engine);
+    });
+    let mut threads = shared.threads.lock().unwrap();
+    // Drop the handles of retired workers, or they'd pile up for as long as
+    // the pool lives.
+    threads.retain(|thread| !thread.is_finished());
+    threads.push(thread);
+}
+
+/// Spawns a new engine for a worker. On failure the worker is given up, and
+/// if it was the last one, the queued jobs fail as there's no engine left to
+/// run them.
+fn start_engine(shared: &Shared) -> Option<Engine> {
+    let builder = shared.builder.clone();
+    let reason = match panic::catch_unwind(AssertUnwindSafe(|| builder.build())) {
+        Ok(Ok(engine)) => return Some(engine),
+        Ok(Err(e)) => e.to_string(),
+        Err(_) => "starting it panicked".to_string(),
+    };
+    warn!("Engine pool couldn't start an engine: {}", reason);
+
+    let mut state = shared.state.lock().unwrap();
+    state.workers -= 1;
+    state.start_failure = Some(reason.clone());
+    if state.workers == 0 {
+        let jobs: Vec<Queued> = state.jobs.drain(..).collect();
+        drop(state);
+        for queued in jobs {
+            (queued.job)(Err(no_engine(shared, Some(&reason))));
+        }
+    }
+    None
+}
+
+/// The error of a job left without an engine to run it.
+fn no_engine(shared: &Shared, start_failure: Option<&str>) -> EngineError {
+    let message = match start_failure {
+        Some(reason) => format!("no engine could be started: {}", reason),
+        None => "the pool has no engines".to_string(),
+    };
+    EngineError::Io(io::Error::other(message)).labelled(shared.builder.label_ref())
+}
+
+enum Task {
+    Run(Queued),
+    Check(Duration),
+    Exit,
+}
+
+fn work(shared: &Shared, id: usize, engine: Engine) {
+    shared.state.lock().unwrap().telemetry.insert(id, Telemetry::new());
+    serve(shared, id, engine);
+    shared.state.lock().unwrap().telemetry.remove(&id);
+}
+
+fn serve(shared: &Shared, id: usize, mut engine: Engine) {
+    let mut monitor = SearchMonitor::new(&engine);
+    let mut idle_since = Instant::now();
+    let mut last_check = Instant::now();
+
+    loop {
+        let problem = match next_task(shared, idle_since, last_check) {
+            Task::Exit => return,
+            Task::Check(timeout) => {
+                last_check = Instant::now();
+                engine.ping(timeout).err().map(|e| format!("failed health check: {}", e))
+            }
+            Task::Run(queued) => {
+                let job = queued.job;
+                let before = engine.stats();
+                if panic::catch_unwind(AssertUnwindSafe(|| job(Ok(&engine)))).is_err() {
+                    warn!("Pool job {} panicked", queued.id);
+                }
+                idle_since = Instant::now();
+
+                let sample = monitor.sample();
+                let mut state = shared.state.lock().unwrap();
+                if let Some(telemetry) = state.telemetry.get_mut(&id) {
+                    telemetry.record(&sample);
+                }
+                state.totals.merge(&engine.stats().since(&before));
+                state.min_nps_ratio.and_then(|ratio| monitor.degraded(&sample, ratio))
+            }
+        };
+
+        if let Some(reason) = problem {
+            let name = match engine.label() {
+                Some(label) => format!("engine {} ({})", id, label),
+                None => format!("engine {}", id),
+            };
+            warn!("Replacing pool {}: {}", name, reason);
+            engine.kill();
+            shared.publish(Event::Replaced(format!("{}: {}", name, reason)));
+            engine = match start_engine(shared) {
+                Some(engine) => engine,
+                None => return,
+            };
+            if let Some(telemetry) = shared.state.lock().unwrap().telemetry.get_mut(&id) {
+                telemetry.restarts += 1;
+                telemetry.started = Instant::now();
+            }
+            monitor = SearchMonitor::new(&engine);
+            last_check = Instant::now();
+        }
+    }
+}
+
+/// Waits until there's something for an idle worker to do.
+fn next_task(shared: &Shared, idle_since: Instant, last_check: Instant) -> Task {
+    let mut state = shared.state.lock().unwrap();
+    state.idle += 1;
+    let task = loop {
+        if state.shutdown {
+            break Task::Exit;
+        }
+        if let Some(queued) = state.jobs.pop_front() {
+            break Task::Run(queued);
+        }
+
+        let retire_in = match state.scaling {
+            Some(scaling) if state.workers > scaling.min => {
+                let left = scaling.idle_timeout.checked_sub(idle_since.elapsed());
+                if left.is_none() {
+                    state.workers -= 1;
+                    debug!("Shrinking engine pool to {} engines", state.workers);
+                    break Task::Exit;
+                }
+                left
+            }
+            _ => None,
+        };
+        let check_in = match state.health {
+            Some(health) => match health.interval.checked_sub(last_check.elapsed()) {
+                Some(left) => Some(left),
+                None => break Task::Check(health.timeout),
+            },
+            None => None,
+        };
+
+        state = match retire_in.into_iter().chain(check_in).min() {
+            Some(timeout) => shared.available.wait_timeout(state, timeout).unwrap().0,
+            None => shared.available.wait(state).unwrap(),
+        };
+    };
+    state.idle -= 1;
+    task
+}
+
+/// What the engine last reported during a job.
+#[derive(Default)]
+struct Sample {
+    depth: Option<u32>,
+    nps: Option<u64>,
+    time: Option<u64>,
+}
+
+/// Keeps track of what an engine reports in its searches.
+struct SearchMonitor {
+    events: Subscription,
+    best_nps: u64,
+}
+
+impl SearchMonitor {
+    fn new(engine: &Engine) -> SearchMonitor {
+        SearchMonitor {
+            events: engine.subscribe(ChannelPolicy::DropOldest(16)),
+            best_nps: 0,
+        }
+    }
+
+    /// Collects the output of the job that just ran.
+    fn sample(&mut self) -> Sample {
+        let mut sample = Sample::default();
+        while let Some(event) = self.events.try_recv() {
+            if let Event::Info(ref line) = event {
+                if let Some(info) = InfoRef::parse(line) {
+                    sample.depth = info.depth.or(sample.depth);
+                    if info.nps.is_some() {
+                        sample.nps = info.nps;
+                        sample.time = info.time;
+                    }
+                }
+            }
+        }
+        sample
+    }
+
+    /// Checks the speed of a job that searched for at least 100 ms against
+    /// the best speed seen so far.
+    fn degraded(&mut self, sample: &Sample, ratio: f64) -> Option<String> {
+        let nps = match (sample.nps, sample.time) {
+            (Some(nps), Some(time)) if time >= 100 => nps,
+            _ => return None,
+        };
+        self.best_nps = self.best_nps.max(nps);
+        if (nps as f64) < self.best_nps as f64 * ratio {
+            Some(format!("speed dropped to {} nps from {} nps", nps, self.best_nps))
+        } else {
+            None
+        }
+    }
+}
+
+enum Status<T> {
+    Queued,
+    Running,
+    Done(Result<T>),
+    Taken,
+}
+
+struct JobState<T> {
+    status: Mutex<Status<T>>,
+    done: Condvar,
+    waker: Mutex<Option<Waker>>,
+
+    /// Stops the searches of the engine running the job.
+    interrupter: Mutex<Option<Interrupter>>,
+}
+
+impl<T> JobState<T> {
+    fn set(&self, status: Status<T>) {
+        *self.status.lock().unwrap() = status;
+    }
+
+    fn take_result(status: &mut Status<T>) -> Option<Result<T>> {
+        if let Status::Done(..) = *status {
+            match ::std::mem::replace(status, Status::Taken) {
+                Status::Done(result) => return Some(result),
+                _ => unreachable!(),
+            }
+        }
+        None
+    }
+}
+
+/// Delivers the result of a job, or [`EngineError::Cancelled`] if the job is
+/// dropped without running.
+struct Completion<T> {
+    job: Arc<JobState<T>>,
+}
+
+impl<T> Completion<T> {
+    fn finish(&self, result: Result<T>) {
+        self.job.set(Status::Done(result));
+        self.job.done.notify_all();
+        if let Some(waker) = self.job.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Drop for Completion<T> {
+    fn drop(&mut self) {
+        let finished = matches!(*self.job.status.lock().unwrap(), Status::Done(..) | Status::Taken);
+        if !finished {
+            self.finish(Err(EngineError::Cancelled));
+        }
+    }
+}
+
+/// Handle to a job submitted to an [`EnginePool`].
+///
+/// [`EnginePool`]: struct.EnginePool.html
+pub struct JobHandle<T> {
+    id: u64,
+    job: Arc<JobState<T>>,
+    pool: Arc<Shared>,
+}
+
+impl<T> JobHandle<T> {
+    /// Blocks until the job is done and returns its result.
+    ///
+    /// Returns [`EngineError::Cancelled`] if the job was cancelled or the
+    /// pool was dropped before it ran.
+    ///
+    /// [`EngineError::Cancelled`]: ../enum.EngineError.html#variant.Cancelled
+    pub fn wait(self) -> Result<T> {
+        let mut status = self.job.status.lock().unwrap();
+        loop {
+            if let Some(result) = JobState::take_result(&mut status) {
+                return result;
+            }
+            status = self.job.done.wait(status).unwrap();
+        }
+    }
+
+    /// Removes the job from the queue if it hasn't started yet.
+    ///
+    /// Returns whether the job was cancelled. Running jobs can't be
+    /// cancelled, but can be [`interrupt`]ed.
+    ///
+    /// [`interrupt`]: #method.interrupt
+    pub fn cancel(&self) -> bool {
+        let mut state = self.pool.state.lock().unwrap();
+        match state.jobs.iter().position(|q| q.id == self.id) {
+            Some(i) => {
+                // Dropping the job resolves the handle as cancelled.
+                state.jobs.remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stops the searches of a running job, which then finishes with what
+    /// the engine found so far, see [`Interrupter::interrupt`].
+    ///
+    /// Returns whether the job was running.
+    ///
+    /// [`Interrupter::interrupt`]: ../struct.Interrupter.html#method.interrupt
+    pub fn interrupt(&self) -> bool {
+        match *self.job.interrupter.lock().unwrap() {
+            Some(ref interrupter) => {
+                if let Err(e) = interrupter.interrupt() {
+                    warn!("Job {} could not be interrupted: {}", self.id, e);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Number of jobs ahead of this one in the queue, or `None` once the job
+    /// has been picked up by an engine.
+    pub fn queue_position(&self) -> Option<usize> {
+        self.pool.state.lock().unwrap().jobs.iter().position(|q| q.id == self.id)
+    }
+
+    /// Whether the job has finished, successfully or not.
+    pub fn is_done(&self) -> bool {
+        matches!(*self.job.status.lock().unwrap(), Status::Done(..) | Status::Taken)
+    }
+}
+
+impl<T> Future for JobHandle<T> {
+    type Output = Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<T>> {
+        let mut status = self.job.status.lock().unwrap();
+        match JobState::take_result(&mut status) {
+            Some(result) => Poll::Ready(result),
+            None => {
+                *self.job.waker.lock().unwrap() = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_jobs() {
+        let pool = EnginePool::new(EngineBuilder::new("./stockfish"), 1).unwrap();
+        let first = pool.submit(|engine| engine.bestmove());
+        let second = pool.submit(|engine| engine.bestmove());
+        let third = pool.submit(|engine| engine.bestmove());
+
+        assert!(third.cancel());
+        assert!(!third.cancel());
+        assert!(first.wait().is_ok());
+        assert!(second.wait().is_ok());
+        match third.wait() {
+            Err(EngineError::Cancelled) => {}
+            _ => panic!("expected the job to be cancelled"),
+        }
+    }
+
+    #[test]
+    fn test_no_engine_left() {
+        let builder = EngineBuilder::new("./no-such-engine");
+        let pool = EnginePool::new(builder.clone(), 0).unwrap();
+        assert!(pool.submit(|engine| engine.bestmove()).wait().is_err());
+
+        let pool = EnginePool::new(builder, 0).unwrap()
+            .autoscale(Some(2), Duration::from_millis(50));
+        let handles: Vec<_> = (0..3).map(|_| pool.submit(|engine| engine.bestmove())).collect();
+        for handle in handles {
+            let err = handle.wait().unwrap_err();
+            assert!(err.to_string().contains("no engine"), "{}", err);
+        }
+        assert_eq!(pool.size(), 0);
+    }
+
+    #[test]
+    fn test_autoscale() {
+        let pool = EnginePool::new(EngineBuilder::new("./stockfish"), 1).unwrap()
+            .autoscale(Some(3), Duration::from_millis(50));
+        let handles: Vec<_> = (0..6).map(|_| pool.submit(|engine| engine.bestmove())).collect();
+        assert!(pool.size() > 1 && pool.size() <= 3);
+
+        for handle in handles {
+            handle.wait().unwrap();
+        }
+        thread::sleep(Duration::from_millis(500));
+        assert_eq!(pool.size(), 1);
+
+        let handles: Vec<_> = (0..6).map(|_| pool.submit(|engine| engine.bestmove())).collect();
+        for handle in handles {
+            handle.wait().unwrap();
+        }
+        assert!(pool.shared.threads.lock().unwrap().len() <= 3);
+    }
+
+    #[test]
+    fn test_health_checks_keep_healthy_engines() {
+        let pool = EnginePool::new(EngineBuilder::new("./stockfish"), 1).unwrap()
+            .health_checks(Duration::from_millis(10), Duration::from_secs(1));
+        let events = pool.subscribe(ChannelPolicy::Unbounded);
+        thread::sleep(Duration::from_millis(100));
+
+        assert!(pool.submit(|engine| engine.bestmove()).wait().is_ok());
+        assert_eq!(events.try_recv(), None);
+        assert_eq!(pool.size(), 1);
+    }
+
+    #[test]
+    fn test_telemetry_averages() {
+        let mut telemetry = Telemetry::new();
+        telemetry.record(&Sample { depth: Some(10), nps: Some(1000), time: None });
+        telemetry.record(&Sample { depth: Some(20), nps: None, time: None });
+        telemetry.record(&Sample::default());
+
+        let stats = telemetry.stats(3, Some("sf16-main"));
+        assert_eq!(stats.id, 3);
+        assert_eq!(stats.label.as_deref(), Some("sf16-main"));
+        assert_eq!(stats.jobs, 3);
+        assert_eq!(stats.average_depth, Some(15.0));
+        assert_eq!(stats.average_nps, Some(1000.0));
+        assert_eq!(stats.restarts, 0);
+    }
+}
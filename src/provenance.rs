@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+use std::time::SystemTime;
+
+use error::Result;
+use {Engine, SearchLimits};
+
+/// How an analysis result was produced, for telling apart stored results
+/// that can't be compared, see [`Engine::provenance`].
+///
+/// [`Engine::provenance`]: struct.Engine.html#method.provenance
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Provenance {
+    /// The engine's `id name`.
+    pub engine: Option<String>,
+
+    /// The engine's [`label`], if it was given one.
+    ///
+    /// [`label`]: struct.EngineBuilder.html#method.label
+    pub label: Option<String>,
+
+    /// Hash of the engine's options and their current values.
+    pub options_hash: u64,
+
+    /// The engine's [`config_fingerprint`].
+    ///
+    /// [`config_fingerprint`]: struct.Engine.html#method.config_fingerprint
+    pub fingerprint: u64,
+
+    /// The engine's search limits.
+    pub limits: SearchLimits,
+
+    /// Version of this crate.
+    pub crate_version: &'static str,
+
+    /// When the result was produced.
+    pub timestamp: SystemTime,
+}
+
+impl Provenance {
+    pub(crate) fn new(engine: &Engine) -> Provenance {
+        Provenance {
+            engine: engine.name().map(str::to_string),
+            label: engine.label().map(str::to_string),
+            options_hash: fnv1a(options_bytes(engine)),
+            fingerprint: engine.config_fingerprint(),
+            limits: engine.limits(),
+            crate_version: env!("CARGO_PKG_VERSION"),
+            timestamp: SystemTime::now(),
+        }
+    }
+}
+
+/// An analysis result along with how it was produced.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Traced<T> {
+    pub result: T,
+    pub provenance: Provenance,
+}
+
+impl<T> Traced<T> {
+    /// Runs an analysis and records the engine's configuration as it started.
+    pub(crate) fn run<F>(engine: &Engine, analysis: F) -> Result<Traced<T>>
+        where F: FnOnce(&Engine) -> Result<T>
+    {
+        let provenance = Provenance::new(engine);
+        Ok(Traced { result: analysis(engine)?, provenance })
+    }
+}
+
+/// The engine's options and their current values, by lowercase name, as
+/// hashed: every name and value followed by a zero byte.
+pub(crate) fn options_bytes(engine: &Engine) -> Vec<u8> {
+    let mut options: BTreeMap<String, String> = BTreeMap::new();
+    for declared in engine.declared_options() {
+        if let Some(default) = declared.default {
+            options.insert(declared.name.to_lowercase(), default);
+        }
+    }
+    options.extend(engine.option_values.borrow().iter().map(|(name, value)| (name.clone(), value.clone())));
+
+    let mut bytes = vec![];
+    for (name, value) in options {
+        bytes.extend(name.bytes().chain(Some(0)).chain(value.bytes()).chain(Some(0)));
+    }
+    bytes
+}
+
+/// The 64 bit FNV-1a hash, which unlike the standard library's hasher is
+/// stable across versions and platforms, as stored hashes need to be.
+pub(crate) fn fnv1a<I: IntoIterator<Item = u8>>(bytes: I) -> u64 {
+    bytes.into_iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv1a() {
+        assert_eq!(fnv1a(vec![]), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a("a".bytes()), 0xaf63_dc4c_8601_ec8c);
+        assert_ne!(fnv1a("ab".bytes()), fnv1a("ba".bytes()));
+    }
+}
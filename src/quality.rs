@@ -0,0 +1,117 @@
+use std::time::Duration;
+
+use error::Result;
+use Engine;
+
+/// Duration of the calibration search of [`Presets::calibrate`].
+///
+/// [`Presets::calibrate`]: struct.Presets.html#method.calibrate
+const CALIBRATION_TIME: Duration = Duration::from_millis(500);
+
+/// How thorough an analysis should be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quality {
+    /// Enough for hints while the user is moving pieces.
+    Quick,
+
+    /// A few candidate moves at a depth good for reviewing games.
+    Standard,
+
+    /// Several candidates, searched long enough to trust small differences.
+    Deep,
+}
+
+impl Quality {
+    /// Nodes to search, number of candidate moves and the range of time the
+    /// search may take in milliseconds.
+    fn budget(self) -> (u64, u32, u32, u32) {
+        match self {
+            Quality::Quick => (2_000_000, 1, 100, 1_000),
+            Quality::Standard => (20_000_000, 3, 500, 5_000),
+            Quality::Deep => (200_000_000, 5, 2_000, 30_000),
+        }
+    }
+}
+
+/// What a search of a given [`Quality`] is limited to.
+///
+/// [`Quality`]: enum.Quality.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnalysisLimits {
+    /// Search time in milliseconds.
+    pub movetime: u32,
+
+    /// Number of candidate moves, sent as the `MultiPV` option.
+    pub multipv: u32,
+}
+
+/// Maps [`Quality`] levels to limits for an engine of a given speed.
+///
+/// Every level stands for a node budget, so a deep analysis means the same
+/// amount of work on fast and slow hardware, within sensible time bounds.
+///
+/// # Examples
+///
+/// ```
+/// use uci::quality::{Presets, Quality};
+///
+/// let engine = uci::Engine::new("stockfish").unwrap();
+/// let presets = Presets::calibrate(&engine).unwrap();
+///
+/// engine.set_position("6b1/8/1k5P/8/1P3B2/5pp1/8/4K3 b - - 0 1").unwrap();
+/// let overlay = engine.analyze(&presets.limits(Quality::Quick)).unwrap();
+/// ```
+///
+/// [`Quality`]: enum.Quality.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Presets {
+    nps: u64,
+}
+
+impl Presets {
+    /// Creates presets for an engine searching `nps` nodes per second.
+    pub fn for_speed(nps: u64) -> Presets {
+        Presets { nps }
+    }
+
+    /// Measures the engine's speed with a short search of the starting
+    /// position, see [`Engine::warmup`]. The engine's current position is
+    /// replaced.
+    ///
+    /// [`Engine::warmup`]: ../struct.Engine.html#method.warmup
+    pub fn calibrate(engine: &Engine) -> Result<Presets> {
+        let report = engine.warmup(&[], CALIBRATION_TIME)?;
+        Ok(Presets::for_speed(report.nps))
+    }
+
+    /// Speed the presets were made for, in nodes per second.
+    pub fn nps(&self) -> u64 {
+        self.nps
+    }
+
+    /// Returns the limits of a quality level.
+    pub fn limits(&self, quality: Quality) -> AnalysisLimits {
+        let (nodes, multipv, min, max) = quality.budget();
+        let movetime = match (nodes * 1000).checked_div(self.nps) {
+            Some(ms) => ms.clamp(u64::from(min), u64::from(max)) as u32,
+            None => max,
+        };
+        AnalysisLimits { movetime, multipv }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits_scale_with_speed() {
+        let slow = Presets::for_speed(1_000_000);
+        let fast = Presets::for_speed(10_000_000);
+
+        assert_eq!(slow.limits(Quality::Quick), AnalysisLimits { movetime: 1_000, multipv: 1 });
+        assert_eq!(fast.limits(Quality::Quick).movetime, 200);
+        assert_eq!(fast.limits(Quality::Standard), AnalysisLimits { movetime: 2_000, multipv: 3 });
+        assert_eq!(Presets::for_speed(0).limits(Quality::Deep).movetime, 30_000);
+    }
+}
@@ -0,0 +1,104 @@
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+/// How many times reads and writes to the engine are attempted when they
+/// fail with a transient error, like an interrupted system call or a pipe
+/// that's momentarily unavailable.
+///
+/// This only covers hiccups of the pipes: an engine that crashed or stopped
+/// answering is left to the restart policy of the [`pool`].
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use uci::RetryPolicy;
+///
+/// let engine = uci::Engine::new("stockfish").unwrap()
+///     .retry_policy(RetryPolicy::new(5, Duration::from_millis(10)));
+/// ```
+///
+/// [`pool`]: pool/index.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    attempts: u32,
+    backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// Creates a policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `attempts` - Total number of attempts, including the first one.
+    /// * `backoff`  - Wait before the first retry, doubled for every next one.
+    pub fn new(attempts: u32, backoff: Duration) -> RetryPolicy {
+        RetryPolicy { attempts: attempts.max(1), backoff }
+    }
+
+    /// A policy that never retries. This is the default.
+    pub fn none() -> RetryPolicy {
+        RetryPolicy::new(1, Duration::from_millis(0))
+    }
+
+    /// Runs `f` until it succeeds, fails with an error that isn't transient,
+    /// or runs out of attempts.
+    pub(crate) fn run<T, F>(&self, mut f: F) -> io::Result<T>
+        where F: FnMut() -> io::Result<T>
+    {
+        let mut backoff = self.backoff;
+        let mut attempt = 1;
+        loop {
+            match f() {
+                Err(ref e) if attempt < self.attempts && is_transient(e) => {
+                    warn!("Transient I/O error, retrying in {:?}: {}", backoff, e);
+                    thread::sleep(backoff);
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy::none()
+    }
+}
+
+fn is_transient(err: &io::Error) -> bool {
+    matches!(err.kind(),
+             io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_transient_errors() {
+        let policy = RetryPolicy::new(3, Duration::from_millis(1));
+
+        let mut calls = 0;
+        let res = policy.run(|| {
+            calls += 1;
+            if calls < 3 {
+                Err(io::Error::new(io::ErrorKind::Interrupted, "interrupted"))
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(res.unwrap(), 3);
+
+        calls = 0;
+        let res: io::Result<()> = policy.run(|| {
+            calls += 1;
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "broken pipe"))
+        });
+        assert!(res.is_err());
+        assert_eq!(calls, 1);
+    }
+}
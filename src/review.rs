@@ -0,0 +1,598 @@
+use score::Score;
+
+/// The engine's verdict on a single move of a game.
+///
+/// Both scores are from the point of view of the side that played the move,
+/// so an engine evaluation of the position *after* the move has to be negated
+/// before it is stored in `played_score`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveEval {
+    /// Ply of the move, starting at 1 for white's first move.
+    pub ply: u32,
+
+    /// FEN of the position the move was played in.
+    pub fen: String,
+
+    /// The move that was played. Uses Coordinate notation.
+    pub played: String,
+
+    /// Evaluation of the position with best play.
+    pub best_score: Score,
+
+    /// Evaluation of the position after the played move.
+    pub played_score: Score,
+
+    /// The line the engine preferred, starting with its best move.
+    pub best_line: Vec<String>,
+}
+
+impl MoveEval {
+    /// Winning chances of the mover before the move, in percent.
+    pub fn win_before(&self) -> f64 {
+        self.best_score.win_probability()
+    }
+
+    /// Winning chances of the mover after the move, in percent.
+    pub fn win_after(&self) -> f64 {
+        self.played_score.win_probability()
+    }
+
+    /// How many percentage points of winning chances the move threw away.
+    pub fn win_loss(&self) -> f64 {
+        (self.win_before() - self.win_after()).max(0.0)
+    }
+
+    /// Centipawns lost by the move compared to the engine's choice.
+    pub fn cp_loss(&self) -> i32 {
+        (self.best_score.centipawns() - self.played_score.centipawns()).max(0)
+    }
+
+    /// Accuracy of the move in percent, following the lichess formula.
+    pub fn accuracy(&self) -> f64 {
+        (103.1668 * (-0.04354 * self.win_loss()).exp() - 3.1669).clamp(0.0, 100.0)
+    }
+
+    /// Grades the move by how much winning chances it lost.
+    pub fn class(&self) -> MoveClass {
+        let loss = self.win_loss();
+        if loss >= 15.0 {
+            MoveClass::Blunder
+        } else if loss >= 10.0 {
+            MoveClass::Mistake
+        } else if loss >= 5.0 {
+            MoveClass::Inaccuracy
+        } else if self.best_line.first() == Some(&self.played) {
+            MoveClass::Best
+        } else {
+            MoveClass::Good
+        }
+    }
+
+    /// Whether the move was played by white, according to the FEN side to
+    /// move or, failing that, the ply number.
+    pub fn is_white(&self) -> bool {
+        match self.fen.split(' ').nth(1) {
+            Some("w") => true,
+            Some("b") => false,
+            _ => self.ply % 2 == 1,
+        }
+    }
+
+    /// The phase of the game the move was played in.
+    pub fn phase(&self) -> Phase {
+        Phase::of(&self.fen, self.ply)
+    }
+}
+
+/// How good a move was compared to the engine's choice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveClass {
+    /// The engine's own choice.
+    Best,
+
+    /// Not the engine's choice, but costs (almost) nothing.
+    Good,
+
+    /// Loses 5 or more percentage points of winning chances.
+    Inaccuracy,
+
+    /// Loses 10 or more percentage points of winning chances.
+    Mistake,
+
+    /// Loses 15 or more percentage points of winning chances.
+    Blunder,
+}
+
+impl MoveClass {
+    /// The standard PGN NAG for a move of this class, if any.
+    ///
+    /// `$6` (?!) for inaccuracies, `$2` (?) for mistakes and `$4` (??) for
+    /// blunders.
+    pub fn nag(self) -> Option<u8> {
+        match self {
+            MoveClass::Best | MoveClass::Good => None,
+            MoveClass::Inaccuracy => Some(6),
+            MoveClass::Mistake => Some(2),
+            MoveClass::Blunder => Some(4),
+        }
+    }
+
+    /// The NAG given to the engine's move in the alternative line: `$5` (!?)
+    /// after an inaccuracy and `$1` (!) after a mistake or blunder.
+    pub fn alternative_nag(self) -> Option<u8> {
+        match self {
+            MoveClass::Best | MoveClass::Good => None,
+            MoveClass::Inaccuracy => Some(5),
+            MoveClass::Mistake | MoveClass::Blunder => Some(1),
+        }
+    }
+
+    /// The colour moves of this class are usually drawn in, as a CSS hex
+    /// colour.
+    pub fn color(self) -> &'static str {
+        match self {
+            MoveClass::Best => "#5c8bb0",
+            MoveClass::Good => "#81b64c",
+            MoveClass::Inaccuracy => "#f7c631",
+            MoveClass::Mistake => "#e58f2a",
+            MoveClass::Blunder => "#ca3431",
+        }
+    }
+}
+
+/// Returns the traditional symbol for the NAGs used by this module.
+pub fn nag_symbol(nag: u8) -> Option<&'static str> {
+    match nag {
+        1 => Some("!"),
+        2 => Some("?"),
+        4 => Some("??"),
+        5 => Some("!?"),
+        6 => Some("?!"),
+        _ => None,
+    }
+}
+
+/// The phase of the game a position belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Opening,
+    Middlegame,
+    Endgame,
+}
+
+/// Last ply still counted as the opening.
+const OPENING_PLIES: u32 = 20;
+
+/// Positions with this much non-pawn material or less (both sides, knight and
+/// bishop worth 3, rook 5, queen 9) are considered endgames.
+const ENDGAME_MATERIAL: u32 = 26;
+
+impl Phase {
+    /// Guesses the phase from the material on the board and the move number.
+    ///
+    /// # Arguments
+    ///
+    /// * `fen` - The position. Only the piece placement field is used.
+    /// * `ply` - Ply of the game, starting at 1 for white's first move.
+    pub fn of(fen: &str, ply: u32) -> Phase {
+        let placement = fen.split(' ').next().unwrap_or("");
+        let material: u32 = placement.chars()
+            .map(|c| match c.to_ascii_lowercase() {
+                'n' | 'b' => 3,
+                'r' => 5,
+                'q' => 9,
+                _ => 0,
+            })
+            .sum();
+
+        if !placement.is_empty() && material <= ENDGAME_MATERIAL {
+            Phase::Endgame
+        } else if ply <= OPENING_PLIES {
+            Phase::Opening
+        } else {
+            Phase::Middlegame
+        }
+    }
+}
+
+/// Aggregated move quality over a set of moves.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PhaseStats {
+    /// Number of moves counted.
+    pub moves: u32,
+
+    pub inaccuracies: u32,
+    pub mistakes: u32,
+    pub blunders: u32,
+
+    cp_loss: u64,
+    accuracy: f64,
+}
+
+impl PhaseStats {
+    fn add(&mut self, eval: &MoveEval) {
+        self.moves += 1;
+        self.cp_loss += eval.cp_loss() as u64;
+        self.accuracy += eval.accuracy();
+        match eval.class() {
+            MoveClass::Inaccuracy => self.inaccuracies += 1,
+            MoveClass::Mistake => self.mistakes += 1,
+            MoveClass::Blunder => self.blunders += 1,
+            MoveClass::Best | MoveClass::Good => {}
+        }
+    }
+
+    /// Average centipawn loss, or 0 if no moves were counted.
+    pub fn acpl(&self) -> f64 {
+        if self.moves == 0 {
+            0.0
+        } else {
+            self.cp_loss as f64 / self.moves as f64
+        }
+    }
+
+    /// Average accuracy per move in percent, or 100 if no moves were counted.
+    pub fn accuracy(&self) -> f64 {
+        if self.moves == 0 {
+            100.0
+        } else {
+            self.accuracy / self.moves as f64
+        }
+    }
+}
+
+/// Move quality of one player, overall and split by phase.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlayerReport {
+    pub overall: PhaseStats,
+    pub opening: PhaseStats,
+    pub middlegame: PhaseStats,
+    pub endgame: PhaseStats,
+}
+
+impl PlayerReport {
+    /// Returns the statistics for a single phase.
+    pub fn phase(&self, phase: Phase) -> &PhaseStats {
+        match phase {
+            Phase::Opening => &self.opening,
+            Phase::Middlegame => &self.middlegame,
+            Phase::Endgame => &self.endgame,
+        }
+    }
+
+    fn add(&mut self, eval: &MoveEval) {
+        self.overall.add(eval);
+        match eval.phase() {
+            Phase::Opening => self.opening.add(eval),
+            Phase::Middlegame => self.middlegame.add(eval),
+            Phase::Endgame => self.endgame.add(eval),
+        }
+    }
+}
+
+/// Summary of how well both sides played a game.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GameReport {
+    pub white: PlayerReport,
+    pub black: PlayerReport,
+}
+
+impl GameReport {
+    /// Builds the report out of the evaluations of every move of the game.
+    pub fn new(evals: &[MoveEval]) -> GameReport {
+        let mut report = GameReport::default();
+        for eval in evals {
+            if eval.is_white() {
+                report.white.add(eval);
+            } else {
+                report.black.add(eval);
+            }
+        }
+        report
+    }
+}
+
+/// Why a move was singled out as a key moment of the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MomentKind {
+    /// The mover was winning and let the advantage slip.
+    MissedWin,
+
+    /// The position could still be held, but the move lost it.
+    MissedDefense,
+
+    /// A large swing that didn't cross a winning/losing boundary.
+    TurningPoint,
+}
+
+/// A move whose evaluation swing makes it worth looking at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CriticalMoment {
+    /// Ply of the move, starting at 1 for white's first move.
+    pub ply: u32,
+
+    /// FEN of the position the move was played in.
+    pub fen: String,
+
+    /// The move that was played.
+    pub played: String,
+
+    pub kind: MomentKind,
+
+    /// Winning chances lost by the move, in percentage points.
+    pub swing: f64,
+
+    /// The engine's better line from the same position.
+    pub best_line: Vec<String>,
+}
+
+/// Winning chances at or above this are considered a won position.
+const WINNING: f64 = 75.0;
+
+/// Winning chances at or below this are considered a lost position.
+const LOSING: f64 = 25.0;
+
+/// Swings smaller than this aren't reported.
+const MIN_SWING: f64 = 10.0;
+
+/// Picks the turning points of a game out of its per-move evaluations.
+///
+/// Returns at most `max` moments, largest swing first.
+///
+/// # Arguments
+///
+/// * `evals` - Evaluations of every move of the game, in order.
+/// * `max`   - Maximum number of moments to return.
+pub fn critical_moments(evals: &[MoveEval], max: usize) -> Vec<CriticalMoment> {
+    let mut moments: Vec<CriticalMoment> = evals.iter()
+        .filter(|e| e.win_loss() >= MIN_SWING)
+        .map(|e| {
+            let (before, after) = (e.win_before(), e.win_after());
+            let kind = if before >= WINNING && after < WINNING {
+                MomentKind::MissedWin
+            } else if before > LOSING && after <= LOSING {
+                MomentKind::MissedDefense
+            } else {
+                MomentKind::TurningPoint
+            };
+
+            CriticalMoment {
+                ply: e.ply,
+                fen: e.fen.clone(),
+                played: e.played.clone(),
+                kind,
+                swing: e.win_loss(),
+                best_line: e.best_line.clone(),
+            }
+        })
+        .collect();
+
+    moments.sort_by(|a, b| b.swing.partial_cmp(&a.swing).unwrap());
+    moments.truncate(max);
+    moments
+}
+
+/// A point of an evaluation graph, see [`eval_graph`].
+///
+/// Evaluations are from white's point of view, as charts usually show them.
+///
+/// [`eval_graph`]: fn.eval_graph.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphPoint {
+    /// Ply of the move, starting at 1 for white's first move.
+    pub ply: u32,
+
+    /// Evaluation after the move, in centipawns clamped to +/-1000.
+    pub cp: i32,
+
+    /// White's winning chances after the move, in percent.
+    pub win: f64,
+
+    /// Moves to mate after the move, negative if black mates.
+    pub mate: Option<i32>,
+
+    pub class: MoveClass,
+
+    /// The colour of `class`, see [`MoveClass::color`].
+    ///
+    /// [`MoveClass::color`]: enum.MoveClass.html#method.color
+    pub color: &'static str,
+}
+
+/// Turns the evaluations of a game into a series ready to plot, one point
+/// per move.
+///
+/// # Examples
+///
+/// ```
+/// use uci::Score;
+/// use uci::review::{eval_graph, MoveEval};
+///
+/// let evals = vec![MoveEval {
+///     ply: 1,
+///     fen: "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string(),
+///     played: "e2e4".to_string(),
+///     best_score: Score::Cp(30),
+///     played_score: Score::Cp(30),
+///     best_line: vec!["e2e4".to_string()],
+/// }];
+///
+/// for point in eval_graph(&evals) {
+///     println!("{} {} {:.1}% {}", point.ply, point.cp, point.win, point.color);
+/// }
+/// ```
+pub fn eval_graph(evals: &[MoveEval]) -> Vec<GraphPoint> {
+    evals.iter()
+        .map(|eval| {
+            let score = if eval.is_white() { eval.played_score } else { eval.played_score.negate() };
+            let class = eval.class();
+            GraphPoint {
+                ply: eval.ply,
+                cp: score.centipawns(),
+                win: score.win_probability(),
+                mate: match score {
+                    Score::Mate(n) => Some(n),
+                    Score::Cp(_) => None,
+                },
+                class,
+                color: class.color(),
+            }
+        })
+        .collect()
+}
+
+/// Writes the moves as PGN movetext annotated with NAGs, `[%eval]` comments
+/// and the engine's line as a variation wherever the played move was an
+/// inaccuracy or worse.
+///
+/// Moves are written exactly as they are stored in the evaluations, so give
+/// them in SAN if the output is meant for PGN viewers.
+#[cfg(feature = "pgn")]
+pub fn annotate(evals: &[MoveEval]) -> String {
+    let mut out: Vec<String> = vec![];
+
+    for eval in evals {
+        let number = move_number(eval);
+        let white = eval.is_white();
+        out.push(numbered(number, white, &eval.played));
+
+        let class = eval.class();
+        if let Some(nag) = class.nag() {
+            out.push(format!("${}", nag));
+        }
+
+        let after = if white { eval.played_score } else { eval.played_score.negate() };
+        out.push(format!("{{ [%eval {}] }}", pgn_eval(after)));
+
+        if let (Some(nag), Some(best)) = (class.alternative_nag(), eval.best_line.first()) {
+            if *best != eval.played {
+                let mut line = vec![format!("({}", numbered(number, white, best)),
+                                    format!("${}", nag)];
+                let mut white = white;
+                let mut number = number;
+                for mv in &eval.best_line[1..] {
+                    if !white {
+                        number += 1;
+                    }
+                    white = !white;
+                    line.push(if white { format!("{}. {}", number, mv) } else { mv.clone() });
+                }
+                let last = line.pop().unwrap();
+                line.push(format!("{})", last));
+                out.push(line.join(" "));
+            }
+        }
+    }
+
+    out.join(" ")
+}
+
+#[cfg(feature = "pgn")]
+fn move_number(eval: &MoveEval) -> u32 {
+    eval.fen.split(' ').nth(5)
+        .and_then(|n| n.parse::<u32>().ok())
+        .unwrap_or(eval.ply.div_ceil(2))
+}
+
+#[cfg(feature = "pgn")]
+pub(crate) fn numbered(number: u32, white: bool, mv: &str) -> String {
+    if white {
+        format!("{}. {}", number, mv)
+    } else {
+        format!("{}... {}", number, mv)
+    }
+}
+
+pub(crate) fn pgn_eval(score: Score) -> String {
+    match score {
+        Score::Cp(cp) => format!("{:.2}", cp as f64 / 100.0),
+        Score::Mate(n) => format!("#{}", n),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(ply: u32, best: Score, played: Score) -> MoveEval {
+        MoveEval {
+            ply,
+            fen: String::new(),
+            played: "a2a3".to_string(),
+            best_score: best,
+            played_score: played,
+            best_line: vec!["e2e4".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_critical_moments() {
+        let evals = vec![
+            eval(1, Score::Cp(20), Score::Cp(10)),
+            eval(2, Score::Mate(3), Score::Cp(50)),
+            eval(3, Score::Cp(0), Score::Cp(-400)),
+            eval(4, Score::Cp(-50), Score::Cp(-200)),
+        ];
+
+        let moments = critical_moments(&evals, 10);
+        let kinds: Vec<(u32, MomentKind)> = moments.iter().map(|m| (m.ply, m.kind)).collect();
+        assert_eq!(kinds, vec![(2, MomentKind::MissedWin),
+                               (3, MomentKind::MissedDefense),
+                               (4, MomentKind::TurningPoint)]);
+
+        assert_eq!(critical_moments(&evals, 1).len(), 1);
+    }
+
+    #[test]
+    fn test_eval_graph() {
+        let evals = vec![
+            eval(1, Score::Cp(20), Score::Cp(20)),
+            eval(2, Score::Cp(-20), Score::Mate(-2)),
+        ];
+
+        let graph = eval_graph(&evals);
+        assert_eq!(graph[0].cp, 20);
+        assert_eq!(graph[1].cp, 1000);
+        assert_eq!(graph[1].mate, Some(2));
+        assert_eq!(graph[1].win, 100.0);
+        assert_eq!(graph[1].class, MoveClass::Blunder);
+        assert_eq!(graph[1].color, "#ca3431");
+    }
+
+    #[test]
+    fn test_game_report() {
+        let mut endgame = eval(41, Score::Cp(300), Score::Cp(0));
+        endgame.fen = "8/5k2/8/3R4/8/8/2K5/8 w - - 0 21".to_string();
+
+        let mut evals = vec![
+            eval(1, Score::Cp(20), Score::Cp(20)),
+            eval(2, Score::Cp(0), Score::Cp(-400)),
+            eval(23, Score::Cp(50), Score::Cp(-50)),
+            endgame,
+        ];
+        evals[0].played = "e2e4".to_string();
+
+        let report = GameReport::new(&evals);
+        assert_eq!(report.white.overall.moves, 3);
+        assert_eq!(report.white.opening.moves, 1);
+        assert_eq!(report.white.middlegame.inaccuracies, 1);
+        assert_eq!(report.white.endgame.blunders, 1);
+        assert!(report.white.phase(Phase::Opening).accuracy() > 99.9);
+        assert_eq!(report.black.opening.blunders, 1);
+        assert_eq!(report.black.opening.acpl(), 400.0);
+    }
+
+    #[cfg(feature = "pgn")]
+    #[test]
+    fn test_annotate() {
+        let mut first = eval(1, Score::Cp(20), Score::Cp(20));
+        first.played = "e4".to_string();
+        let mut second = eval(2, Score::Cp(-20), Score::Cp(-350));
+        second.played = "f6".to_string();
+        second.best_line = vec!["e5".to_string(), "Nf3".to_string(), "Nc6".to_string()];
+
+        assert_eq!(annotate(&[first, second]),
+                   "1. e4 { [%eval 0.20] } 1... f6 $4 { [%eval 3.50] } (1... e5 $1 2. Nf3 Nc6)");
+        assert_eq!(nag_symbol(6), Some("?!"));
+    }
+}
@@ -0,0 +1,355 @@
+use std::collections::VecDeque;
+use std::io::BufRead;
+
+use error::Result;
+use info::InfoRef;
+use pool::{EnginePool, JobHandle};
+use score::Score;
+use {Engine, SearchLimits, START_FEN};
+
+/// A game read from a PGN file by a [`PgnReader`].
+///
+/// [`PgnReader`]: struct.PgnReader.html
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PgnGame {
+    /// Tag pairs, in the order they appear.
+    pub headers: Vec<(String, String)>,
+
+    /// The moves of the main line, without comments, variations or NAGs.
+    pub moves: Vec<String>,
+}
+
+impl PgnGame {
+    /// The value of a tag pair.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|&(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    /// The position the game starts from, given by its `FEN` tag or the
+    /// standard starting position.
+    pub fn start(&self) -> &str {
+        self.header("FEN").unwrap_or(START_FEN)
+    }
+}
+
+/// Reads the games of a PGN file one by one, without loading the whole file.
+///
+/// Moves have to be in Coordinate notation, as this crate writes them; games
+/// in SAN have to be converted first.
+///
+/// # Examples
+///
+/// ```
+/// use uci::scan::PgnReader;
+///
+/// let pgn = "[White \"A\"]\n\n1. e2e4 {best} e7e5 (1... c7c5) 2. g1f3 *\n";
+/// let game = PgnReader::new(pgn.as_bytes()).next().unwrap().unwrap();
+/// assert_eq!(game.header("White"), Some("A"));
+/// assert_eq!(game.moves, vec!["e2e4", "e7e5", "g1f3"]);
+/// ```
+pub struct PgnReader<R> {
+    input: R,
+
+    /// A tag pair of the next game, read while looking for the end of the
+    /// current one.
+    pending: Option<String>,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(input: R) -> PgnReader<R> {
+        PgnReader { input, pending: None }
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<PgnGame>;
+
+    fn next(&mut self) -> Option<Result<PgnGame>> {
+        let mut game = PgnGame::default();
+        let mut movetext = String::new();
+
+        loop {
+            let line = match self.pending.take() {
+                Some(line) => line,
+                None => {
+                    let mut line = String::new();
+                    match self.input.read_line(&mut line) {
+                        Ok(0) => break,
+                        Ok(_) => line,
+                        Err(e) => return Some(Err(e.into())),
+                    }
+                }
+            };
+
+            let line = line.trim();
+            if line.starts_with('[') {
+                if !movetext.is_empty() {
+                    self.pending = Some(line.to_string());
+                    break;
+                }
+                if let Some(header) = parse_header(line) {
+                    game.headers.push(header);
+                }
+            } else if !line.is_empty() {
+                movetext.push_str(line);
+                movetext.push('\n');
+            }
+        }
+
+        if game.headers.is_empty() && movetext.is_empty() {
+            return None;
+        }
+        game.moves = parse_movetext(&movetext);
+        Some(Ok(game))
+    }
+}
+
+/// Parses a `[Name "Value"]` tag pair.
+fn parse_header(line: &str) -> Option<(String, String)> {
+    let inner = line.strip_prefix('[')?.strip_suffix(']')?;
+    let (name, value) = inner.split_once(' ')?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+    Some((name.to_string(), value.replace("\\\"", "\"")))
+}
+
+/// Picks the moves of the main line out of PGN movetext.
+fn parse_movetext(movetext: &str) -> Vec<String> {
+    let mut moves = vec![];
+    let mut depth: usize = 0;
+    let mut token = String::new();
+    let mut chars = movetext.chars();
+
+    while let Some(c) = chars.next() {
+        if !c.is_whitespace() && !"{};()".contains(c) {
+            token.push(c);
+            continue;
+        }
+
+        push_move(&mut moves, &mut token, depth);
+        match c {
+            '{' => chars.by_ref().take_while(|&c| c != '}').for_each(drop),
+            ';' => chars.by_ref().take_while(|&c| c != '\n').for_each(drop),
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    push_move(&mut moves, &mut token, depth);
+    moves
+}
+
+/// Keeps a token ended by a separator if it's a main line move.
+fn push_move(moves: &mut Vec<String>, token: &mut String, depth: usize) {
+    // Move numbers may be glued to the move: "1.e2e4".
+    let mv = token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.')
+        .trim_end_matches(['+', '#', '!', '?']);
+    let is_move = !mv.is_empty() && !mv.starts_with('$')
+        && !["1-0", "0-1", "1/2-1/2", "*"].contains(&token.as_str());
+    if depth == 0 && is_move {
+        moves.push(mv.to_string());
+    }
+    token.clear();
+}
+
+/// A move losing more winning chances than the threshold of a
+/// [`BlunderScan`].
+///
+/// [`BlunderScan`]: struct.BlunderScan.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct Blunder {
+    /// Index of the game in the PGN file, starting at 0.
+    pub game: usize,
+
+    /// Ply of the move in the game, starting at 1.
+    pub ply: u32,
+
+    /// FEN of the position the move was played in.
+    pub fen: String,
+
+    /// The move that was played.
+    pub played: String,
+
+    /// Winning chances lost by the move, in percentage points.
+    pub win_loss: f64,
+
+    /// The line the engine preferred, starting with its best move.
+    pub best_line: Vec<String>,
+
+    /// The engine's line after the played move, starting with the reply
+    /// punishing it.
+    pub refutation: Vec<String>,
+}
+
+/// Finds the blunders of a PGN database, as when building a set of tactics
+/// out of real games.
+///
+/// Games are analysed in parallel on the engines of an [`EnginePool`], every
+/// position once. Games are read as the engines are ready for them, so the
+/// database doesn't have to fit in memory.
+///
+/// # Examples
+///
+/// ```
+/// use uci::SearchLimits;
+/// use uci::pool::EnginePool;
+/// use uci::scan::BlunderScan;
+///
+/// let pool = EnginePool::new(uci::EngineBuilder::new("stockfish"), 2).unwrap();
+/// let pgn = "1. f2f3 e7e5 2. g2g4 d8h4 0-1\n";
+///
+/// let scan = BlunderScan::new()
+///     .limits(SearchLimits { depth: Some(10), ..SearchLimits::default() })
+///     .threshold(20.0);
+/// scan.run(&pool, pgn.as_bytes(), |blunder| {
+///     println!("{} in {}: refuted by {:?}", blunder.played, blunder.fen, blunder.refutation);
+/// }).unwrap();
+/// ```
+///
+/// [`EnginePool`]: ../pool/struct.EnginePool.html
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlunderScan {
+    limits: SearchLimits,
+    threshold: f64,
+}
+
+impl Default for BlunderScan {
+    fn default() -> BlunderScan {
+        BlunderScan::new()
+    }
+}
+
+impl BlunderScan {
+    /// Creates a scan searching every position for 100 milliseconds and
+    /// reporting moves losing 15 or more percentage points of winning
+    /// chances, the bound of [`MoveClass::Blunder`].
+    ///
+    /// [`MoveClass::Blunder`]: ../review/enum.MoveClass.html#variant.Blunder
+    pub fn new() -> BlunderScan {
+        BlunderScan { limits: SearchLimits::default(), threshold: 15.0 }
+    }
+
+    /// Changes the limits every position is searched with.
+    pub fn limits(mut self, limits: SearchLimits) -> BlunderScan {
+        self.limits = limits;
+        self
+    }
+
+    /// Changes how many percentage points of winning chances a move has to
+    /// lose to be reported.
+    pub fn threshold(mut self, threshold: f64) -> BlunderScan {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Scans the games of a PGN file, passing the blunders to `emit` game
+    /// by game, in the order of the file. Returns the number of games
+    /// scanned.
+    ///
+    /// A game that can't be analysed, as one with an illegal move, is
+    /// skipped with a warning. Errors reading the file end the scan.
+    pub fn run<R, F>(&self, pool: &EnginePool, pgn: R, mut emit: F) -> Result<usize>
+        where R: BufRead,
+              F: FnMut(Blunder)
+    {
+        let mut running: VecDeque<(usize, JobHandle<Vec<Blunder>>)> = VecDeque::new();
+        let mut games = 0;
+
+        let mut collect = |(index, job): (usize, JobHandle<Vec<Blunder>>)| match job.wait() {
+            Ok(blunders) => blunders.into_iter().for_each(&mut emit),
+            Err(e) => warn!("Game {} could not be scanned: {}", index, e),
+        };
+
+        for game in PgnReader::new(pgn) {
+            let game = game?;
+            let scan = *self;
+            let index = games;
+            running.push_back((index, pool.submit(move |engine| scan.scan_game(engine, index, &game))));
+            games += 1;
+
+            // Keep every engine busy, but no more games than that in memory.
+            if running.len() > pool.size() {
+                collect(running.pop_front().unwrap());
+            }
+        }
+        running.into_iter().for_each(collect);
+
+        Ok(games)
+    }
+
+    fn scan_game(&self, engine: &Engine, index: usize, pgn: &PgnGame) -> Result<Vec<Blunder>> {
+        engine.new_game()?;
+        let mut game = engine.game(pgn.start())?;
+
+        let mut fens = vec![game.fen().to_string()];
+        let mut evals = vec![self.evaluate(engine)?];
+        for mv in &pgn.moves {
+            game.play(mv)?;
+            fens.push(game.fen().to_string());
+            evals.push(self.evaluate(engine)?);
+        }
+
+        let mut blunders = vec![];
+        for (i, mv) in pgn.moves.iter().enumerate() {
+            let (best, ref best_line) = evals[i];
+            let (after, ref refutation) = evals[i + 1];
+            let (best, after) = match (best, after) {
+                // Mate 0 is reported for the side being mated, the mover
+                // mated them.
+                (Some(best), Some(Score::Mate(0))) => (best, Score::Mate(1)),
+                (Some(best), Some(after)) => (best, after.negate()),
+                _ => continue,
+            };
+
+            let win_loss = best.win_probability() - after.win_probability();
+            if win_loss >= self.threshold {
+                blunders.push(Blunder {
+                    game: index,
+                    ply: i as u32 + 1,
+                    fen: fens[i].clone(),
+                    played: mv.clone(),
+                    win_loss,
+                    best_line: best_line.clone(),
+                    refutation: refutation.clone(),
+                });
+            }
+        }
+        Ok(blunders)
+    }
+
+    /// Searches the engine's position and returns the score for the side to
+    /// move and the principal variation.
+    fn evaluate(&self, engine: &Engine) -> Result<(Option<Score>, Vec<String>)> {
+        let lines = engine.search(&self.limits.go_args())?;
+        let last = lines.iter()
+            .rev()
+            .filter_map(|line| InfoRef::parse(line))
+            .find(|info| info.score.is_some() && info.multipv.unwrap_or(1) == 1);
+
+        Ok(match last {
+            Some(info) => (info.score.map(|score| engine.normalize(score)),
+                           info.pv_moves().map(str::to_string).collect()),
+            None => (None, vec![]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pgn_reader() {
+        let pgn = "[Event \"First\"]\n[FEN \"8/8/8/8/8/8/4k3/4K2R w K - 0 1\"]\n\n\
+                   1. h1h2+ $2 {a check\nover two lines} e2e3 ; comment\n\
+                   2. h2h3! (2. e1f1 (2. h2g2) e3f3) 2... e3e4 1/2-1/2\n\n\
+                   [Event \"Second\"]\n\n1.e2e4 e7e5 *\n";
+        let games: Vec<PgnGame> = PgnReader::new(pgn.as_bytes()).map(|game| game.unwrap()).collect();
+
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].header("Event"), Some("First"));
+        assert_eq!(games[0].start(), "8/8/8/8/8/8/4k3/4K2R w K - 0 1");
+        assert_eq!(games[0].moves, vec!["h1h2", "e2e3", "h2h3", "e3e4"]);
+        assert_eq!(games[1].start(), START_FEN);
+        assert_eq!(games[1].moves, vec!["e2e4", "e7e5"]);
+    }
+}
@@ -0,0 +1,170 @@
+/// An engine evaluation, always from the point of view of the side to move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Score {
+    /// Evaluation in centipawns.
+    Cp(i32),
+
+    /// Forced mate in the given number of moves. Negative values mean the
+    /// side to move is getting mated.
+    Mate(i32),
+}
+
+/// How the centipawns an engine reports relate to the scale used by this
+/// crate, where a pawn of advantage is 100 centipawns and
+/// [`Score::win_probability`] gives the expected winning chances.
+///
+/// Engines disagree: some report internal units, Leela derives its
+/// centipawns from the expected score. Normalizing makes scores of different
+/// engines comparable.
+///
+/// # Examples
+///
+/// ```
+/// use uci::{Normalization, Score};
+///
+/// // An engine whose pawn is worth 208 internal units.
+/// let internal = Normalization::Linear(100.0 / 208.0);
+/// assert_eq!(Score::Cp(208).normalize(internal), Score::Cp(100));
+///
+/// // Leela's 128 cp is an expected score of 75% in its own model.
+/// let cp = Score::Cp(112).normalize(Normalization::Leela);
+/// assert!((cp.win_probability() - 75.0).abs() < 0.5);
+/// ```
+///
+/// [`Score::win_probability`]: enum.Score.html#method.win_probability
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Normalization {
+    /// The engine already uses the crate's scale. This is the default.
+    #[default]
+    None,
+
+    /// Centipawns are multiplied by the factor.
+    Linear(f64),
+
+    /// Centipawns are converted back to Leela's expected score, then to the
+    /// centipawns with the same winning chances.
+    Leela,
+}
+
+/// Constants of Leela's conversion from expected score to centipawns,
+/// `cp = A * tan(B * q)`.
+const LEELA_A: f64 = 111.714640912;
+const LEELA_B: f64 = 1.5620688421;
+
+/// Slope of the logistic model behind [`Score::win_probability`].
+const WIN_SLOPE: f64 = 0.00368208;
+
+/// Centipawn values beyond this are treated as completely decided when
+/// converting to win probabilities or averaging losses.
+const CP_CEILING: i32 = 1000;
+
+impl Score {
+    /// Parses the `cp <x>` / `mate <y>` pair found in `info` lines.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use uci::Score;
+    ///
+    /// assert_eq!(Score::parse("cp", "-35"), Some(Score::Cp(-35)));
+    /// assert_eq!(Score::parse("mate", "3"), Some(Score::Mate(3)));
+    /// assert_eq!(Score::parse("wdl", "3"), None);
+    /// ```
+    pub fn parse(kind: &str, value: &str) -> Option<Score> {
+        let value = value.parse::<i32>().ok()?;
+        match kind {
+            "cp" => Some(Score::Cp(value)),
+            "mate" => Some(Score::Mate(value)),
+            _ => None,
+        }
+    }
+
+    /// Returns the same evaluation seen from the other side.
+    pub fn negate(self) -> Score {
+        match self {
+            Score::Cp(cp) => Score::Cp(-cp),
+            Score::Mate(n) => Score::Mate(-n),
+        }
+    }
+
+    /// Returns the score in centipawns, clamped to +/-1000. Mates count as
+    /// the clamped maximum.
+    pub fn centipawns(self) -> i32 {
+        match self {
+            Score::Cp(cp) => cp.clamp(-CP_CEILING, CP_CEILING),
+            Score::Mate(n) if n > 0 => CP_CEILING,
+            Score::Mate(_) => -CP_CEILING,
+        }
+    }
+
+    /// Returns the expected winning chances of the side to move, in percent.
+    ///
+    /// Uses the logistic model popularized by lichess, fitted on games between
+    /// human players.
+    pub fn win_probability(self) -> f64 {
+        match self {
+            Score::Mate(n) if n > 0 => 100.0,
+            Score::Mate(_) => 0.0,
+            Score::Cp(_) => {
+                let cp = self.centipawns() as f64;
+                50.0 + 50.0 * (2.0 / (1.0 + (-WIN_SLOPE * cp).exp()) - 1.0)
+            }
+        }
+    }
+
+    /// Converts a score reported by an engine to the crate's scale. Mates are
+    /// left alone.
+    pub fn normalize(self, normalization: Normalization) -> Score {
+        let cp = match self {
+            Score::Cp(cp) => cp as f64,
+            Score::Mate(_) => return self,
+        };
+        let normalized = match normalization {
+            Normalization::None => return self,
+            Normalization::Linear(factor) => cp * factor,
+            Normalization::Leela => {
+                let q = ((cp / LEELA_A).atan() / LEELA_B).clamp(-0.9999, 0.9999);
+                ((1.0 + q) / (1.0 - q)).ln() / WIN_SLOPE
+            }
+        };
+        Score::Cp(normalized.round() as i32)
+    }
+}
+
+/// Win, draw and loss chances of the side to move, in permill, as sent in
+/// the `wdl` field of `info` lines by engines with `UCI_ShowWDL` enabled.
+///
+/// Unlike centipawns, these tell a drawish advantage from a winning one.
+///
+/// # Examples
+///
+/// ```
+/// use uci::Wdl;
+///
+/// let wdl = Wdl { win: 300, draw: 600, loss: 100 };
+/// assert_eq!(wdl.expected_score(), 60.0);
+/// assert_eq!(wdl.flip(), Wdl { win: 100, draw: 600, loss: 300 });
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Wdl {
+    pub win: u32,
+    pub draw: u32,
+    pub loss: u32,
+}
+
+impl Wdl {
+    /// Returns the expected score of the side to move, counting draws as
+    /// half a win, in percent.
+    pub fn expected_score(self) -> f64 {
+        let total = self.win + self.draw + self.loss;
+        if total == 0 {
+            return 50.0;
+        }
+        100.0 * (f64::from(self.win) + f64::from(self.draw) / 2.0) / f64::from(total)
+    }
+
+    /// The same chances from the point of view of the other side.
+    pub fn flip(self) -> Wdl {
+        Wdl { win: self.loss, draw: self.draw, loss: self.win }
+    }
+}
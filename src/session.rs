@@ -0,0 +1,160 @@
+use std::time::{Duration, Instant};
+
+use error::Result;
+use info::InfoRef;
+use overlay::Overlay;
+use Engine;
+
+/// Analysis of one position that can be continued, see
+/// [`Engine::session`].
+///
+/// Every call to [`deepen`] searches the same position again without
+/// clearing the engine's hash, so most of the previous work is reused and the
+/// search reaches greater depths on top of it.
+///
+/// [`branch`] explores a hypothetical move in a child session, leaving this
+/// one as it is.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+///
+/// let engine = uci::Engine::new("stockfish").unwrap();
+/// let mut session = engine.session("6b1/8/1k5P/8/1P3B2/5pp1/8/4K3 b - - 0 1").unwrap();
+///
+/// session.deepen(Duration::from_millis(100)).unwrap();
+/// // The user asks for more.
+/// session.deepen(Duration::from_millis(400)).unwrap();
+/// println!("depth {} after {:?}: {:?}", session.depth(), session.time(), session.overlay());
+/// ```
+///
+/// [`Engine::session`]: ../struct.Engine.html#method.session
+/// [`deepen`]: #method.deepen
+/// [`branch`]: #method.branch
+pub struct AnalysisSession<'a> {
+    engine: &'a Engine,
+    fen: String,
+    moves: Vec<String>,
+    depth: u32,
+    time: Duration,
+    searches: usize,
+    overlay: Overlay,
+}
+
+impl<'a> AnalysisSession<'a> {
+    pub(crate) fn new(engine: &'a Engine, fen: &str) -> Result<AnalysisSession<'a>> {
+        engine.set_position(fen)?;
+        Ok(AnalysisSession {
+            engine,
+            fen: fen.to_string(),
+            moves: vec![],
+            depth: 0,
+            time: Duration::default(),
+            searches: 0,
+            overlay: Overlay::default(),
+        })
+    }
+
+    /// Searches the position for `extra` more time.
+    pub fn deepen(&mut self, extra: Duration) -> Result<&Overlay> {
+        self.run(&format!("movetime {}", extra.as_millis().max(1)))
+    }
+
+    /// Searches the position until `plies` deeper than reached so far.
+    pub fn deepen_by_depth(&mut self, plies: u32) -> Result<&Overlay> {
+        self.run(&format!("depth {}", self.depth + plies))
+    }
+
+    fn run(&mut self, args: &str) -> Result<&Overlay> {
+        // Sending the position again doesn't clear the hash, and keeps the
+        // session right if the engine was used for something else meanwhile.
+        self.engine.make_moves_from_position(&self.fen, &self.moves)?;
+
+        let start = Instant::now();
+        let lines = self.engine.search(args)?;
+        self.time += start.elapsed();
+        self.searches += 1;
+
+        let depth = lines.iter()
+            .filter_map(|line| InfoRef::parse(line).and_then(|info| info.depth))
+            .max();
+        self.depth = self.depth.max(depth.unwrap_or(0));
+        let overlay = Overlay::from_info_lines(lines.iter().map(String::as_str));
+        if !overlay.candidates.is_empty() {
+            self.overlay = overlay;
+        }
+        Ok(&self.overlay)
+    }
+
+    /// Starts a session analysing the position after `mv`.
+    ///
+    /// The child shares the engine, switching it to its position whenever it
+    /// searches, and starts with no depth or time of its own. This session is
+    /// not affected.
+    ///
+    /// # Arguments
+    ///
+    /// * `mv` - The move, in Coordinate notation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// let mut main = engine.session("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+    /// main.deepen(Duration::from_millis(100)).unwrap();
+    ///
+    /// let mut what_if = main.branch("g2g4");
+    /// what_if.deepen(Duration::from_millis(100)).unwrap();
+    /// main.deepen(Duration::from_millis(100)).unwrap();
+    /// assert_eq!(what_if.moves(), ["g2g4"]);
+    /// ```
+    pub fn branch(&self, mv: &str) -> AnalysisSession<'a> {
+        let mut moves = self.moves.clone();
+        moves.push(mv.to_string());
+        AnalysisSession {
+            engine: self.engine,
+            fen: self.fen.clone(),
+            moves,
+            depth: 0,
+            time: Duration::default(),
+            searches: 0,
+            overlay: Overlay::default(),
+        }
+    }
+
+    /// The position the session, or the session it branched from, started
+    /// at.
+    pub fn fen(&self) -> &str {
+        &self.fen
+    }
+
+    /// Moves leading from [`fen`] to the analysed position.
+    ///
+    /// [`fen`]: #method.fen
+    pub fn moves(&self) -> &[String] {
+        &self.moves
+    }
+
+    /// Deepest depth reached so far.
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Total time spent searching so far.
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    /// Number of searches run so far.
+    pub fn searches(&self) -> usize {
+        self.searches
+    }
+
+    /// Candidate moves of the latest search that reported any.
+    pub fn overlay(&self) -> &Overlay {
+        &self.overlay
+    }
+}
@@ -0,0 +1,143 @@
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+use std::sync::{Arc, Condvar, Mutex};
+
+use Engine;
+
+/// An [`Engine`] that can be shared between threads.
+///
+/// Callers get exclusive use of the engine for a whole closure, so a complete
+/// conversation (position, go, reading the result) can't be interleaved with
+/// another caller's. Waiting callers are served by priority, and in arrival
+/// order within the same priority.
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+///
+/// let engine = uci::SharedEngine::new(uci::Engine::new("stockfish").unwrap());
+/// let handles: Vec<_> = vec!["e2e4", "d2d4"].into_iter().map(|mv| {
+///     let engine = engine.clone();
+///     thread::spawn(move || engine.run(|e| {
+///         e.make_moves(&[mv.to_string()]).unwrap();
+///         e.bestmove().unwrap()
+///     }))
+/// }).collect();
+///
+/// for handle in handles {
+///     println!("{}", handle.join().unwrap());
+/// }
+/// ```
+///
+/// [`Engine`]: struct.Engine.html
+#[derive(Clone)]
+pub struct SharedEngine {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    engine: Mutex<Engine>,
+    scheduler: Mutex<Scheduler>,
+    turn: Condvar,
+}
+
+/// Tickets of the callers waiting for the engine.
+#[derive(Default)]
+struct Scheduler {
+    next_ticket: u64,
+    waiting: BTreeSet<(Reverse<i32>, u64)>,
+    busy: bool,
+}
+
+impl Scheduler {
+    fn enqueue(&mut self, priority: i32) -> (Reverse<i32>, u64) {
+        let ticket = (Reverse(priority), self.next_ticket);
+        self.next_ticket += 1;
+        self.waiting.insert(ticket);
+        ticket
+    }
+
+    fn is_turn(&self, ticket: &(Reverse<i32>, u64)) -> bool {
+        !self.busy && self.waiting.iter().next() == Some(ticket)
+    }
+}
+
+/// Hands the engine to the next caller when dropped, even on panic.
+struct Turn<'a> {
+    inner: &'a Inner,
+}
+
+impl<'a> Drop for Turn<'a> {
+    fn drop(&mut self) {
+        self.inner.scheduler.lock().unwrap().busy = false;
+        self.inner.turn.notify_all();
+    }
+}
+
+impl SharedEngine {
+    /// Wraps an engine so it can be shared.
+    pub fn new(engine: Engine) -> SharedEngine {
+        SharedEngine {
+            inner: Arc::new(Inner {
+                engine: Mutex::new(engine),
+                scheduler: Mutex::new(Scheduler::default()),
+                turn: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Waits for the engine and runs `f` with exclusive access to it.
+    pub fn run<F, T>(&self, f: F) -> T
+        where F: FnOnce(&Engine) -> T
+    {
+        self.run_with_priority(0, f)
+    }
+
+    /// Like [`run`], but callers with a higher priority are served first.
+    ///
+    /// [`run`]: #method.run
+    pub fn run_with_priority<F, T>(&self, priority: i32, f: F) -> T
+        where F: FnOnce(&Engine) -> T
+    {
+        {
+            let mut scheduler = self.inner.scheduler.lock().unwrap();
+            let ticket = scheduler.enqueue(priority);
+            while !scheduler.is_turn(&ticket) {
+                scheduler = self.inner.turn.wait(scheduler).unwrap();
+            }
+            scheduler.waiting.remove(&ticket);
+            scheduler.busy = true;
+        }
+
+        let _turn = Turn { inner: &self.inner };
+        let engine = self.inner.engine.lock().unwrap_or_else(|e| e.into_inner());
+        f(&engine)
+    }
+
+    /// Number of callers currently waiting for the engine.
+    pub fn queued(&self) -> usize {
+        self.inner.scheduler.lock().unwrap().waiting.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scheduler_order() {
+        let mut scheduler = Scheduler::default();
+        let first = scheduler.enqueue(0);
+        let second = scheduler.enqueue(0);
+        let urgent = scheduler.enqueue(5);
+
+        assert!(scheduler.is_turn(&urgent));
+        scheduler.waiting.remove(&urgent);
+        assert!(scheduler.is_turn(&first));
+        assert!(!scheduler.is_turn(&second));
+
+        scheduler.busy = true;
+        assert!(!scheduler.is_turn(&first));
+    }
+}
@@ -0,0 +1,153 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use info::InfoRef;
+use score::Score;
+
+/// What is known so far of the search in progress, see
+/// [`Engine::current_search`].
+///
+/// [`Engine::current_search`]: struct.Engine.html#method.current_search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchSnapshot {
+    pub depth: Option<u32>,
+    pub score: Option<Score>,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+
+    /// The principal variation, in Coordinate notation.
+    pub pv: Vec<String>,
+
+    /// Time since the search started.
+    pub elapsed: Duration,
+}
+
+impl SearchSnapshot {
+    /// Output standing in for a search that was cut short: an `info` line
+    /// with what was found and a `bestmove` line with the first move of the
+    /// pv. `None` if there's no pv yet.
+    pub(crate) fn as_output(&self) -> Option<Vec<String>> {
+        let bestmove = self.pv.first()?;
+        let mut info = vec!["info".to_string()];
+        if let Some(depth) = self.depth {
+            info.push(format!("depth {}", depth));
+        }
+        match self.score {
+            Some(Score::Cp(cp)) => info.push(format!("score cp {}", cp)),
+            Some(Score::Mate(n)) => info.push(format!("score mate {}", n)),
+            None => {}
+        }
+        info.push(format!("pv {}", self.pv.join(" ")));
+        Some(vec![info.join(" "), format!("bestmove {}", bestmove)])
+    }
+}
+
+/// A handle on the state of an engine's searches that can be sent to, and
+/// polled from, other threads, see [`Engine::search_probe`].
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// let engine = uci::Engine::new("stockfish").unwrap();
+/// let probe = engine.search_probe();
+/// let status = thread::spawn(move || {
+///     for _ in 0..5 {
+///         println!("{:?}", probe.get());
+///         thread::sleep(Duration::from_millis(50));
+///     }
+/// });
+/// engine.command("go movetime 300").unwrap();
+/// status.join().unwrap();
+/// ```
+///
+/// [`Engine::search_probe`]: struct.Engine.html#method.search_probe
+#[derive(Debug, Clone, Default)]
+pub struct SearchProbe {
+    inner: Arc<Mutex<Option<(Instant, SearchSnapshot)>>>,
+
+    /// The state the last search had reached when it finished.
+    last: Arc<Mutex<Option<SearchSnapshot>>>,
+}
+
+impl SearchProbe {
+    /// The state of the search in progress, or `None` if the engine isn't
+    /// searching.
+    pub fn get(&self) -> Option<SearchSnapshot> {
+        self.inner.lock().unwrap().as_ref().map(|&(start, ref snapshot)| SearchSnapshot {
+            elapsed: start.elapsed(),
+            ..snapshot.clone()
+        })
+    }
+
+    pub(crate) fn start(&self) {
+        *self.inner.lock().unwrap() = Some((Instant::now(), SearchSnapshot {
+            depth: None,
+            score: None,
+            nodes: None,
+            nps: None,
+            pv: vec![],
+            elapsed: Duration::from_secs(0),
+        }));
+    }
+
+    /// Updates the state with an output line of the search. Only the first
+    /// line of `MultiPV` searches is followed.
+    pub(crate) fn update(&self, line: &str) {
+        let info = match InfoRef::parse(line) {
+            Some(ref info) if info.multipv.unwrap_or(1) == 1 => *info,
+            _ => return,
+        };
+        let mut inner = self.inner.lock().unwrap();
+        let snapshot = match *inner {
+            Some((_, ref mut snapshot)) => snapshot,
+            None => return,
+        };
+        snapshot.depth = info.depth.or(snapshot.depth);
+        snapshot.score = info.score.or(snapshot.score);
+        snapshot.nodes = info.nodes.or(snapshot.nodes);
+        snapshot.nps = info.nps.or(snapshot.nps);
+        if info.pv.is_some() {
+            snapshot.pv = info.pv_moves().map(String::from).collect();
+        }
+    }
+
+    pub(crate) fn finish(&self) {
+        *self.last.lock().unwrap() = self.get();
+        *self.inner.lock().unwrap() = None;
+    }
+
+    /// The state the last finished search had reached.
+    pub(crate) fn last_reached(&self) -> Option<SearchSnapshot> {
+        self.last.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probe_updates() {
+        let probe = SearchProbe::default();
+        probe.update("info depth 3 score cp 10 pv e2e4");
+        assert!(probe.get().is_none());
+
+        probe.start();
+        probe.update("info depth 8 score cp 30 nodes 1000 nps 50000 pv e2e4 e7e5");
+        probe.update("info depth 8 multipv 2 score cp 20 pv d2d4");
+        probe.update("info depth 9 currmove g1f3 currmovenumber 2");
+        let snapshot = probe.get().unwrap();
+        assert_eq!(snapshot.depth, Some(9));
+        assert_eq!(snapshot.score, Some(Score::Cp(30)));
+        assert_eq!(snapshot.pv, vec!["e2e4", "e7e5"]);
+
+        let output = snapshot.as_output().unwrap();
+        assert_eq!(output, vec!["info depth 9 score cp 30 pv e2e4 e7e5", "bestmove e2e4"]);
+
+        probe.finish();
+        assert!(probe.get().is_none());
+    }
+}
@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+use info::InfoRef;
+
+/// How far and how fast a search went, as last reported by the engine in
+/// its `info` lines, see [`Engine::bestmove_with_stats`].
+///
+/// Fields the engine never sent are `None`.
+///
+/// [`Engine::bestmove_with_stats`]: struct.Engine.html#method.bestmove_with_stats
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    pub depth: Option<u32>,
+    pub seldepth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+
+    /// Search time reported by the engine.
+    pub time: Option<Duration>,
+
+    /// Hash table usage in permill.
+    pub hashfull: Option<u32>,
+    pub tbhits: Option<u64>,
+}
+
+impl SearchStats {
+    /// Takes every field from the last line of the search output carrying
+    /// it, as engines don't send all of them every time.
+    pub(crate) fn from_lines(lines: &[String]) -> SearchStats {
+        let mut stats = SearchStats::default();
+        for info in lines.iter().filter_map(|line| InfoRef::parse(line)) {
+            stats.depth = info.depth.or(stats.depth);
+            stats.seldepth = info.seldepth.or(stats.seldepth);
+            stats.nodes = info.nodes.or(stats.nodes);
+            stats.nps = info.nps.or(stats.nps);
+            stats.time = info.time.map(Duration::from_millis).or(stats.time);
+            stats.hashfull = info.hashfull.or(stats.hashfull);
+            stats.tbhits = info.tbhits.or(stats.tbhits);
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_lines() {
+        let lines: Vec<String> = vec![
+            "info depth 9 seldepth 12 nodes 9000 nps 90000 hashfull 4 tbhits 0 time 100 pv e2e4",
+            "info depth 10 seldepth 14 nodes 12000 nps 100000 time 120 pv e2e4 e7e5",
+            "info depth 10 currmove d2d4 currmovenumber 2",
+            "bestmove e2e4",
+        ].into_iter().map(str::to_string).collect();
+        let stats = SearchStats::from_lines(&lines);
+
+        assert_eq!(stats.depth, Some(10));
+        assert_eq!(stats.seldepth, Some(14));
+        assert_eq!(stats.nodes, Some(12000));
+        assert_eq!(stats.time, Some(Duration::from_millis(120)));
+        assert_eq!(stats.hashfull, Some(4));
+        assert_eq!(SearchStats::from_lines(&[]), SearchStats::default());
+    }
+}
@@ -0,0 +1,81 @@
+use info::InfoRef;
+use score::Score;
+
+/// Whether the game can go on in a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionStatus {
+    /// The side to move has legal moves.
+    Ongoing,
+
+    /// The side to move is mated.
+    Checkmate,
+
+    /// The side to move has no legal moves but isn't in check.
+    Stalemate,
+}
+
+impl PositionStatus {
+    /// Classifies a position from the output of a search of it.
+    ///
+    /// Engines report a position without legal moves with `bestmove (none)`,
+    /// and most add `score mate 0` when it's mate. Returns `None` when the
+    /// engine had no move but didn't say whether it's mate, which takes
+    /// knowing whether the side to move is in check.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use uci::PositionStatus;
+    ///
+    /// let mated = ["info depth 0 score mate 0", "bestmove (none)"];
+    /// assert_eq!(PositionStatus::from_search(mated.iter().cloned()), Some(PositionStatus::Checkmate));
+    /// assert_eq!(PositionStatus::from_search(vec!["bestmove e2e4"]), Some(PositionStatus::Ongoing));
+    /// assert_eq!(PositionStatus::from_search(vec!["bestmove (none)"]), None);
+    /// ```
+    pub fn from_search<'a, I>(lines: I) -> Option<PositionStatus>
+        where I: IntoIterator<Item = &'a str>
+    {
+        let mut mated = false;
+        let mut no_move = false;
+        for line in lines {
+            if let Some(info) = InfoRef::parse(line) {
+                mated |= info.score == Some(Score::Mate(0));
+            } else if line.starts_with("bestmove") {
+                no_move = matches!(line.split_whitespace().nth(1), None | Some("(none)") | Some("0000"));
+            }
+        }
+
+        if mated {
+            Some(PositionStatus::Checkmate)
+        } else if !no_move {
+            Some(PositionStatus::Ongoing)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the game is over.
+    pub fn is_over(self) -> bool {
+        self != PositionStatus::Ongoing
+    }
+}
+
+/// Reads whether the side to move is in check from the `Checkers:` line of
+/// Stockfish's `d` output.
+pub(crate) fn in_check(board: &str) -> Option<bool> {
+    board.lines()
+        .find_map(|line| line.trim().strip_prefix("Checkers:"))
+        .map(|checkers| !checkers.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_in_check() {
+        assert_eq!(in_check(" Fen: 7k/5Q2/6K1/8/8/8/8/8 b - - 0 1\n Checkers: \n"), Some(false));
+        assert_eq!(in_check(" Checkers: f7 \n"), Some(true));
+        assert_eq!(in_check("Unknown command: d"), None);
+    }
+}
@@ -0,0 +1,74 @@
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use logging::Logger;
+
+/// The last lines an engine printed on stdout and stderr, kept to explain
+/// crashes and timeouts.
+#[derive(Debug)]
+pub(crate) struct OutputTail {
+    lines: Mutex<VecDeque<String>>,
+    capacity: usize,
+}
+
+impl OutputTail {
+    pub(crate) fn new(capacity: usize) -> OutputTail {
+        OutputTail {
+            lines: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    /// Records a line, prefixed with the stream it came from.
+    pub(crate) fn push(&self, stream: &str, line: &str) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut lines = self.lines.lock().unwrap();
+        if lines.len() == self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(format!("{}: {}", stream, line.trim_end()));
+    }
+
+    /// The recorded lines, oldest first.
+    pub(crate) fn lines(&self) -> Vec<String> {
+        self.lines.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Records the lines of `stderr` from a thread of its own, until it's
+    /// closed.
+    pub(crate) fn follow<R: Read + Send + 'static>(tail: Arc<OutputTail>, stderr: R, logger: Logger)
+                                                   -> JoinHandle<()> {
+        thread::spawn(move || {
+            for line in BufReader::new(stderr).split(b'\n') {
+                let line = match line {
+                    Ok(line) => String::from_utf8_lossy(&line).into_owned(),
+                    Err(_) => break,
+                };
+                engine_log!(logger, Debug, "Engine stderr: {}", line.trim_end());
+                tail.push("stderr", &line);
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tail_capacity() {
+        let tail = OutputTail::new(2);
+        tail.push("stdout", "uciok\n");
+        tail.push("stderr", "warning: no NNUE file");
+        tail.push("stdout", "readyok\n");
+        assert_eq!(tail.lines(), vec!["stderr: warning: no NNUE file", "stdout: readyok"]);
+
+        let off = OutputTail::new(0);
+        off.push("stdout", "uciok");
+        assert!(off.lines().is_empty());
+    }
+}
@@ -0,0 +1,91 @@
+use info::InfoRef;
+use score::Score;
+
+/// What the opponent would do if the side to move passed, see
+/// [`Engine::threat`].
+///
+/// [`Engine::threat`]: ../struct.Engine.html#method.threat
+#[derive(Debug, Clone, PartialEq)]
+pub struct Threat {
+    /// The position with the opponent to move.
+    pub fen: String,
+
+    /// Score of the threat from the opponent's point of view.
+    pub score: Option<Score>,
+
+    /// The opponent's best line after the pass, starting with the threat.
+    pub line: Vec<String>,
+}
+
+impl Threat {
+    pub(crate) fn from_search(fen: String, lines: &[String]) -> Threat {
+        let best = lines.iter()
+            .rev()
+            .filter_map(|line| InfoRef::parse(line))
+            .find(|info| info.pv.is_some() && info.multipv.unwrap_or(1) == 1);
+        Threat {
+            fen,
+            score: best.as_ref().and_then(|info| info.score),
+            line: best.map(|info| info.pv_moves().map(String::from).collect()).unwrap_or_default(),
+        }
+    }
+
+    /// The threatened move, if the engine found one.
+    pub fn mv(&self) -> Option<&str> {
+        self.line.first().map(String::as_str)
+    }
+}
+
+/// Returns the position with the other side to move, as if the side to move
+/// passed.
+///
+/// The en passant square is cleared, the halfmove clock advanced, and the
+/// move number too after a pass by Black. Returns `None` if the FEN is
+/// malformed. Whether passing is legal, which it isn't in check, can't be
+/// told from the FEN alone.
+///
+/// # Examples
+///
+/// ```
+/// use uci::threat::null_move;
+///
+/// assert_eq!(null_move("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"),
+///            Some("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1 2".to_string()));
+/// ```
+pub fn null_move(fen: &str) -> Option<String> {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    if fields.len() < 4 {
+        return None;
+    }
+
+    let (side, next) = match fields[1] {
+        "w" => ("b", 0),
+        "b" => ("w", 1),
+        _ => return None,
+    };
+    let halfmoves = fields.get(4).and_then(|n| n.parse::<u32>().ok()).unwrap_or(0);
+    let fullmoves = fields.get(5).and_then(|n| n.parse::<u32>().ok()).unwrap_or(1);
+
+    Some(format!("{} {} {} - {} {}", fields[0], side, fields[2], halfmoves + 1, fullmoves + next))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_threat_from_search() {
+        let lines = vec![
+            "info depth 5 multipv 1 score cp 40 pv d1h5 g7g6".to_string(),
+            "info depth 6 multipv 2 score cp 10 pv g1f3".to_string(),
+            "info depth 6 multipv 1 score mate 1 pv h5f7".to_string(),
+            "bestmove h5f7".to_string(),
+        ];
+        let threat = Threat::from_search("fen".to_string(), &lines);
+
+        assert_eq!(threat.mv(), Some("h5f7"));
+        assert_eq!(threat.score, Some(Score::Mate(1)));
+        assert_eq!(null_move("8/8/8/8/8/8/8/K1k5 w - - 0 7").unwrap(), "8/8/8/8/8/8/8/K1k5 b - - 1 7");
+        assert_eq!(null_move("8/8/8/8"), None);
+    }
+}
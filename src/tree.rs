@@ -0,0 +1,256 @@
+use error::Result;
+use quality::AnalysisLimits;
+#[cfg(feature = "pgn")]
+use review::numbered;
+use review::pgn_eval;
+use score::Score;
+use Engine;
+
+/// A position of an [`AnalysisTree`], reached by a candidate move of its
+/// parent.
+///
+/// [`AnalysisTree`]: struct.AnalysisTree.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeNode {
+    /// The move leading here, in Coordinate notation. `None` at the root.
+    pub mv: Option<String>,
+
+    /// Score of the move for the side that played it, as found when
+    /// analysing the parent.
+    pub score: Option<Score>,
+
+    /// Depth the position was searched to. `None` for the leaves, which
+    /// aren't searched.
+    pub depth: Option<u32>,
+
+    /// Best replies, ordered by rank.
+    pub children: Vec<TreeNode>,
+}
+
+impl TreeNode {
+    fn new(mv: Option<String>, score: Option<Score>) -> TreeNode {
+        TreeNode { mv, score, depth: None, children: vec![] }
+    }
+
+    /// Follows the moves from this node.
+    pub fn find(&self, moves: &[&str]) -> Option<&TreeNode> {
+        match moves.split_first() {
+            None => Some(self),
+            Some((mv, rest)) => self.children.iter()
+                .find(|child| child.mv.as_deref() == Some(*mv))
+                .and_then(|child| child.find(rest)),
+        }
+    }
+
+    /// Number of nodes below this one.
+    pub fn descendants(&self) -> usize {
+        self.children.iter().map(|child| 1 + child.descendants()).sum()
+    }
+}
+
+/// The top engine lines of a position, expanded move by move, as used when
+/// preparing openings.
+///
+/// # Examples
+///
+/// ```
+/// use uci::tree::AnalysisTree;
+///
+/// let engine = uci::Engine::new("stockfish").unwrap();
+/// let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+/// let tree = AnalysisTree::build(&engine, fen, 2, 2, 100).unwrap();
+///
+/// for first in &tree.root.children {
+///     println!("{:?} {:?}", first.mv, first.score);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnalysisTree {
+    /// The root position, in FEN notation.
+    pub fen: String,
+
+    pub root: TreeNode,
+}
+
+impl AnalysisTree {
+    /// Builds the tree by analysing every position and expanding its best
+    /// moves, down to the given number of plies.
+    ///
+    /// The tree holds `width + width^2 + ... + width^plies` moves, each
+    /// position searched once, so keep both small. The engine's position and
+    /// `MultiPV` option are changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine`   - The engine to analyse with.
+    /// * `fen`      - The root position, in FEN notation.
+    /// * `plies`    - How many moves deep to expand.
+    /// * `width`    - How many candidate moves to expand in every position.
+    /// * `movetime` - Search time for every position, in milliseconds.
+    pub fn build(engine: &Engine, fen: &str, plies: u32, width: u32, movetime: u32)
+                 -> Result<AnalysisTree> {
+        let limits = AnalysisLimits { movetime, multipv: width.max(1) };
+        let mut root = TreeNode::new(None, None);
+        expand(engine, fen, &mut vec![], &mut root, plies, &limits)?;
+        Ok(AnalysisTree { fen: fen.to_string(), root })
+    }
+
+    /// Formats the tree as a PGN game starting at the root position. The
+    /// best line is the main line, other candidates are variations, and every
+    /// move carries its evaluation as an `[%eval]` comment.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use uci::Score;
+    /// use uci::tree::{AnalysisTree, TreeNode};
+    ///
+    /// let node = |mv: &str, cp, children| TreeNode {
+    ///     mv: Some(mv.to_string()),
+    ///     score: Some(Score::Cp(cp)),
+    ///     depth: None,
+    ///     children,
+    /// };
+    /// let tree = AnalysisTree {
+    ///     fen: "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1".to_string(),
+    ///     root: TreeNode {
+    ///         mv: None,
+    ///         score: None,
+    ///         depth: Some(20),
+    ///         children: vec![node("e2e4", 30, vec![node("c7c5", -30, vec![])]),
+    ///                        node("d2d4", 25, vec![])],
+    ///     },
+    /// };
+    ///
+    /// assert!(tree.to_pgn().ends_with(
+    ///     "1. e2e4 { [%eval 0.30] } (1. d2d4 { [%eval 0.25] }) 1... c7c5 { [%eval 0.30] } *\n"));
+    /// ```
+    #[cfg(feature = "pgn")]
+    pub fn to_pgn(&self) -> String {
+        let mut fields = self.fen.split_whitespace().skip(1);
+        let white = fields.next() != Some("b");
+        let number = fields.nth(3).and_then(|n| n.parse().ok()).unwrap_or(1);
+
+        let mut tokens = vec![];
+        write_children(&self.root, white, number, true, &mut tokens);
+        tokens.push("*".to_string());
+        let movetext = tokens.join(" ").replace("( ", "(").replace(" )", ")");
+
+        format!("[Event \"Analysis\"]\n[SetUp \"1\"]\n[FEN \"{}\"]\n\n{}\n", self.fen, movetext)
+    }
+
+    /// Formats the tree as a Graphviz graph. Positions are labeled with
+    /// the evaluation of the move reaching them, from White's point of view,
+    /// and the depth they were searched to; edges with the moves.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use uci::tree::AnalysisTree;
+    ///
+    /// let engine = uci::Engine::new("stockfish").unwrap();
+    /// let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    /// let dot = AnalysisTree::build(&engine, fen, 2, 2, 100).unwrap().to_dot();
+    /// assert!(dot.starts_with("digraph"));
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let white = self.fen.split_whitespace().nth(1) != Some("b");
+        let mut out = String::from("digraph analysis {\n    node [shape=box];\n");
+        let mut next_id = 0;
+        write_dot(&self.root, white, &mut next_id, &mut out);
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Writes a node and, below it, its replies. Returns the node's id.
+fn write_dot(node: &TreeNode, white: bool, next_id: &mut usize, out: &mut String) -> usize {
+    let id = *next_id;
+    *next_id += 1;
+
+    let mut label = match node.score {
+        // The score is the mover's, who isn't the side to move here.
+        Some(score) => pgn_eval(if white { score.negate() } else { score }),
+        None => "root".to_string(),
+    };
+    if let Some(depth) = node.depth {
+        label.push_str(&format!("\\ndepth {}", depth));
+    }
+    out.push_str(&format!("    n{} [label=\"{}\"];\n", id, label));
+
+    for child in &node.children {
+        let child_id = write_dot(child, !white, next_id, out);
+        out.push_str(&format!("    n{} -> n{} [label=\"{}\"];\n",
+                              id, child_id, child.mv.as_deref().unwrap_or("")));
+    }
+    id
+}
+
+/// Writes the replies of a node: the best one, the others as variations, then
+/// the line continuing from the best one.
+#[cfg(feature = "pgn")]
+fn write_children(node: &TreeNode, white: bool, number: u32, force_number: bool,
+                  tokens: &mut Vec<String>) {
+    let (main, others) = match node.children.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+    let next = if white { number } else { number + 1 };
+
+    write_move(main, white, number, force_number, tokens);
+    for other in others {
+        tokens.push("(".to_string());
+        write_move(other, white, number, true, tokens);
+        write_children(other, !white, next, false, tokens);
+        tokens.push(")".to_string());
+    }
+    write_children(main, !white, next, !others.is_empty(), tokens);
+}
+
+#[cfg(feature = "pgn")]
+fn write_move(node: &TreeNode, white: bool, number: u32, force_number: bool,
+              tokens: &mut Vec<String>) {
+    let mv = node.mv.as_deref().unwrap_or("--");
+    tokens.push(if white || force_number { numbered(number, white, mv) } else { mv.to_string() });
+    if let Some(score) = node.score {
+        let score = if white { score } else { score.negate() };
+        tokens.push(format!("{{ [%eval {}] }}", pgn_eval(score)));
+    }
+}
+
+fn expand(engine: &Engine, fen: &str, path: &mut Vec<String>, node: &mut TreeNode,
+          plies: u32, limits: &AnalysisLimits) -> Result<()> {
+    if plies == 0 {
+        return Ok(());
+    }
+
+    engine.make_moves_from_position(fen, path)?;
+    let overlay = engine.analyze(limits)?;
+    node.depth = overlay.depth;
+    for candidate in overlay.candidates.into_iter().take(limits.multipv as usize) {
+        let mut child = TreeNode::new(Some(candidate.mv.clone()), Some(candidate.score));
+        path.push(candidate.mv);
+        expand(engine, fen, path, &mut child, plies - 1, limits)?;
+        path.pop();
+        node.children.push(child);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find() {
+        let mut root = TreeNode::new(None, None);
+        let mut e4 = TreeNode::new(Some("e2e4".to_string()), Some(Score::Cp(30)));
+        e4.children.push(TreeNode::new(Some("c7c5".to_string()), Some(Score::Cp(-25))));
+        root.children.push(e4);
+        root.children.push(TreeNode::new(Some("d2d4".to_string()), Some(Score::Cp(25))));
+
+        assert_eq!(root.descendants(), 3);
+        assert_eq!(root.find(&["e2e4", "c7c5"]).unwrap().score, Some(Score::Cp(-25)));
+        assert!(root.find(&["d2d4", "d7d5"]).is_none());
+    }
+}
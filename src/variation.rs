@@ -0,0 +1,50 @@
+use overlay::Candidate;
+use score::Score;
+
+/// One of the lines of a MultiPV search, see [`Engine::variations`].
+///
+/// [`Engine::variations`]: struct.Engine.html#method.variations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variation {
+    /// Rank of the line, as reported by `multipv`. 1 is the best line.
+    pub rank: u32,
+
+    /// Score of the line, from the point of view of the side to move.
+    pub score: Score,
+
+    /// The moves of the line, in Coordinate notation.
+    pub pv: Vec<String>,
+
+    /// Depth the line was searched to.
+    pub depth: Option<u32>,
+}
+
+impl From<Candidate> for Variation {
+    fn from(candidate: Candidate) -> Variation {
+        Variation {
+            rank: candidate.rank,
+            score: candidate.score,
+            pv: candidate.pv,
+            depth: candidate.depth,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Engine;
+
+    #[test]
+    fn test_variations() {
+        let engine = Engine::new("./stockfish").unwrap();
+        engine.multipv(3).unwrap();
+        let variations = engine.variations().unwrap();
+
+        assert_eq!(variations.len(), 3);
+        assert_eq!(variations.iter().map(|v| v.rank).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(variations[0].pv[0], "e2e4");
+        assert_eq!(variations[1].score, Score::Cp(10));
+        assert_eq!(variations[2].depth, Some(5));
+    }
+}
@@ -0,0 +1,70 @@
+use score::Score;
+
+/// Whether a move holds up against the engine's choice, with the evidence,
+/// see [`Engine::verify_move`].
+///
+/// [`Engine::verify_move`]: struct.Engine.html#method.verify_move
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoveVerdict {
+    /// The move checked, in Coordinate notation.
+    pub mv: String,
+
+    /// Score of the move for the side to move. `None` if the engine didn't
+    /// report one or wouldn't play the move, as for an illegal move.
+    pub score: Option<Score>,
+
+    /// The engine's own choice.
+    pub bestmove: String,
+
+    /// Score of the engine's choice for the side to move.
+    pub best_score: Option<Score>,
+
+    /// Depth the engine's choice was searched to.
+    pub depth: Option<u32>,
+
+    /// Centipawns the move loses compared to the engine's choice, mates
+    /// counting as 1000. `None` if either score is missing.
+    pub loss: Option<i32>,
+
+    /// Whether the move loses no more than the margin. Always true when it
+    /// is the engine's choice, never when its score is missing.
+    pub accepted: bool,
+}
+
+impl MoveVerdict {
+    pub(crate) fn new(mv: &str, score: Option<Score>, bestmove: &str, best_score: Option<Score>,
+                      depth: Option<u32>, margin: i32) -> MoveVerdict {
+        let loss = match (score, best_score) {
+            (Some(score), Some(best)) => Some((best.centipawns() - score.centipawns()).max(0)),
+            _ => None,
+        };
+        MoveVerdict {
+            mv: mv.to_string(),
+            score,
+            bestmove: bestmove.to_string(),
+            best_score,
+            depth,
+            loss,
+            accepted: mv == bestmove || loss.is_some_and(|loss| loss <= margin),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verdict() {
+        let close = MoveVerdict::new("d2d4", Some(Score::Cp(20)), "e2e4", Some(Score::Cp(35)), Some(20), 30);
+        assert_eq!(close.loss, Some(15));
+        assert!(close.accepted);
+
+        let blunder = MoveVerdict::new("g2g4", Some(Score::Mate(-3)), "e2e4", Some(Score::Cp(35)), None, 30);
+        assert_eq!(blunder.loss, Some(1035));
+        assert!(!blunder.accepted);
+
+        let illegal = MoveVerdict::new("e2e5", None, "e2e4", Some(Score::Cp(35)), None, 30);
+        assert!(!illegal.accepted);
+    }
+}
@@ -0,0 +1,75 @@
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+use connection::Connection;
+use logging::Logger;
+
+/// What the watchdog does to an engine that is still busy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Action {
+    /// Sends `stop`, making the engine end its search with a `bestmove`.
+    Stop,
+
+    /// Kills the engine process.
+    Kill,
+}
+
+/// Acts on the engine at the given instants unless disarmed first, by
+/// dropping it, for when the engine can't be trusted to answer in time.
+pub(crate) struct Watchdog {
+    fired: Arc<Mutex<Option<Action>>>,
+    disarm: Option<mpsc::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Watchdog {
+    /// Starts the watchdog. `actions` must be ordered by instant.
+    pub(crate) fn arm(child: Arc<Mutex<Connection>>, logger: Logger, actions: Vec<(Instant, Action)>)
+                      -> Watchdog {
+        let (disarm, disarmed) = mpsc::channel::<()>();
+        let fired = Arc::new(Mutex::new(None));
+        let last_fired = fired.clone();
+        let thread = thread::spawn(move || {
+            for (at, action) in actions {
+                let left = at.saturating_duration_since(Instant::now());
+                if disarmed.recv_timeout(left) != Err(mpsc::RecvTimeoutError::Timeout) {
+                    return;
+                }
+
+                let mut child = child.lock().unwrap();
+                *last_fired.lock().unwrap() = Some(action);
+                match action {
+                    Action::Stop => {
+                        engine_log!(logger, Warn, "Engine didn't answer in time, sending stop");
+                        let _ = child.input().write_all(b"stop\n");
+                    }
+                    Action::Kill => {
+                        engine_log!(logger, Warn, "Engine didn't answer in time, killing it");
+                        let _ = child.kill();
+                    }
+                }
+            }
+        });
+
+        Watchdog {
+            fired,
+            disarm: Some(disarm),
+            thread: Some(thread),
+        }
+    }
+
+    /// The last action taken so far, if any.
+    pub(crate) fn fired(&self) -> Option<Action> {
+        *self.fired.lock().unwrap()
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.disarm.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Directories created so far by this process, for unique names.
+static CREATED: AtomicUsize = AtomicUsize::new(0);
+
+/// A temporary working directory for an engine process, removed with
+/// everything in it when dropped.
+#[derive(Debug)]
+pub(crate) struct WorkDir {
+    path: PathBuf,
+}
+
+impl WorkDir {
+    /// Creates a new, empty directory in the system's temporary directory.
+    pub(crate) fn create() -> io::Result<WorkDir> {
+        loop {
+            let n = CREATED.fetch_add(1, Ordering::SeqCst);
+            let path = std::env::temp_dir().join(format!("uci-engine-{}-{}", process::id(), n));
+            match fs::create_dir(&path) {
+                Ok(()) => return Ok(WorkDir { path }),
+                // Left over by an earlier process with the same id.
+                Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for WorkDir {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.path) {
+            warn!("Working directory {} could not be removed: {}", self.path.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use EngineBuilder;
+
+    #[test]
+    fn test_workdir_cleanup() {
+        let dir = WorkDir::create().unwrap();
+        let other = WorkDir::create().unwrap();
+        assert_ne!(dir.path(), other.path());
+
+        fs::write(dir.path().join("learn.bin"), b"data").unwrap();
+        let path = dir.path().to_path_buf();
+        drop(dir);
+        assert!(!path.exists());
+
+        let engine = EngineBuilder::new("./stockfish").temp_dir(true).build().unwrap();
+        engine.bestmove().unwrap();
+    }
+}
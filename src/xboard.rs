@@ -0,0 +1,573 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// How long an engine gets to announce its features before the handshake
+/// goes on without them, as the protocol suggests.
+const FEATURE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Types of the options xboard engines declare, as in `-spin`.
+const OPTION_TYPES: [&str; 9] = ["button", "save", "check", "spin", "slider", "string", "file", "path", "combo"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Handshake {
+    NotStarted,
+    Waiting,
+
+    /// The engine sent `feature done=0`, asking for more time.
+    Held,
+    Done,
+}
+
+/// What the engine is doing, as far as the adapter knows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Search {
+    Idle,
+    Go,
+    Analyze,
+
+    /// Analysing for a ponder search, with the limits to search with on
+    /// `ponderhit`.
+    Ponder(Vec<String>),
+}
+
+/// State shared by both directions of the translation.
+struct Adapter {
+    engine: Box<dyn Write + Send>,
+
+    /// Where translated output goes, until the engine closes its output.
+    output: Option<Sender<String>>,
+    handshake: Handshake,
+    name: Option<String>,
+
+    /// Declared options, as `option` lines, with their xboard types.
+    options: Vec<(String, String, String)>,
+    ping: bool,
+    pings: u32,
+    usermove: bool,
+    search: Search,
+    white_to_move: bool,
+    last_pv: Vec<String>,
+}
+
+impl Adapter {
+    fn send(&mut self, command: &str) -> io::Result<()> {
+        self.engine.write_all(command.as_bytes())?;
+        self.engine.write_all(b"\n")?;
+        self.engine.flush()
+    }
+
+    fn emit(&self, line: String) {
+        if let Some(ref output) = self.output {
+            let _ = output.send(line);
+        }
+    }
+
+    /// Answers `uci` with what the engine announced.
+    fn finish_handshake(&mut self) {
+        self.handshake = Handshake::Done;
+        self.emit(format!("id name {}", self.name.as_deref().unwrap_or("xboard engine")));
+        for (_, _, line) in &self.options {
+            self.emit(line.clone());
+        }
+        self.emit("uciok".to_string());
+        let _ = self.send("easy");
+    }
+
+    /// Translates a UCI command to xboard commands, answering those xboard
+    /// has no equivalent for right away.
+    fn command(&mut self, adapter: &Arc<Mutex<Adapter>>, line: &str) -> io::Result<()> {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("uci") if self.handshake == Handshake::Done => self.finish_handshake(),
+            Some("uci") => {
+                self.handshake = Handshake::Waiting;
+                self.send("xboard")?;
+                self.send("protover 2")?;
+                let adapter = adapter.clone();
+                thread::spawn(move || {
+                    thread::sleep(FEATURE_TIMEOUT);
+                    let mut adapter = adapter.lock().unwrap();
+                    if adapter.handshake == Handshake::Waiting {
+                        adapter.finish_handshake();
+                    }
+                });
+            }
+            Some("isready") if self.ping => {
+                self.pings += 1;
+                let ping = format!("ping {}", self.pings);
+                self.send(&ping)?;
+            }
+            Some("isready") => self.emit("readyok".to_string()),
+            Some("ucinewgame") => self.send("new")?,
+            Some("position") => self.position(&words.collect::<Vec<_>>())?,
+            Some("go") => self.go(&words.collect::<Vec<_>>())?,
+            Some("stop") => self.stop()?,
+            Some("ponderhit") => {
+                if let Search::Ponder(limits) = self.search.clone() {
+                    self.send("exit")?;
+                    self.start(&limits)?;
+                }
+            }
+            Some("setoption") => self.set_option(line)?,
+            Some("debug") | Some("register") | None => {}
+            Some(_) => self.send(line)?,
+        }
+        Ok(())
+    }
+
+    fn position(&mut self, words: &[&str]) -> io::Result<()> {
+        let split = words.iter().position(|&w| w == "moves").unwrap_or(words.len());
+        let (setup, moves) = (&words[..split], words.get(split + 1..).unwrap_or(&[]));
+
+        self.send("new")?;
+        self.send("force")?;
+        self.white_to_move = true;
+        if setup.first() == Some(&"fen") {
+            let fen = setup[1..].join(" ");
+            self.white_to_move = setup.get(2) != Some(&"b");
+            self.send(&format!("setboard {}", fen))?;
+        }
+        for mv in moves {
+            let mv = if self.usermove { format!("usermove {}", mv) } else { mv.to_string() };
+            self.send(&mv)?;
+            self.white_to_move = !self.white_to_move;
+        }
+        self.search = Search::Idle;
+        Ok(())
+    }
+
+    fn go(&mut self, words: &[&str]) -> io::Result<()> {
+        let value = |name: &str| -> Option<u64> {
+            words.iter().position(|&w| w == name)
+                .and_then(|i| words.get(i + 1))
+                .and_then(|v| v.parse().ok())
+        };
+        let (own, other, increment) = if self.white_to_move {
+            (value("wtime"), value("btime"), value("winc"))
+        } else {
+            (value("btime"), value("wtime"), value("binc"))
+        };
+
+        let mut limits = vec![];
+        if let Some(depth) = value("depth") {
+            limits.push(format!("sd {}", depth));
+        }
+        if let Some(movetime) = value("movetime") {
+            limits.push(format!("st {}", movetime.div_ceil(1000).max(1)));
+        } else if let Some(own) = own {
+            let increment = increment.unwrap_or(0) as f64 / 1000.0;
+            limits.push(format!("level {} {}:{:02} {}",
+                                value("movestogo").unwrap_or(0), own / 60_000, own / 1000 % 60, increment));
+            limits.push(format!("time {}", own / 10));
+            if let Some(other) = other {
+                limits.push(format!("otim {}", other / 10));
+            }
+        }
+
+        self.last_pv.clear();
+        if words.contains(&"ponder") {
+            self.send("post")?;
+            self.send("analyze")?;
+            self.search = Search::Ponder(limits);
+        } else if words.contains(&"infinite") {
+            self.send("post")?;
+            self.send("analyze")?;
+            self.search = Search::Analyze;
+        } else {
+            self.start(&limits)?;
+        }
+        Ok(())
+    }
+
+    /// Makes the engine play the side to move within `limits`.
+    fn start(&mut self, limits: &[String]) -> io::Result<()> {
+        for limit in limits {
+            self.send(limit)?;
+        }
+        self.send("post")?;
+        self.send("go")?;
+        self.search = Search::Go;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> io::Result<()> {
+        match self.search {
+            Search::Go => self.send("?"),
+            Search::Analyze | Search::Ponder(_) => {
+                self.send("exit")?;
+                self.search = Search::Idle;
+                let bestmove = match (self.last_pv.first(), self.last_pv.get(1)) {
+                    (Some(mv), Some(ponder)) => format!("bestmove {} ponder {}", mv, ponder),
+                    (Some(mv), None) => format!("bestmove {}", mv),
+                    (None, _) => "bestmove (none)".to_string(),
+                };
+                self.emit(bestmove);
+                Ok(())
+            }
+            Search::Idle => Ok(()),
+        }
+    }
+
+    /// Sets an option the engine declared. `Hash` and `Threads` are set
+    /// with the commands xboard has for them.
+    fn set_option(&mut self, line: &str) -> io::Result<()> {
+        let rest = line.trim_start_matches("setoption").trim();
+        let rest = rest.strip_prefix("name").unwrap_or(rest).trim();
+        let (name, value) = match rest.find(" value ") {
+            Some(i) => (&rest[..i], rest[i + 7..].trim()),
+            None => (rest, ""),
+        };
+
+        let kind = self.options.iter()
+            .find(|(declared, _, _)| declared.eq_ignore_ascii_case(name))
+            .map(|(_, kind, _)| kind.clone());
+        let command = match kind.as_deref() {
+            Some("button") | Some("save") => format!("option {}", name),
+            Some("check") => format!("option {}={}", name, if value == "true" { 1 } else { 0 }),
+            Some(kind @ "memory") | Some(kind @ "cores") => format!("{} {}", kind, value),
+            Some(_) => format!("option {}={}", name, value),
+            None => return Ok(()),
+        };
+        self.send(&command)
+    }
+
+    /// Translates a line of the engine's output to the UCI lines it stands
+    /// for. Lines without an equivalent are passed on as they are.
+    fn output(&mut self, line: &str) {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("move") => {
+                if let Some(mv) = words.next() {
+                    self.search = Search::Idle;
+                    self.emit(format!("bestmove {}", mv));
+                }
+            }
+            Some("feature") => self.features(&line["feature".len()..]),
+            Some("pong") => self.emit("readyok".to_string()),
+            Some("resign") | Some("1-0") | Some("0-1") | Some("1/2-1/2") if self.search == Search::Go => {
+                self.search = Search::Idle;
+                self.emit(line.to_string());
+                self.emit("bestmove (none)".to_string());
+            }
+            _ => match thinking(line) {
+                Some((info, pv)) => {
+                    if !pv.is_empty() {
+                        self.last_pv = pv;
+                    }
+                    self.emit(info);
+                }
+                None => self.emit(line.to_string()),
+            },
+        }
+    }
+
+    fn features(&mut self, features: &str) {
+        for (name, value) in parse_features(features) {
+            // Moves in SAN can't be told apart from UCI moves without a board.
+            let accepted = !(name == "san" && value == "1");
+            let _ = self.send(&format!("{} {}", if accepted { "accepted" } else { "rejected" }, name));
+            match name.as_str() {
+                "myname" => self.name = Some(value),
+                "ping" => self.ping = value == "1",
+                "usermove" => self.usermove = value == "1",
+                "memory" if value == "1" => self.declare("Hash", "memory",
+                                                         "option name Hash type spin default 16 min 1 max 33554432"),
+                "smp" if value == "1" => self.declare("Threads", "cores",
+                                                      "option name Threads type spin default 1 min 1 max 1024"),
+                "option" => if let Some((name, kind, line)) = parse_option(&value) {
+                    self.options.push((name, kind, line));
+                },
+                "done" if value == "0" => self.handshake = Handshake::Held,
+                "done" if self.handshake != Handshake::Done => self.finish_handshake(),
+                _ => {}
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &str, kind: &str, line: &str) {
+        self.options.push((name.to_string(), kind.to_string(), line.to_string()));
+    }
+}
+
+/// Splits `key=value key="quoted value"` pairs.
+fn parse_features(s: &str) -> Vec<(String, String)> {
+    let mut features = vec![];
+    let mut rest = s.trim_start();
+    while let Some(eq) = rest.find('=') {
+        let name = rest[..eq].trim().to_string();
+        rest = &rest[eq + 1..];
+        let value = if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            rest = quoted.get(end + 1..).unwrap_or("");
+            &quoted[..end]
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let value = &rest[..end];
+            rest = &rest[end..];
+            value
+        };
+        features.push((name, value.to_string()));
+        rest = rest.trim_start();
+    }
+    features
+}
+
+/// Parses an option declared as `NAME -TYPE VALUES`, returning its name,
+/// type and the equivalent UCI `option` line.
+fn parse_option(declaration: &str) -> Option<(String, String, String)> {
+    let words: Vec<&str> = declaration.split_whitespace().collect();
+    let at = words.iter().position(|w| w.strip_prefix('-').is_some_and(|kind| OPTION_TYPES.contains(&kind)))?;
+    let name = words[..at].join(" ");
+    let kind = words[at][1..].to_string();
+    let values = &words[at + 1..];
+
+    let uci = match kind.as_str() {
+        "button" | "save" => "type button".to_string(),
+        "check" => format!("type check default {}", values.first() == Some(&"1")),
+        "spin" | "slider" if values.len() >= 3 => {
+            format!("type spin default {} min {} max {}", values[0], values[1], values[2])
+        }
+        "string" | "file" | "path" => format!("type string default {}", values.join(" ")),
+        "combo" => {
+            let choices: Vec<String> = values.join(" ").split("///").map(|c| c.trim().to_string()).collect();
+            let default = choices.iter().find(|c| c.starts_with('*')).unwrap_or(&choices[0]);
+            let vars: Vec<String> = choices.iter().map(|c| format!("var {}", c.trim_start_matches('*'))).collect();
+            format!("type combo default {} {}", default.trim_start_matches('*'), vars.join(" "))
+        }
+        _ => return None,
+    };
+    Some((name.clone(), kind, format!("option name {} {}", name, uci)))
+}
+
+/// Translates a thinking line, `DEPTH SCORE TIME NODES PV`, to an `info`
+/// line, also returning the moves of its PV. Moves that aren't in
+/// coordinate notation end the PV.
+fn thinking(line: &str) -> Option<(String, Vec<String>)> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let numbers: Vec<i64> = words.iter().take(4).map_while(|w| w.trim_end_matches(['&', '.']).parse().ok()).collect();
+    if numbers.len() < 4 {
+        return None;
+    }
+    let (depth, score, centis, nodes) = (numbers[0], numbers[1], numbers[2], numbers[3]);
+
+    // Mate in N is announced as 100000 + N.
+    let score = if score >= 100_000 {
+        format!("mate {}", score - 100_000)
+    } else if score <= -100_000 {
+        format!("mate -{}", -score - 100_000)
+    } else {
+        format!("cp {}", score)
+    };
+    let mut info = format!("info depth {} score {} time {} nodes {}", depth, score, centis * 10, nodes);
+    if centis > 0 {
+        info.push_str(&format!(" nps {}", nodes * 100 / centis));
+    }
+
+    // Newer engines may add more numbers, like the selective depth.
+    let pv: Vec<String> = words[4..].iter()
+        .skip_while(|w| w.parse::<i64>().is_ok())
+        .map_while(|w| Some(w.trim_end_matches(['+', '#', '!', '?'])).filter(|w| is_move(w)))
+        .map(str::to_string)
+        .collect();
+    if !pv.is_empty() {
+        info.push_str(" pv ");
+        info.push_str(&pv.join(" "));
+    }
+    Some((info, pv))
+}
+
+/// Whether the word is a move in coordinate notation, like `e7e8q`.
+fn is_move(word: &str) -> bool {
+    let b = word.as_bytes();
+    (b.len() == 4 || (b.len() == 5 && b"qrbn".contains(&b[4])))
+        && (b'a'..=b'h').contains(&b[0]) && (b'1'..=b'8').contains(&b[1])
+        && (b'a'..=b'h').contains(&b[2]) && (b'1'..=b'8').contains(&b[3])
+}
+
+/// Where the commands for an xboard engine are written, in UCI.
+pub(crate) struct XboardInput {
+    adapter: Arc<Mutex<Adapter>>,
+    line: Vec<u8>,
+}
+
+impl Write for XboardInput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.line.extend_from_slice(buf);
+        while let Some(end) = self.line.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.line.drain(..end + 1).collect();
+            let line = String::from_utf8_lossy(&line);
+            self.adapter.lock().unwrap().command(&self.adapter, line.trim())?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.adapter.lock().unwrap().engine.flush()
+    }
+}
+
+impl Drop for XboardInput {
+    /// Asks the engine to quit and closes its input.
+    fn drop(&mut self) {
+        let mut adapter = self.adapter.lock().unwrap();
+        let _ = adapter.send("quit");
+        adapter.engine = Box::new(io::sink());
+    }
+}
+
+/// Where the output of an xboard engine is read from, in UCI.
+pub(crate) struct XboardOutput {
+    lines: Receiver<String>,
+    line: Vec<u8>,
+    read: usize,
+}
+
+impl Read for XboardOutput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.read == self.line.len() {
+            match self.lines.recv() {
+                Ok(line) => {
+                    self.line = line.into_bytes();
+                    self.line.push(b'\n');
+                    self.read = 0;
+                }
+                // The engine closed its output.
+                Err(_) => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.line.len() - self.read);
+        buf[..n].copy_from_slice(&self.line[self.read..self.read + n]);
+        self.read += n;
+        Ok(n)
+    }
+}
+
+/// Wraps the input and output of an engine speaking the xboard protocol
+/// (CECP) into streams speaking UCI.
+///
+/// Positions are set up with `new`, `force` and `setboard`, searches with a
+/// time or depth limit are played with `go`, and infinite and ponder
+/// searches become `analyze` sessions, ended by `exit`. Options the engine
+/// declares with `feature option` are offered as UCI options, as are `Hash`
+/// and `Threads` for engines with the `memory` and `smp` features.
+pub(crate) fn adapt<W, R>(input: W, output: R) -> (XboardInput, XboardOutput)
+    where W: Write + Send + 'static,
+          R: Read + Send + 'static
+{
+    let (sender, lines) = mpsc::channel();
+    let adapter = Arc::new(Mutex::new(Adapter {
+        engine: Box::new(input),
+        output: Some(sender),
+        handshake: Handshake::NotStarted,
+        name: None,
+        options: vec![],
+        ping: false,
+        pings: 0,
+        usermove: false,
+        search: Search::Idle,
+        white_to_move: true,
+        last_pv: vec![],
+    }));
+
+    let reader = adapter.clone();
+    thread::spawn(move || {
+        for line in BufReader::new(output).split(b'\n') {
+            let line = match line {
+                Ok(line) => String::from_utf8_lossy(&line).trim().to_string(),
+                Err(_) => break,
+            };
+            reader.lock().unwrap().output(&line);
+        }
+        reader.lock().unwrap().output = None;
+    });
+
+    (XboardInput { adapter, line: vec![] }, XboardOutput { lines, line: vec![], read: 0 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records what's written to it.
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Recorder {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_xboard_adapter() {
+        let script = "feature myname=\"Fairy 1.0\" ping=1 usermove=1 san=1\n\
+                      feature option=\"Style -combo Solid /// *Normal\" memory=1 done=1\n\
+                      pong 1\n\
+                      3 25 12 4000 e2e4 e7e5 Nf3\n\
+                      7 100004 50 90000 d1h5\n\
+                      move e2e4\n";
+        let sent = Recorder::default();
+        let (mut input, output) = adapt(sent.clone(), io::Cursor::new(script));
+
+        let lines: Vec<String> = BufReader::new(output).lines().map(|line| line.unwrap()).collect();
+        assert_eq!(lines, vec![
+            "id name Fairy 1.0",
+            "option name Style type combo default Normal var Solid var Normal",
+            "option name Hash type spin default 16 min 1 max 33554432",
+            "uciok",
+            "readyok",
+            "info depth 3 score cp 25 time 120 nodes 4000 nps 33333 pv e2e4 e7e5",
+            "info depth 7 score mate 4 time 500 nodes 90000 nps 180000 pv d1h5",
+            "bestmove e2e4",
+        ]);
+
+        input.write_all(b"isready\nsetoption name Hash value 64\nsetoption name Style value Solid\n").unwrap();
+        input.write_all(b"position fen 8/8/8/8/8/8/8/K1k5 b - - 0 1 moves c1c2\n").unwrap();
+        input.write_all(b"go wtime 61000 btime 30000 winc 1000\nstop\n").unwrap();
+        drop(input);
+
+        let sent = String::from_utf8(sent.0.lock().unwrap().clone()).unwrap();
+        assert!(sent.contains("accepted usermove\nrejected san\n"));
+        let commands: Vec<&str> = sent.lines()
+            .filter(|line| !line.starts_with("accepted") && !line.starts_with("rejected"))
+            .collect();
+        assert_eq!(commands, vec![
+            "easy",
+            "ping 1",
+            "memory 64",
+            "option Style=Solid",
+            "new",
+            "force",
+            "setboard 8/8/8/8/8/8/8/K1k5 b - - 0 1",
+            "usermove c1c2",
+            "level 0 1:01 1",
+            "time 6100",
+            "otim 3000",
+            "post",
+            "go",
+            "?",
+            "quit",
+        ]);
+    }
+
+    #[test]
+    fn test_parse_features() {
+        assert_eq!(parse_features(" ping=1 myname=\"Fairy Max\" done=1"), vec![
+            ("ping".to_string(), "1".to_string()),
+            ("myname".to_string(), "Fairy Max".to_string()),
+            ("done".to_string(), "1".to_string()),
+        ]);
+        assert_eq!(parse_option("Resign Threshold -spin 800 100 1000").unwrap().2,
+                   "option name Resign Threshold type spin default 800 min 100 max 1000");
+        assert_eq!(parse_option("Ponder -check 1").unwrap().2, "option name Ponder type check default true");
+        assert!(thinking("Illegal move: e2e5").is_none());
+    }
+}